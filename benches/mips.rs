@@ -0,0 +1,121 @@
+// Builds `n` copies of the MIPS example in a single Sim and times wiring them
+// up and running them, to see how construction and propagation scale with the
+// size of the circuit. The copies share nothing but the clock, so they also
+// show what running them on threads gains. Building with the linear-scan
+// feature times the lookup of the pins of a net from before nets kept their
+// member lists, for comparison.
+//
+//     cargo bench --bench mips
+//     cargo bench --bench mips --features linear-scan
+
+use sim_rs::{
+    components::{
+        cpu::Mips,
+        logic::{And, Not},
+        mem::Ram,
+    },
+    Sim,
+};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+const SIZES: [usize; 5] = [1, 2, 4, 8, 16];
+const TICKS: usize = 1000;
+
+const PROGRAM: [u32; 30] = [
+    0x20040002, 0x20050002, 0x201d0080, 0x0c100008, 0xac020000, 0x2008ffff, 0xad000000, 0x08100007,
+    0x23bdfff8, 0xafb00004, 0xafbf0000, 0x14800002, 0x20a20001, 0x0810001a, 0x14a00004, 0x2084ffff,
+    0x20050001, 0x0c100008, 0x0810001a, 0x00808020, 0x20a5ffff, 0x0c100008, 0x2204ffff, 0x00402820,
+    0x0c100008, 0x0810001a, 0x8fb00004, 0x8fbf0000, 0x23bd0008, 0x03e00008,
+];
+
+fn range(start: usize, end: usize) -> Vec<usize> {
+    (start..=end).collect()
+}
+
+fn add_mips(s: &mut Sim) {
+    let mut instr = [0; 1 << 8];
+    instr[0..30].copy_from_slice(&PROGRAM);
+
+    let cpu = s.add_component(Mips::new());
+    let instr_ram = s.add_component(Ram::new(&instr));
+    let data_ram = s.add_component(Ram::new(&[0; 32]));
+    let not = s.add_component(Not);
+    let and = s.add_component(And);
+
+    s.connect(not, 1, cpu, 96);
+    s.connect_to_clk(and, 1);
+    s.connect(and, 2, not, 2);
+    s.connect(and, 3, cpu, 130);
+
+    s.connect_bulk(cpu, &range(3, 10), instr_ram, &range(1, 8));
+    s.connect_bulk(cpu, &range(33, 64), instr_ram, &range(33, 64));
+
+    s.connect_bulk(cpu, &range(67, 71), data_ram, &range(1, 5));
+    s.connect_bulk(cpu, &range(97, 128), data_ram, &range(33, 64));
+    s.connect(cpu, 129, data_ram, 65);
+}
+
+fn build(n: usize, batched: bool) -> (Sim, Duration) {
+    let start = Instant::now();
+    let mut s = Sim::new();
+
+    if batched {
+        s.begin_build();
+    }
+    for _ in 0..n {
+        add_mips(&mut s);
+    }
+    if batched {
+        s.finish_build();
+    }
+
+    (s, start.elapsed())
+}
+
+fn run(s: &mut Sim) -> Duration {
+    let start = Instant::now();
+    for _ in 0..TICKS {
+        s.tick();
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    if cfg!(feature = "linear-scan") {
+        println!("nets looked up by scanning every pin");
+    }
+
+    println!(
+        "{:>6} {:>14} {:>14} {:>14} {:>12} {:>14}",
+        "cpus",
+        "build (ms)",
+        "batched (ms)",
+        "run (ms)",
+        "ticks/s",
+        format!("{} thr (ms)", threads)
+    );
+
+    for &n in &SIZES {
+        let (_, unbatched) = build(n, false);
+        let (mut s, batched) = build(n, true);
+        let single = run(&mut s);
+
+        let (mut s, _) = build(n, true);
+        s.set_threads(threads);
+        let threaded = run(&mut s);
+
+        println!(
+            "{:>6} {:>14.2} {:>14.2} {:>14.2} {:>12.0} {:>14.2}",
+            n,
+            unbatched.as_secs_f64() * 1e3,
+            batched.as_secs_f64() * 1e3,
+            single.as_secs_f64() * 1e3,
+            TICKS as f64 / single.as_secs_f64(),
+            threaded.as_secs_f64() * 1e3
+        );
+    }
+}
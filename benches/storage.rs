@@ -0,0 +1,87 @@
+// Wires up generated gate netlists of growing size with each graph storage
+// backend, comparing construction time and the memory held by the edges. The
+// k2 tree, taking seconds for 10^3 gates, stops there.
+//
+//     cargo bench --bench storage
+
+use sim_rs::{
+    components::logic::{And, Not, Xor},
+    storage::{AdjacencyList, K2Tree, Storage},
+    Sim,
+};
+use std::time::Instant;
+
+const SIZES: [usize; 4] = [100, 1000, 10_000, 100_000];
+
+// xorshift, so every backend gets the same netlist
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+// `gates` gates whose inputs are driven by random earlier gates
+fn netlist<S: 'static + Storage>(storage: S, gates: usize) -> Sim {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let mut s = Sim::with_storage(storage);
+    s.begin_build();
+
+    let not = s.add_component(Not);
+    s.connect_to_clk(not, 1);
+    let mut outputs = vec![(not, 2)];
+
+    for i in 1..gates {
+        let g = if i % 2 == 0 {
+            s.add_component(And)
+        } else {
+            s.add_component(Xor)
+        };
+
+        for pin in 1..=2 {
+            let (src, out) = outputs[rng.below(outputs.len())];
+            s.connect(g, pin, src, out);
+        }
+        outputs.push((g, 3));
+    }
+
+    s.finish_build();
+    s
+}
+
+fn run<S: 'static + Storage>(name: &str, storage: impl Fn() -> S, max: usize) {
+    for &n in SIZES.iter().filter(|&&n| n <= max) {
+        let start = Instant::now();
+        let mut s = netlist(storage(), n);
+        let build = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..10 {
+            s.tick();
+        }
+        let ticks = start.elapsed();
+
+        println!(
+            "{:>14} {:>8} {:>12.2} {:>12.2} {:>12}",
+            name,
+            n,
+            build.as_secs_f64() * 1e3,
+            ticks.as_secs_f64() * 1e3,
+            s.storage_size()
+        );
+    }
+}
+
+fn main() {
+    println!(
+        "{:>14} {:>8} {:>12} {:>12} {:>12}",
+        "storage", "gates", "build (ms)", "10 ticks", "edge bytes"
+    );
+
+    run("k2 tree", K2Tree::new, 1000);
+    run("adjacency list", AdjacencyList::new, usize::MAX);
+}
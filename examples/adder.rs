@@ -0,0 +1,73 @@
+use sim_rs::{
+    components::logic::{And, Or, Xor},
+    Sim,
+};
+use std::time::Instant;
+
+// (a, b, cin, sum, cout) pins of a full adder
+type Pin = (usize, usize);
+
+fn full_adder(s: &mut Sim) -> [Pin; 5] {
+    let x1 = s.add_component(Xor);
+    let a1 = s.add_component(And);
+    let x2 = s.add_component(Xor);
+    let a2 = s.add_component(And);
+    let or = s.add_component(Or);
+
+    s.connect(x1, 1, a1, 1);
+    s.connect(x1, 2, a1, 2);
+    s.connect(x1, 3, x2, 1);
+    s.connect(x2, 1, a2, 1);
+    s.connect(x2, 2, a2, 2);
+    s.connect(a2, 3, or, 1);
+    s.connect(a1, 3, or, 2);
+
+    [(x1, 1), (x1, 2), (x2, 2), (x2, 3), (or, 3)]
+}
+
+fn main() {
+    let mut s = Sim::new();
+
+    s.begin_build();
+    let adders = (0..8).map(|_| full_adder(&mut s)).collect::<Vec<_>>();
+    for w in adders.windows(2) {
+        let ((c1, p1), (c2, p2)) = (w[0][4], w[1][2]);
+        s.connect(c1, p1, c2, p2);
+    }
+    s.finish_build();
+
+    // every combination of a, b and cin, 64 of them per pass
+    let start = Instant::now();
+    let mut p = s.parallel();
+    for pass in 0..(1 << 17) / 64 {
+        let pattern =
+            |bit: usize| (0..64).fold(0, |acc, i| acc | (((pass * 64 + i) >> bit & 1) as u64) << i);
+
+        for (i, f) in adders.iter().enumerate() {
+            p.write(f[0].0, f[0].1, pattern(i));
+            p.write(f[1].0, f[1].1, pattern(8 + i));
+        }
+        p.write(adders[0][2].0, adders[0][2].1, pattern(16));
+        p.run();
+
+        for i in 0..64 {
+            let n = pass * 64 + i;
+            let expected = (n & 0xff) + (n >> 8 & 0xff) + (n >> 16);
+            let bit = |(c, pin): Pin| (p.read(c, pin) >> i & 1) as usize;
+            let sum = adders
+                .iter()
+                .enumerate()
+                .fold(bit(adders[7][4]) << 8, |acc, (j, f)| acc | bit(f[3]) << j);
+            assert_eq!(
+                sum,
+                expected,
+                "a={} b={} cin={}",
+                n & 0xff,
+                n >> 8 & 0xff,
+                n >> 16
+            );
+        }
+    }
+
+    println!("2^17 patterns verified in {:?}", start.elapsed());
+}
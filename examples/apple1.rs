@@ -0,0 +1,172 @@
+use sim_rs::{
+    components::{
+        bus::SystemBus,
+        cpu::Mos6502,
+        intc::InterruptController,
+        logic::Not,
+        mem::{Ram, Rom},
+        timer::Timer,
+        uart::Uart,
+        Static,
+    },
+    Sim,
+};
+
+// Laid out like an Apple I: 4K of Ram from 0, a Uart standing in for the
+// keyboard and display at 0xd010 and a page of Rom at 0xff00, each byte of
+// memory taking a word of a Ram or Rom, with a Timer at 0xd020 interrupting
+// through an InterruptController at 0xd030. The program prints a message,
+// echoes what it is sent in upper case until a newline, then counts three
+// ticks of the Timer.
+const ROM: [u8; 0x78] = [
+    0xa2, 0x00, // ldx #0
+    0xbd, 0x78, 0xff, // print: lda message,x
+    0xf0, 0x07, // beq echo
+    0x20, 0x5c, 0xff, // jsr out
+    0xe8, // inx
+    0x4c, 0x02, 0xff, // jmp print
+    0xad, 0x11, 0xd0, // echo: lda status
+    0x4a, // lsr a
+    0x90, 0xfa, // bcc echo
+    0xad, 0x10, 0xd0, // lda data
+    0xa0, 0x01, // ldy #1
+    0x8c, 0x11, 0xd0, // sty status
+    0xc9, 0x61, // cmp #'a'
+    0x90, 0x06, // bcc put
+    0xc9, 0x7b, // cmp #'z' + 1
+    0xb0, 0x02, // bcs put
+    0x29, 0xdf, // and #0xdf
+    0x20, 0x5c, 0xff, // put: jsr out
+    0xc9, 0x0a, // cmp #'\n'
+    0xd0, 0xe1, // bne echo
+    0xa9, 0x03, // lda #3
+    0x8d, 0x22, 0xd0, // sta prescaler
+    0xa9, 0xfa, // lda #250
+    0x8d, 0x21, 0xd0, // sta compare
+    0xa9, 0x07, // lda #7
+    0x8d, 0x23, 0xd0, // sta control
+    0xa9, 0x01, // lda #1
+    0x8d, 0x31, 0xd0, // sta enable
+    0x58, // cli
+    0xa0, 0x00, // ldy #0
+    0xc4, 0x00, // tick: cpy ticks
+    0xf0, 0xfc, // beq tick
+    0xc8, // iny
+    0x98, // tya
+    0x09, 0x30, // ora #'0'
+    0x20, 0x5c, 0xff, // jsr out
+    0xc0, 0x03, // cpy #3
+    0xd0, 0xf1, // bne tick
+    0xa9, 0x0a, // lda #'\n'
+    0x20, 0x5c, 0xff, // jsr out
+    0x78, // sei
+    0x4c, 0x59, 0xff, // halt: jmp halt
+    0x48, // out: pha
+    0xad, 0x11, 0xd0, // wait: lda status
+    0x29, 0x02, // and #2
+    0xf0, 0xf9, // beq wait
+    0x68, // pla
+    0x8d, 0x10, 0xd0, // sta data
+    0x60, // rts
+    0x48, // irq: pha
+    0xad, 0x33, 0xd0, // lda claim
+    0xc9, 0x01, // cmp #1
+    0xd0, 0x05, // bne done
+    0x8d, 0x24, 0xd0, // sta timer status
+    0xe6, 0x00, // inc ticks
+    0x68, // done: pla
+    0x40, // rti
+];
+const MESSAGE: &[u8] = b"HELLO, WORLD!\n\0";
+const HALT: u16 = 0xff59;
+
+fn main() {
+    let mut s = Sim::new();
+
+    let mut rom = [0; 0x100];
+    for (word, &byte) in rom.iter_mut().zip(ROM.iter().chain(MESSAGE)) {
+        *word = byte.into();
+    }
+    // nmi, reset and irq vectors
+    rom[0xfa..].copy_from_slice(&[0x59, 0xff, 0x00, 0xff, 0x69, 0xff]);
+
+    s.begin_build();
+
+    let cpu = s.add_component(Mos6502::new());
+    let ram = s.add_component(Ram::new(&[0; 0x1000]));
+    let rom = s.add_component(Rom::new(&rom));
+    let mut uart = Uart::new(4);
+    uart.set_print_output(true);
+    uart.push_input(b"echo this\n");
+    let uart = s.add_component(uart);
+    let timer = s.add_component(Timer::new());
+    let intc = s.add_component(InterruptController::new(2));
+    // pulls rdy, nmi, so and res up
+    let high = s.add_component(Static::<1>(1));
+    let not = s.add_component(Not);
+    let irq = s.add_component(Not);
+
+    let mut map = SystemBus::new();
+    let slaves = [
+        (map.map(0, 0x1000), ram, 12),
+        (map.map(0xd010, 4), uart, 2),
+        (map.map(0xd020, 8), timer, 3),
+        (map.map(0xd030, 8), intc, 3),
+        (map.map(0xff00, 0x100), rom, 8),
+    ];
+    let bus = s.add_component(map);
+
+    fn range(start: usize, end: usize) -> Vec<usize> {
+        (start..=end).collect()
+    }
+
+    s.connect_to_clk(cpu, 37);
+    s.connect_to_clk(uart, 66);
+    s.connect_to_clk(timer, 66);
+    s.connect_to_clk(intc, 66);
+    for pin in [2, 6, 38, 40] {
+        s.connect(high, 1, cpu, pin);
+    }
+
+    // the Timer on line 0 and the Uart on line 1, irq being active low
+    s.connect(timer, 67, intc, InterruptController::line_pin(0));
+    s.connect(uart, 69, intc, InterruptController::line_pin(1));
+    s.connect(intc, 67, irq, 1);
+    s.connect(irq, 2, cpu, 4);
+
+    let addr = [
+        9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 22, 23, 24, 25,
+    ];
+    let data = [33, 32, 31, 30, 29, 28, 27, 26];
+    s.connect_bulk(cpu, &addr, bus, &range(1, 16));
+    s.connect_bulk(cpu, &data, bus, &range(33, 40));
+
+    // writes last the whole cycle, the data only being driven during phi2;
+    // the Not is added before the bus so it sees r/w and the address change
+    // together, and no write lands on the next address
+    s.connect(cpu, 34, not, 1);
+    s.connect(not, 2, bus, 65);
+
+    for &(slave, k, bits) in &slaves {
+        let pin = |pin| SystemBus::slave_pin(slave, pin);
+        s.connect_bulk(bus, &range(pin(1), pin(bits)), k, &range(1, bits));
+        s.connect_bulk(bus, &range(pin(33), pin(40)), k, &range(33, 40));
+        if k != rom {
+            s.connect(bus, pin(65), k, 65);
+        }
+    }
+
+    s.finish_build();
+
+    s.run_until(|s| {
+        s.component::<Mos6502>(cpu).unwrap().pc() == HALT
+            && s.component::<Uart>(uart).unwrap().is_idle()
+    });
+
+    let cpu = s.component::<Mos6502>(cpu).unwrap();
+    println!(
+        "{} instructions in {} cycles",
+        cpu.instructions(),
+        cpu.cycles()
+    );
+}
@@ -0,0 +1,61 @@
+use sim_rs::{
+    components::{
+        cpu::{Event, Mips},
+        logic::{And, Not},
+        mem::Ram,
+    },
+    elf::Elf,
+    Sim,
+};
+
+// Runs a big endian MIPS executable with its text at 0 and its data at
+// 0x10000000, each in 16KiB of Ram, printing every function it enters:
+//
+//     cargo run --example elf -- program.elf
+fn main() {
+    let path = std::env::args().nth(1).expect("usage: elf <program>");
+    let elf = Elf::parse(&std::fs::read(path).unwrap()).unwrap();
+
+    let mut s = Sim::new();
+    s.begin_build();
+
+    let mut mips = Mips::new();
+    let symbols = elf.clone();
+    mips.set_trace(move |cycle, event| {
+        if let Event::Retire { pc, .. } = event {
+            if symbols.lookup(pc).is_some_and(|f| f.addr() == pc) {
+                println!("[{}] {}", cycle, symbols.describe(pc));
+            }
+        }
+    });
+    let cpu = s.add_component(mips);
+    let text = s.add_component(Ram::new(&[0; 1 << 12]));
+    let data = s.add_component(Ram::new(&[0; 1 << 12]));
+    let not = s.add_component(Not);
+    let and = s.add_component(And);
+
+    s.connect(not, 1, cpu, 96);
+    s.connect_to_clk(and, 1);
+    s.connect(and, 2, not, 2);
+    s.connect(and, 3, cpu, 130);
+
+    fn range(start: usize, end: usize) -> Vec<usize> {
+        (start..=end).collect()
+    }
+
+    s.connect_bulk(cpu, &range(3, 14), text, &range(1, 12));
+    s.connect_bulk(cpu, &range(33, 64), text, &range(33, 64));
+
+    s.connect_bulk(cpu, &range(67, 78), data, &range(1, 12));
+    s.connect_bulk(cpu, &range(97, 128), data, &range(33, 64));
+    s.connect(cpu, 129, data, 65);
+
+    s.finish_build();
+
+    elf.load(&mut s, cpu, &[(text, 0), (data, 0x1000_0000)])
+        .unwrap();
+
+    // programs halt by writing to 0xffffffff, which also gates the clock
+    let reason = s.run_until_pin(cpu, 96, true);
+    println!("{:?} after {} cycles", reason, s.cycles());
+}
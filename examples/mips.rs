@@ -1,6 +1,7 @@
 use sim_rs::{
     components::{
-        cpu::Mips,
+        bus::SystemBus,
+        cpu::{Mips, Print},
         logic::{And, Not},
         mem::Ram,
     },
@@ -20,13 +21,25 @@ fn main() {
     ]);
     let data = [0; 32];
 
-    let cpu = s.add_component(Mips::new());
+    s.begin_build();
+
+    let mut mips = Mips::new();
+    mips.set_trace(Print);
+    let cpu = s.add_component(mips);
     let instr_ram = s.add_component(Ram::new(&instr));
     let data_ram = s.add_component(Ram::new(&data));
     let not = s.add_component(Not);
     let and = s.add_component(And);
 
-    s.connect(not, 1, cpu, 96);
+    // the data Ram at 0, and above 0xffff0000 a device halting the clock when
+    // selected
+    let mut map = SystemBus::new();
+    let ram_slave = map.map(0, 4 * data.len() as u32);
+    let halt_slave = map.map(0xffff_0000, 0x1_0000);
+    let bus = s.add_component(map);
+    let halt = SystemBus::slave_pin(halt_slave, 66);
+
+    s.connect(not, 1, bus, halt);
     s.connect_to_clk(and, 1);
     s.connect(and, 2, not, 2);
     s.connect(and, 3, cpu, 130);
@@ -38,11 +51,15 @@ fn main() {
     s.connect_bulk(cpu, &range(3, 10), instr_ram, &range(1, 8));
     s.connect_bulk(cpu, &range(33, 64), instr_ram, &range(33, 64));
 
-    s.connect_bulk(cpu, &range(67, 71), data_ram, &range(1, 5));
-    s.connect_bulk(cpu, &range(97, 128), data_ram, &range(33, 64));
-    s.connect(cpu, 129, data_ram, 65);
+    s.connect_bulk(cpu, &range(65, 129), bus, &range(1, 65));
 
-    loop {
-        s.tick();
-    }
+    let slave = |pin| SystemBus::slave_pin(ram_slave, pin);
+    s.connect_bulk(bus, &range(slave(3), slave(7)), data_ram, &range(1, 5));
+    s.connect_bulk(bus, &range(slave(33), slave(65)), data_ram, &range(33, 65));
+
+    s.finish_build();
+
+    // the program halts by writing to 0xffffffff
+    let reason = s.run_until_pin(bus, halt, true);
+    println!("{:?} after {} cycles", reason, s.cycles());
 }
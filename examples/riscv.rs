@@ -0,0 +1,58 @@
+use sim_rs::{
+    components::{cpu::Rv32, mem::Ram},
+    elf::Elf,
+    Sim,
+};
+
+// Runs riscv-tests style programs, such as the rv32ui and rv32um ones built
+// for the p environment, reporting what each writes to `tohost`:
+//
+//     cargo run --example riscv -- rv32ui-p-*
+fn main() {
+    let mut failed = 0;
+
+    for path in std::env::args().skip(1) {
+        let elf = Elf::parse(&std::fs::read(&path).unwrap()).unwrap();
+        let tohost = elf.symbol("tohost").expect("no tohost symbol").addr();
+
+        let mut s = Sim::new();
+        s.begin_build();
+
+        let cpu = s.add_component(Rv32::new(true));
+        let text = s.add_component(Ram::new(&[0; 1 << 16]));
+        let data = s.add_component(Ram::new(&[0; 1 << 16]));
+
+        fn range(start: usize, end: usize) -> Vec<usize> {
+            (start..=end).collect()
+        }
+
+        // 256KiB of each from 0x80000000, the program being in both
+        s.connect_bulk(cpu, &range(3, 18), text, &range(1, 16));
+        s.connect_bulk(cpu, &range(33, 64), text, &range(33, 64));
+        s.connect_bulk(cpu, &range(67, 82), data, &range(1, 16));
+        s.connect_bulk(cpu, &range(97, 129), data, &range(33, 65));
+        s.connect_to_clk(cpu, 130);
+
+        s.finish_build();
+
+        elf.load(&mut s, cpu, &[(text, 0x8000_0000)]).unwrap();
+        elf.load(&mut s, cpu, &[(data, 0x8000_0000)]).unwrap();
+
+        let word = ((tohost - 0x8000_0000) / 4) as usize;
+        let reason = s.run_until(|s| s.component::<Ram>(data).unwrap().read(word) != 0);
+
+        match s.component::<Ram>(data).unwrap().read(word) {
+            1 => println!("{path}: pass"),
+            0 => {
+                println!("{path}: {reason:?} without a result");
+                failed += 1;
+            }
+            v => {
+                println!("{path}: fail, test {}", v >> 1);
+                failed += 1;
+            }
+        }
+    }
+
+    std::process::exit(failed);
+}
@@ -3,80 +3,229 @@
 
 extern crate alloc;
 
-use alloc::vec::Vec;
+use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
 use core::ops::RangeFrom;
-use fnv::FnvHashMap;
-use k2_tree::K2Tree;
+use fnv::{FnvHashMap, FnvHashSet};
+use storage::Storage;
+
+pub mod storage;
 
 pub struct Graph {
     nodes: Vec<usize>,
-    edges: K2Tree,
+    edges: Box<dyn Storage>,
     id_gen: RangeFrom<usize>,
+    // union-find over node ids, kept up to date on every edge insertion,
+    // with the nodes of each component listed under its root
+    parent: Vec<usize>,
+    members: Vec<Vec<usize>>,
+}
+
+impl Default for Graph {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Graph {
+    #[must_use]
     pub fn new() -> Self {
+        Self::with_storage(storage::K2Tree::new())
+    }
+
+    pub fn with_storage<S: 'static + Storage>(storage: S) -> Self {
         Self {
             nodes: Vec::new(),
-            edges: K2Tree::new(),
+            edges: Box::new(storage),
             id_gen: 0..,
+            parent: Vec::new(),
+            members: Vec::new(),
         }
     }
 
+    #[must_use]
     pub fn nodes(&self) -> &[usize] {
         &self.nodes
     }
 
+    /// # Panics
+    ///
+    /// Will panic once every id has been handed out
     pub fn add_node(&mut self) -> usize {
         let id = self.id_gen.next().unwrap();
         self.nodes.push(id);
+        self.parent.push(id);
+        self.members.push(alloc::vec![id]);
         id
     }
 
+    // removes the node along with its edges, which may split its component
     pub fn remove_node(&mut self, id: usize) {
-        if self.nodes.iter().find(|p| **p == id).is_some() {
-            for (i, &n) in self.edges.get_row(id).unwrap().iter().enumerate() {
-                if n {
-                    self.set_edge(id, i, false);
-                }
+        if let Some(i) = self.nodes.iter().position(|&n| n == id) {
+            for n in self.neighbours(id).collect::<Vec<_>>() {
+                self.remove_edge(id, n);
             }
 
-            self.nodes.remove(id);
+            self.nodes.remove(i);
         }
     }
 
-    fn set_edge(&mut self, id1: usize, id2: usize, value: bool) {
-        let (id1, id2) = if id1 > id2 { (id1, id2) } else { (id2, id1) };
+    // nodes sharing an edge with `id`
+    pub fn neighbours(&self, id: usize) -> impl Iterator<Item = usize> {
+        self.edges.neighbours(id).into_iter()
+    }
+
+    // approximate bytes used on the heap by the edge storage
+    #[must_use]
+    pub fn storage_size(&self) -> usize {
+        self.edges.heap_size()
+    }
 
-        while id1 >= self.edges.matrix_width() || id2 >= self.edges.matrix_width() {
-            self.edges.grow();
+    // returns (kept, absorbed) roots when the edge joins two components
+    pub fn add_edge(&mut self, id1: usize, id2: usize) -> Option<(usize, usize)> {
+        self.edges.set(id1, id2, true);
+        self.union(id1, id2)
+    }
+
+    // returns the roots of both halves when removing the edge splits a component
+    pub fn remove_edge(&mut self, id1: usize, id2: usize) -> Option<(usize, usize)> {
+        if id1 == id2 || !self.has_edge(id1, id2) {
+            return None;
+        }
+        self.edges.set(id1, id2, false);
+
+        let part = self.split_off(id1, id2)?;
+        let root = self.find(id1);
+        let old = core::mem::take(&mut self.members[root]);
+
+        let split = part.iter().copied().collect::<FnvHashSet<_>>();
+        let rest = old
+            .into_iter()
+            .filter(|n| !split.contains(n))
+            .collect::<Vec<_>>();
+
+        // every parent link is reset since paths may cross between both halves,
+        // so relinking costs the whole component even when the search did not
+        let (a, b) = (part[0], rest[0]);
+        for &n in &part {
+            self.parent[n] = a;
         }
+        for &n in &rest {
+            self.parent[n] = b;
+        }
+        self.members[a] = part;
+        self.members[b] = rest;
+
+        Some((self.find(id1), self.find(id2)))
+    }
 
-        self.edges.set(id1, id2, value).unwrap();
+    #[must_use]
+    pub fn has_edge(&self, id1: usize, id2: usize) -> bool {
+        self.edges.get(id1, id2)
     }
 
-    pub fn add_edge(&mut self, id1: usize, id2: usize) {
-        self.set_edge(id1, id2, true);
+    // representative node of the component containing `id`
+    #[must_use]
+    pub fn find(&self, mut id: usize) -> usize {
+        while self.parent[id] != id {
+            id = self.parent[id];
+        }
+
+        id
     }
 
-    pub fn remove_edge(&mut self, id1: usize, id2: usize) {
-        self.set_edge(id1, id2, false);
+    // nodes in the same component as `id`, including itself
+    #[must_use]
+    pub fn members(&self, id: usize) -> &[usize] {
+        &self.members[self.find(id)]
     }
 
+    // every node mapped to the root of its component, as given by find
+    #[must_use]
     pub fn components(&self) -> FnvHashMap<usize, usize> {
-        let mut idx = (0..self.nodes.len()).collect::<Vec<_>>();
-        let mut c = idx.clone();
-
-        for l in self.edges.leaves() {
-            if l.value {
-                if c[idx[l.x]] < c[idx[l.y]] {
-                    c[idx[l.y]] = c[idx[l.x]]
-                } else {
-                    idx[l.x] = idx[l.y];
-                }
+        self.nodes.iter().map(|&n| (n, self.find(n))).collect()
+    }
+
+    fn find_mut(&mut self, mut id: usize) -> usize {
+        while self.parent[id] != id {
+            self.parent[id] = self.parent[self.parent[id]];
+            id = self.parent[id];
+        }
+
+        id
+    }
+
+    fn union(&mut self, id1: usize, id2: usize) -> Option<(usize, usize)> {
+        let (a, b) = (self.find_mut(id1), self.find_mut(id2));
+        if a == b {
+            return None;
+        }
+
+        // union by size keeps trees shallow and moves the shorter member list
+        let (root, child) = if self.members[a].len() < self.members[b].len() {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        self.parent[child] = root;
+
+        let moved = core::mem::take(&mut self.members[child]);
+        self.members[root].extend(moved);
+
+        Some((root, child))
+    }
+
+    // Searches from both ends of a removed edge in lockstep. If one search runs
+    // out of nodes before meeting the other, the nodes it reached were cut off
+    // and are returned, after visiting at most about twice as many nodes as
+    // that smaller half holds. Otherwise both run until they meet.
+    fn split_off(&self, id1: usize, id2: usize) -> Option<Vec<usize>> {
+        let (mut a, mut b) = (Search::new(id1), Search::new(id2));
+
+        loop {
+            if !self.step(&mut a, &b)? {
+                return Some(a.order);
+            }
+            if !self.step(&mut b, &a)? {
+                return Some(b.order);
             }
         }
+    }
+
+    // visits the next node of `this`: None once it meets `other`, false once it runs out
+    fn step(&self, this: &mut Search, other: &Search) -> Option<bool> {
+        let Some(n) = this.queue.pop_front() else {
+            return Some(false);
+        };
 
-        idx.into_iter().map(|i| c[i]).enumerate().collect()
+        for m in self.neighbours(n) {
+            if other.seen.contains(&m) {
+                return None;
+            }
+            if this.seen.insert(m) {
+                this.queue.push_back(m);
+                this.order.push(m);
+            }
+        }
+
+        Some(true)
+    }
+}
+
+struct Search {
+    queue: VecDeque<usize>,
+    seen: FnvHashSet<usize>,
+    order: Vec<usize>,
+}
+
+impl Search {
+    fn new(start: usize) -> Self {
+        let mut seen = FnvHashSet::default();
+        seen.insert(start);
+
+        Self {
+            queue: core::iter::once(start).collect(),
+            seen,
+            order: alloc::vec![start],
+        }
     }
 }
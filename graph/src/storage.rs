@@ -0,0 +1,120 @@
+use alloc::vec::Vec;
+
+// Adjacency of an undirected graph over node ids
+// Send and Sync so a Sim can be read from several threads
+pub trait Storage: Send + Sync {
+    fn set(&mut self, id1: usize, id2: usize, value: bool);
+    fn get(&self, id1: usize, id2: usize) -> bool;
+    fn neighbours(&self, id: usize) -> Vec<usize>;
+    // approximate bytes used on the heap
+    fn heap_size(&self) -> usize;
+}
+
+// Compact bit matrix, slow to mutate
+pub struct K2Tree(k2_tree::K2Tree);
+
+impl K2Tree {
+    #[must_use]
+    pub fn new() -> Self {
+        Self(k2_tree::K2Tree::new())
+    }
+}
+
+impl Default for K2Tree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Storage for K2Tree {
+    fn set(&mut self, id1: usize, id2: usize, value: bool) {
+        // edges are stored once, under (larger id, smaller id)
+        let (id1, id2) = if id1 > id2 { (id1, id2) } else { (id2, id1) };
+
+        while id1 >= self.0.matrix_width() {
+            self.0.grow();
+        }
+
+        self.0.set(id1, id2, value).unwrap();
+    }
+
+    fn get(&self, id1: usize, id2: usize) -> bool {
+        let (id1, id2) = if id1 > id2 { (id1, id2) } else { (id2, id1) };
+
+        id1 < self.0.matrix_width() && self.0.get(id1, id2).unwrap()
+    }
+
+    fn neighbours(&self, id: usize) -> Vec<usize> {
+        if id >= self.0.matrix_width() {
+            return Vec::new();
+        }
+
+        let row = self.0.get_row(id).unwrap();
+        let column = self.0.get_column(id).unwrap();
+
+        let above = row.into_iter().enumerate().skip(id + 1);
+        let below = column.into_iter().enumerate().take(id);
+
+        above
+            .chain(below)
+            .filter(|&(_, e)| e)
+            .map(|(n, _)| n)
+            .collect()
+    }
+
+    fn heap_size(&self) -> usize {
+        (self.0.stems.len() + self.0.leaves.len()).div_ceil(8)
+    }
+}
+
+// Neighbour lists per node, fast to mutate
+#[derive(Default)]
+pub struct AdjacencyList(Vec<Vec<usize>>);
+
+impl AdjacencyList {
+    #[must_use]
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    fn link(&mut self, from: usize, to: usize, value: bool) {
+        if from >= self.0.len() {
+            self.0.resize_with(from + 1, Vec::new);
+        }
+
+        let list = &mut self.0[from];
+        match (list.iter().position(|&n| n == to), value) {
+            (None, true) => list.push(to),
+            (Some(i), false) => {
+                list.swap_remove(i);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Storage for AdjacencyList {
+    fn set(&mut self, id1: usize, id2: usize, value: bool) {
+        self.link(id1, id2, value);
+        if id1 != id2 {
+            self.link(id2, id1, value);
+        }
+    }
+
+    fn get(&self, id1: usize, id2: usize) -> bool {
+        self.0.get(id1).is_some_and(|l| l.contains(&id2))
+    }
+
+    fn neighbours(&self, id: usize) -> Vec<usize> {
+        self.0.get(id).map_or_else(Vec::new, |l| {
+            l.iter().copied().filter(|&n| n != id).collect()
+        })
+    }
+
+    fn heap_size(&self) -> usize {
+        let lists = self.0.capacity() * core::mem::size_of::<Vec<usize>>();
+        let ids = self.0.iter().map(Vec::capacity).sum::<usize>() * core::mem::size_of::<usize>();
+
+        lists + ids
+    }
+}
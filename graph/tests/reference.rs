@@ -0,0 +1,185 @@
+// Random edits applied to a Graph with each storage backend and to a plain
+// set of edges, comparing every query against a brute force search after each
+
+use graph::{
+    storage::{AdjacencyList, K2Tree, Storage},
+    Graph,
+};
+use std::collections::{BTreeSet, VecDeque};
+
+const SEEDS: u64 = 20;
+const OPS: usize = 400;
+const NODES: usize = 24;
+
+// xorshift64*, enough to vary the edits without pulling in a crate
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 33) as usize % n
+    }
+}
+
+#[derive(Default)]
+struct Reference {
+    nodes: BTreeSet<usize>,
+    // neighbours of every id handed out, removed nodes keeping theirs empty
+    adjacent: Vec<BTreeSet<usize>>,
+}
+
+impl Reference {
+    fn add_node(&mut self) -> usize {
+        let id = self.adjacent.len();
+        self.nodes.insert(id);
+        self.adjacent.push(BTreeSet::new());
+        id
+    }
+
+    fn remove_node(&mut self, id: usize) {
+        self.nodes.remove(&id);
+        for n in std::mem::take(&mut self.adjacent[id]) {
+            self.adjacent[n].remove(&id);
+        }
+    }
+
+    fn set(&mut self, a: usize, b: usize, edge: bool) -> bool {
+        if edge {
+            self.adjacent[b].insert(a);
+            self.adjacent[a].insert(b)
+        } else {
+            self.adjacent[b].remove(&a);
+            self.adjacent[a].remove(&b)
+        }
+    }
+
+    fn component(&self, id: usize) -> BTreeSet<usize> {
+        let mut seen = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(id);
+        queue.push_back(id);
+
+        while let Some(n) = queue.pop_front() {
+            for &m in &self.adjacent[n] {
+                if seen.insert(m) {
+                    queue.push_back(m);
+                }
+            }
+        }
+        seen
+    }
+}
+
+fn sorted(ids: impl IntoIterator<Item = usize>) -> BTreeSet<usize> {
+    ids.into_iter().collect()
+}
+
+fn check(g: &Graph, r: &Reference, context: &str) {
+    assert_eq!(
+        sorted(g.nodes().iter().copied()),
+        r.nodes,
+        "{context}: nodes"
+    );
+
+    let ids = r.adjacent.len();
+    let components = (0..ids).map(|id| r.component(id)).collect::<Vec<_>>();
+    for (id, component) in components.iter().enumerate() {
+        assert_eq!(
+            sorted(g.members(id).iter().copied()),
+            *component,
+            "{context}: members of {id}"
+        );
+        assert_eq!(
+            sorted(g.neighbours(id)),
+            r.adjacent[id],
+            "{context}: neighbours of {id}"
+        );
+
+        for other in 0..ids {
+            assert_eq!(
+                g.find(id) == g.find(other),
+                component.contains(&other),
+                "{context}: find({id}) against find({other})"
+            );
+        }
+        // edges never leave a component
+        for &other in component {
+            assert_eq!(
+                g.has_edge(id, other),
+                r.adjacent[id].contains(&other),
+                "{context}: edge {id}-{other}"
+            );
+        }
+    }
+
+    for (&n, &root) in &g.components() {
+        assert_eq!(root, g.find(n), "{context}: components() for {n}");
+    }
+}
+
+fn random_edits(storage: impl Storage + 'static, seed: u64) {
+    let mut g = Graph::with_storage(storage);
+    let mut r = Reference::default();
+    let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1);
+
+    for op in 0..OPS {
+        let context = format!("seed {seed}, op {op}");
+        let live = r.nodes.iter().copied().collect::<Vec<_>>();
+        if live.len() < 2 {
+            assert_eq!(g.add_node(), r.add_node(), "{context}: add_node");
+            continue;
+        }
+        let (a, b) = (live[rng.below(live.len())], live[rng.below(live.len())]);
+
+        match rng.below(10) {
+            0 if live.len() < NODES => {
+                assert_eq!(g.add_node(), r.add_node(), "{context}: add_node");
+            }
+            0 | 1 => {
+                g.remove_node(a);
+                r.remove_node(a);
+            }
+            2..=5 if a != b => {
+                let joined = !r.component(a).contains(&b);
+                let roots = g.add_edge(a, b);
+                r.set(a, b, true);
+
+                assert_eq!(roots.is_some(), joined, "{context}: add_edge({a}, {b})");
+                if let Some((kept, absorbed)) = roots {
+                    assert_ne!(kept, absorbed, "{context}: add_edge({a}, {b})");
+                    assert_eq!(g.find(a), kept, "{context}: add_edge({a}, {b})");
+                }
+            }
+            _ if a != b => {
+                let split = r.set(a, b, false) && !r.component(a).contains(&b);
+                let roots = g.remove_edge(a, b);
+
+                assert_eq!(roots.is_some(), split, "{context}: remove_edge({a}, {b})");
+                if let Some((ra, rb)) = roots {
+                    assert_eq!(ra, g.find(a), "{context}: remove_edge({a}, {b})");
+                    assert_eq!(rb, g.find(b), "{context}: remove_edge({a}, {b})");
+                }
+            }
+            _ => {}
+        }
+
+        check(&g, &r, &context);
+    }
+}
+
+#[test]
+fn k2_tree_matches_reference() {
+    // far slower to query and mutate, so run over fewer seeds
+    for seed in 0..SEEDS / 5 {
+        random_edits(K2Tree::new(), seed);
+    }
+}
+
+#[test]
+fn adjacency_list_matches_reference() {
+    for seed in 0..SEEDS {
+        random_edits(AdjacencyList::new(), seed);
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{components::logic::Gate, event::State, ComponentKey, PinId, Sim};
+use fnv::FnvHashMap;
+
+// one gate evaluation over net values
+struct Instr {
+    gate: Gate,
+    a: usize,
+    b: usize,
+    y: usize,
+}
+
+// The acyclic part of the gate netlist in evaluation order. Gates in loops,
+// or sharing their output net with another gate, are left to the event
+// driven engine along with behavioural components.
+pub(crate) struct Program {
+    instrs: Vec<Instr>,
+    // indexed by component key
+    compiled: Vec<bool>,
+    // first instruction reading each net, NONE for nets no gate reads
+    first: Vec<usize>,
+    // nets changed since the last run, indexed by net and listed, and the
+    // first instruction they make run
+    dirty: Vec<bool>,
+    touched: Vec<usize>,
+    start: usize,
+    full: bool,
+}
+
+const NONE: usize = usize::MAX;
+
+impl Program {
+    pub(crate) fn new(sim: &Sim) -> Self {
+        let gates = sim
+            .keys()
+            .filter_map(|k| {
+                let g = sim.get(k).gate()?;
+                let nets = sim.pins(k).iter().map(|&p| sim.net(p)).collect::<Vec<_>>();
+                Some((k, g, nets))
+            })
+            .collect::<Vec<_>>();
+
+        let mut drivers = FnvHashMap::<usize, usize>::default();
+        for (_, g, nets) in &gates {
+            *drivers.entry(nets[g.inputs()]).or_default() += 1;
+        }
+        let gates = gates
+            .into_iter()
+            .filter(|(_, g, nets)| drivers[&nets[g.inputs()]] == 1)
+            .collect::<Vec<_>>();
+
+        // levelize with Kahn's algorithm, counting each input fed by another gate
+        let outputs = gates
+            .iter()
+            .enumerate()
+            .map(|(i, (_, g, nets))| (nets[g.inputs()], i))
+            .collect::<FnvHashMap<_, _>>();
+        let mut readers = vec![Vec::new(); gates.len()];
+        let mut pending = vec![0; gates.len()];
+        for (i, (_, g, nets)) in gates.iter().enumerate() {
+            for net in &nets[..g.inputs()] {
+                if let Some(&src) = outputs.get(net) {
+                    readers[src].push(i);
+                    pending[i] += 1;
+                }
+            }
+        }
+
+        let mut ready = (0..gates.len())
+            .filter(|&i| pending[i] == 0)
+            .collect::<Vec<_>>();
+        let mut instrs = Vec::with_capacity(gates.len());
+        let mut compiled = Vec::new();
+
+        while let Some(i) = ready.pop() {
+            let (k, gate, nets) = &gates[i];
+            instrs.push(Instr {
+                gate: *gate,
+                a: nets[0],
+                b: nets[gate.inputs() - 1],
+                y: nets[gate.inputs()],
+            });
+
+            if compiled.len() <= *k {
+                compiled.resize(k + 1, false);
+            }
+            compiled[*k] = true;
+
+            for &r in &readers[i] {
+                pending[r] -= 1;
+                if pending[r] == 0 {
+                    ready.push(r);
+                }
+            }
+        }
+
+        let nets = instrs
+            .iter()
+            .map(|i| i.a.max(i.b).max(i.y) + 1)
+            .max()
+            .unwrap_or(0);
+        let mut first = vec![NONE; nets];
+        for (n, i) in instrs.iter().enumerate().rev() {
+            first[i.a] = n;
+            first[i.b] = n;
+        }
+
+        Self {
+            instrs,
+            compiled,
+            first,
+            dirty: vec![false; nets],
+            touched: Vec::new(),
+            start: 0,
+            full: true,
+        }
+    }
+
+    pub(crate) fn is_compiled(&self, c: ComponentKey) -> bool {
+        self.compiled.get(c).copied().unwrap_or(false)
+    }
+
+    pub(crate) fn touch(&mut self, net: usize) {
+        let first = self.first.get(net).copied().unwrap_or(NONE);
+        if first != NONE && !self.dirty[net] {
+            self.dirty[net] = true;
+            self.touched.push(net);
+            self.start = self.start.min(first);
+        }
+    }
+
+    // evaluates every gate with a changed input, the first run evaluating all
+    // of them, and lists the nets whose value changed in `changed`
+    pub(crate) fn run(&mut self, state: &mut impl State, changed: &mut Vec<usize>) {
+        let Self {
+            instrs,
+            dirty,
+            touched,
+            start,
+            full,
+            ..
+        } = self;
+
+        for i in instrs.iter().skip(*start) {
+            if !(*full || dirty[i.a] || dirty[i.b]) {
+                continue;
+            }
+
+            let y = i.gate.eval(
+                state.get(i.a).unwrap_or(false),
+                state.get(i.b).unwrap_or(false),
+            );
+            if state.get(i.y) != Some(y) {
+                state.set(i.y, y);
+                changed.push(i.y);
+
+                if !dirty[i.y] {
+                    dirty[i.y] = true;
+                    touched.push(i.y);
+                }
+            }
+        }
+
+        *start = instrs.len();
+        *full = false;
+        for n in touched.drain(..) {
+            dirty[n] = false;
+        }
+    }
+}
+
+// The gate logic of a Sim evaluated over 64 input patterns at once, bit `i`
+// of every value belonging to pattern `i`. Nets start out from the current
+// Sim values, so constants carry over, and only the gates a Program would
+// compile are evaluated: behavioural components and gates in loops keep
+// their nets at whatever was last written.
+pub struct Parallel<'a> {
+    sim: &'a Sim,
+    instrs: Vec<Instr>,
+    values: Vec<u64>,
+}
+
+impl<'a> Parallel<'a> {
+    pub(crate) fn new(sim: &'a Sim, values: &[Option<bool>]) -> Self {
+        Self {
+            sim,
+            instrs: Program::new(sim).instrs,
+            values: values
+                .iter()
+                .map(|v| if v.unwrap_or(false) { !0 } else { 0 })
+                .collect(),
+        }
+    }
+
+    #[must_use]
+    pub fn read(&self, c: ComponentKey, pin: PinId) -> u64 {
+        self.values[self.net(c, pin)]
+    }
+
+    pub fn write(&mut self, c: ComponentKey, pin: PinId, value: u64) {
+        let net = self.net(c, pin);
+        self.values[net] = value;
+    }
+
+    pub fn run(&mut self) {
+        for i in &self.instrs {
+            self.values[i.y] = i.gate.eval(self.values[i.a], self.values[i.b]);
+        }
+    }
+
+    fn net(&self, c: ComponentKey, pin: PinId) -> usize {
+        self.sim.net(self.sim.pins(c)[pin - 1])
+    }
+}
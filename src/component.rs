@@ -1,15 +1,17 @@
-use crate::{ComponentKey, PinId};
-use fnv::FnvHashMap;
-use std::{cell::RefCell, rc::Rc};
+use crate::{components::logic::Gate, ComponentKey, PinId};
+use std::{
+    any::Any,
+    cell::RefCell,
+    ops::{Deref, DerefMut, RangeInclusive},
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
 
 #[cfg(target_arch = "wasm32")]
-use {
-    js_sys::Array,
-    std::ops::{Deref, DerefMut},
-    wasm_bindgen::prelude::*,
-};
+use {js_sys::Array, wasm_bindgen::prelude::*};
 
 pub trait PinIO {
+    #[must_use]
     fn read(&self, pin: usize) -> bool;
     fn write(&mut self, pin: usize, value: bool);
 
@@ -20,7 +22,7 @@ pub trait PinIO {
         pins.iter()
             .rev()
             .map(|p| self.read(*p))
-            .fold(0, |acc, v| (acc << 1) + v as u32)
+            .fold(0, |acc, v| (acc << 1) + u32::from(v))
     }
 
     // bit n => 1 << n
@@ -29,28 +31,43 @@ pub trait PinIO {
 
         (0..pins.len()).for_each(|i| self.write(pins[i], value & (1 << i) != 0))
     }
-}
 
-#[repr(C)]
-pub struct IO {
-    pins: Vec<usize>,
-    values: Vec<Signal>,
-    changes: FnvHashMap<usize, bool>,
-}
+    // read_u32 over consecutive pins, without building a slice of them
+    fn read_range(&self, pins: RangeInclusive<usize>) -> u32 {
+        assert!(pins.clone().count() <= 32, "Cannot read more than 32 pins");
 
-impl IO {
-    fn new(pins: Vec<usize>, values: Vec<Signal>) -> Self {
-        Self {
-            pins,
-            values,
-            changes: FnvHashMap::with_hasher(Default::default()),
-        }
+        pins.rev()
+            .map(|p| self.read(p))
+            .fold(0, |acc, v| (acc << 1) + u32::from(v))
     }
 
-    fn changes(self) -> FnvHashMap<usize, bool> {
-        self.changes
+    // write_u32 over consecutive pins, without building a slice of them
+    fn write_range(&mut self, pins: RangeInclusive<usize>, value: u32) {
+        assert!(
+            pins.clone().count() <= 32,
+            "Cannot write to more than 32 pins"
+        );
+
+        pins.enumerate()
+            .for_each(|(i, p)| self.write(p, value & (1 << i) != 0));
     }
+}
+
+const UNWRITTEN: usize = usize::MAX;
+
+// View of a component's pins during an update. Writes are collected into a
+// buffer owned by the caller, a pin written twice keeping only its last value,
+// so updating allocates nothing once the buffers have grown.
+#[repr(C)]
+pub struct IO<'a> {
+    pins: &'a [usize],
+    values: &'a [Signal],
+    // index into changes of each written pin
+    slots: &'a mut [usize],
+    changes: &'a mut Vec<(usize, bool)>,
+}
 
+impl IO<'_> {
     #[must_use]
     pub fn is_rising_edge(&self, pin: usize) -> bool {
         matches!(self.values[pin - 1], Signal::RisingEdge)
@@ -62,25 +79,30 @@ impl IO {
     }
 }
 
-impl PinIO for IO {
-    #[must_use]
+impl PinIO for IO<'_> {
     fn read(&self, pin: usize) -> bool {
         self.values[pin - 1].into_bool()
     }
 
     fn write(&mut self, pin: usize, value: bool) {
-        self.values[pin - 1].next_value(value);
-        self.changes.insert(self.pins[pin - 1], value);
+        let slot = &mut self.slots[pin - 1];
+
+        if *slot == UNWRITTEN {
+            *slot = self.changes.len();
+            self.changes.push((self.pins[pin - 1], value));
+        } else {
+            self.changes[*slot].1 = value;
+        }
     }
 }
 
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(js_name = "IO")]
-pub struct JsIO(*mut IO);
+pub struct JsIO(*mut IO<'static>);
 
 #[cfg(target_arch = "wasm32")]
 impl Deref for JsIO {
-    type Target = IO;
+    type Target = IO<'static>;
 
     fn deref(&self) -> &Self::Target {
         unsafe { &*self.0 }
@@ -89,7 +111,7 @@ impl Deref for JsIO {
 
 #[cfg(target_arch = "wasm32")]
 impl DerefMut for JsIO {
-    fn deref_mut(&mut self) -> &mut IO {
+    fn deref_mut(&mut self) -> &mut IO<'static> {
         unsafe { &mut *self.0 }
     }
 }
@@ -126,12 +148,45 @@ impl JsIO {
     }
 }
 
-pub trait Component {
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub trait Component: AsAny {
     fn pin_count(&self) -> usize;
     fn update(&mut self, _io: &mut IO) {}
+
+    // type name, without its module path
+    fn name(&self) -> &'static str {
+        let path = std::any::type_name::<Self>().split('<').next().unwrap();
+        path.rsplit("::").next().unwrap()
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        match self.gate() {
+            Some(g) => g.pin_name(pin).to_string(),
+            None => format!("p{pin}"),
+        }
+    }
+
+    // logic function of the component, if it is a plain gate
+    fn gate(&self) -> Option<Gate> {
+        None
+    }
 }
 
-impl<T: Component> Component for Rc<RefCell<T>> {
+impl<T: 'static + Component> Component for Rc<RefCell<T>> {
     fn pin_count(&self) -> usize {
         self.borrow().pin_count()
     }
@@ -139,6 +194,41 @@ impl<T: Component> Component for Rc<RefCell<T>> {
     fn update(&mut self, io: &mut IO) {
         self.borrow_mut().update(io);
     }
+
+    fn name(&self) -> &'static str {
+        self.borrow().name()
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        self.borrow().pin_name(pin)
+    }
+
+    fn gate(&self) -> Option<Gate> {
+        self.borrow().gate()
+    }
+}
+
+// shared like Rc<RefCell<T>>, but Send so it can run on threads
+impl<T: 'static + Component> Component for Arc<Mutex<T>> {
+    fn pin_count(&self) -> usize {
+        self.lock().unwrap().pin_count()
+    }
+
+    fn update(&mut self, io: &mut IO) {
+        self.lock().unwrap().update(io);
+    }
+
+    fn name(&self) -> &'static str {
+        self.lock().unwrap().name()
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        self.lock().unwrap().pin_name(pin)
+    }
+
+    fn gate(&self) -> Option<Gate> {
+        self.lock().unwrap().gate()
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -205,42 +295,100 @@ impl Component for JsComponent {
     }
 
     fn update(&mut self, io: &mut IO) {
-        JsComponent::update(self, JsIO(io));
+        // only valid during the call, JS components must not keep it
+        JsComponent::update(self, JsIO((io as *mut IO<'_>).cast()));
+    }
+}
+
+// Components as added to a Sim, only those known to be Send being allowed on
+// other threads
+pub enum Boxed {
+    Send(Box<Wrapper<dyn Component + Send>>),
+    Local(Box<Wrapper>),
+}
+
+impl Boxed {
+    // the wrapper, if it can be updated on another thread
+    pub fn as_send_mut(&mut self) -> Option<&mut Wrapper<dyn Component + Send>> {
+        match self {
+            Boxed::Send(w) => Some(w),
+            Boxed::Local(_) => None,
+        }
     }
 }
 
-pub struct Wrapper {
+impl Deref for Boxed {
+    type Target = Wrapper;
+
+    fn deref(&self) -> &Wrapper {
+        match self {
+            Boxed::Send(w) => &**w,
+            Boxed::Local(w) => w,
+        }
+    }
+}
+
+impl DerefMut for Boxed {
+    fn deref_mut(&mut self) -> &mut Wrapper {
+        match self {
+            Boxed::Send(w) => &mut **w,
+            Boxed::Local(w) => w,
+        }
+    }
+}
+
+pub struct Wrapper<C: ?Sized = dyn Component> {
     pins: Vec<usize>,
     input: Vec<Signal>,
-    component: Box<dyn Component>,
+    slots: Vec<usize>,
+    component: C,
 }
 
-impl Wrapper {
-    pub fn new<T: 'static + Component>(pins: Vec<usize>, component: T) -> Self {
+impl<C: Component> Wrapper<C> {
+    pub fn new(pins: Vec<usize>, component: C) -> Self {
         Self {
             input: vec![Signal::Static(false); pins.len()],
+            slots: vec![UNWRITTEN; pins.len()],
             pins,
-            component: Box::new(component),
+            component,
         }
     }
+}
 
+impl Wrapper {
     pub fn pins(&self) -> &[usize] {
         &self.pins
     }
 
-    pub fn set_input(&mut self, input: Vec<bool>) {
-        self.input = self
-            .input
-            .iter()
+    pub fn component(&self) -> &dyn Component {
+        &self.component
+    }
+
+    pub fn component_mut(&mut self) -> &mut dyn Component {
+        &mut self.component
+    }
+
+    pub fn set_input(&mut self, input: &[bool]) {
+        self.input
+            .iter_mut()
             .zip(input)
-            .map(|(old, new)| old.next_value(new))
-            .collect::<Vec<_>>();
+            .for_each(|(old, &new)| *old = old.next_value(new));
     }
 
-    pub fn update(&mut self) -> FnvHashMap<usize, bool> {
-        let mut c = IO::new(self.pins.clone(), self.input.clone());
-        self.component.update(&mut c);
-        c.changes()
+    // appends the pins written by the component to `changes`
+    pub fn update(&mut self, changes: &mut Vec<(usize, bool)>) {
+        let start = changes.len();
+        let mut io = IO {
+            pins: &self.pins,
+            values: &self.input,
+            slots: &mut self.slots,
+            changes,
+        };
+        self.component.update(&mut io);
+
+        if changes.len() > start {
+            self.slots.iter_mut().for_each(|s| *s = UNWRITTEN);
+        }
     }
 }
 
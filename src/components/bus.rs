@@ -0,0 +1,122 @@
+use super::*;
+use bindgen_macro::bindgen;
+
+// Address decoder between a master, such as the data port of a Mips, and the
+// slaves mapped into its address space. The selected slave gets the offset of
+// the address within its range, the master's write and the data in whichever
+// direction it goes, while the others are left alone with write low.
+//
+// pins:
+//  1-32: addr
+//  33-64: data
+//  65: write
+//  66: miss, high when no slave is selected, reads then giving 0
+// and for each slave, numbered like a Ram from SystemBus::slave_pin(slave, 0):
+//  1-32: addr
+//  33-64: data
+//  65: write
+//  66: select
+#[bindgen]
+#[derive(Default)]
+pub struct SystemBus {
+    // base and size in bytes
+    slaves: Vec<(u32, u32)>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl SystemBus {
+    #[must_use]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // maps a slave to `size` bytes from `base`, returning its number; earlier
+    // slaves take precedence where ranges overlap
+    pub fn map(&mut self, base: u32, size: u32) -> usize {
+        self.slaves.push((base, size));
+        self.slaves.len() - 1
+    }
+
+    // the bus pin connected to `pin` of `slave`
+    #[must_use]
+    pub fn slave_pin(slave: usize, pin: usize) -> usize {
+        66 * (slave + 1) + pin
+    }
+
+    // the slave `addr` selects
+    #[must_use]
+    pub fn decode(&self, addr: u32) -> Option<usize> {
+        self.slaves
+            .iter()
+            .position(|&(base, size)| addr.wrapping_sub(base) < size)
+    }
+}
+
+impl Component for SystemBus {
+    fn pin_count(&self) -> usize {
+        66 * (self.slaves.len() + 1)
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        let name = match (pin - 1) % 66 + 1 {
+            p @ 1..=32 => format!("addr[{}]", p - 1),
+            p @ 33..=64 => format!("data[{}]", p - 33),
+            65 => "write".to_string(),
+            _ if pin <= 66 => "miss".to_string(),
+            _ => "select".to_string(),
+        };
+
+        match (pin - 1) / 66 {
+            0 => name,
+            s => format!("s{}.{}", s - 1, name),
+        }
+    }
+
+    fn update(&mut self, io: &mut IO) {
+        let addr = io.read_range(1..=32);
+        let write = io.read(65);
+        let selected = self.decode(addr);
+
+        for (s, &(base, _)) in self.slaves.iter().enumerate() {
+            let pin = Self::slave_pin(s, 0);
+            let hit = selected == Some(s);
+
+            io.write(pin + 66, hit);
+            io.write(pin + 65, hit && write);
+            if !hit {
+                continue;
+            }
+
+            io.write_range(pin + 1..=pin + 32, addr - base);
+            if write {
+                io.write_range(pin + 33..=pin + 64, io.read_range(33..=64));
+            } else {
+                io.write_range(33..=64, io.read_range(pin + 33..=pin + 64));
+            }
+        }
+
+        io.write(66, selected.is_none());
+        if selected.is_none() && !write {
+            io.write_range(33..=64, 0);
+        }
+    }
+}
+
+// Register writes of a slave with Ram-like pins, each taking effect as the
+// write strobe falls with the address and data it was last high with, so
+// the slave can drive its data pins whenever the strobe is low.
+#[derive(Default)]
+pub(crate) struct WriteStrobe(Option<(u32, u32)>);
+
+impl WriteStrobe {
+    // the write that just ended, as its address and data
+    pub(crate) fn update(&mut self, io: &IO) -> Option<(u32, u32)> {
+        if io.read(65) {
+            self.0 = Some((io.read_range(1..=32), io.read_range(33..=64)));
+            None
+        } else {
+            self.0.take()
+        }
+    }
+}
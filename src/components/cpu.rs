@@ -1,8 +1,153 @@
 use super::*;
 use bindgen_macro::{bindgen, constrgen};
 use mips_emu::{Cpu, Mem};
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::ops::RangeInclusive;
+
+mod mos6502;
+mod riscv;
+pub use mos6502::Mos6502;
+pub use riscv::Rv32;
+
+// what the CPU did, emitted to the trace of a Mips or Rv32 as it happens
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event {
+    Fetch { addr: u32, instr: u32 },
+    // `count` instructions retired so far, the last one fetched from `pc`
+    Retire { pc: u32, count: usize },
+    RegWrite { reg: usize, value: u32 },
+    Load { addr: u32, value: u32 },
+    Store { addr: u32, value: u32 },
+    // an interrupt taken, returning to `epc` once handled
+    Exception { epc: u32, cause: u32 },
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Event::Fetch { addr, instr } => write!(f, "fetch {addr:#010x}: {instr:#010x}"),
+            Event::Retire { pc, count } => write!(f, "retire {pc:#010x} (#{count})"),
+            Event::RegWrite { reg, value } => write!(f, "${reg} = {value:#x}"),
+            Event::Load { addr, value } => write!(f, "load {addr:#010x}: {value:#x}"),
+            Event::Store { addr, value } => write!(f, "store {addr:#010x}: {value:#x}"),
+            Event::Exception { epc, cause } => {
+                write!(f, "exception at {epc:#010x} (cause {cause:#x})")
+            }
+        }
+    }
+}
+
+// sink for the events of a Mips, tagged with the cycle they happened on
+pub trait Trace {
+    fn event(&mut self, cycle: usize, event: Event);
+}
+
+impl<F: FnMut(usize, Event)> Trace for F {
+    fn event(&mut self, cycle: usize, event: Event) {
+        self(cycle, event);
+    }
+}
+
+// prints every event, to stdout or the browser console
+pub struct Print;
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str);
+}
+
+impl Trace for Print {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn event(&mut self, cycle: usize, event: Event) {
+        println!("[{cycle}] {event}");
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn event(&mut self, cycle: usize, event: Event) {
+        log(&format!("[{}] {}", cycle, event));
+    }
+}
+
+type Sink = Box<dyn Trace + Send>;
+
+const COP0: u32 = 0x10;
+
+// bits of the Status register
+const STATUS_IE: u32 = 1;
+const STATUS_EXL: u32 = 1 << 1;
+const STATUS_ERL: u32 = 1 << 2;
+const STATUS_BEV: u32 = 1 << 22;
+
+// Coprocessor 0 as far as interrupts need it: Status, Cause, EPC, PRId and
+// EBase. Other registers read as 0 and ignore writes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Cp0 {
+    status: u32,
+    cause: u32,
+    epc: u32,
+    ebase: u32,
+}
+
+impl Default for Cp0 {
+    fn default() -> Self {
+        Self {
+            status: 0,
+            cause: 0,
+            epc: 0,
+            ebase: 0x8000_0000,
+        }
+    }
+}
+
+impl Cp0 {
+    fn read(&self, reg: u32, sel: u32) -> u32 {
+        match (reg, sel) {
+            (12, 0) => self.status,
+            (13, 0) => self.cause,
+            (14, 0) => self.epc,
+            // a 4Kc
+            (15, 0) => 0x0001_8000,
+            (15, 1) => self.ebase,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, reg: u32, sel: u32, value: u32) {
+        match (reg, sel) {
+            (12, 0) => self.status = value,
+            // only the software interrupts are writable
+            (13, 0) => self.cause = self.cause & !0x300 | value & 0x300,
+            (14, 0) => self.epc = value,
+            (15, 1) => self.ebase = 0x8000_0000 | value & 0x3fff_f000,
+            _ => {}
+        }
+    }
+
+    fn interrupted(&self) -> bool {
+        self.status & STATUS_IE != 0
+            && self.status & (STATUS_EXL | STATUS_ERL) == 0
+            && self.cause & self.status & 0xff00 != 0
+    }
+
+    fn vector(&self) -> u32 {
+        if self.status & STATUS_BEV != 0 {
+            0xbfc0_0380
+        } else {
+            self.ebase + 0x180
+        }
+    }
+}
+
+// the delay slot of these has to run before an interrupt can be taken
+fn is_branch(instr: u32) -> bool {
+    match instr >> 26 {
+        0 => matches!(instr & 0x3f, 0x08 | 0x09),
+        op => matches!(op, 0x01..=0x07 | 0x14..=0x17),
+    }
+}
 
 // pins:
 //  1-32: instr_addr
@@ -11,67 +156,461 @@ use std::rc::Rc;
 //  97-128: data
 //  129: data_write
 //  130: clk
+//  131: reset, holding the CPU at its reset vector while high
+//  132-137: int[0-5], the hardware interrupts, Cause bits IP2-IP7
+//
+// COP0 instructions (mfc0, mtc0, di, ei and eret) are carried out here rather
+// than by the emulator, which sees them as nops, when they retire. Interrupts
+// are taken between instructions, never in a delay slot, setting EPC to the
+// next one and jumping to EBase + 0x180.
 #[bindgen]
 #[constrgen]
-pub struct Mips(Cpu);
+pub struct Mips {
+    cpu: Cpu,
+    trace: Option<Sink>,
+    cp0: Cp0,
+    reset_vector: u32,
+    // the last instruction fetched
+    fetched: u32,
+}
+
+impl Mips {
+    // events are only gathered while there is a trace to send them to
+    pub fn set_trace(&mut self, trace: impl Trace + Send + 'static) {
+        self.trace = Some(Box::new(trace));
+    }
+
+    fn reset(&mut self) {
+        self.cpu = Cpu::default();
+        self.cpu.set_pc(self.reset_vector);
+        self.cp0 = Cp0::default();
+        self.fetched = 0;
+    }
+
+    // the instruction fetched last having retired on `cycle`
+    fn retire(&mut self, cycle: usize) {
+        let instr = self.fetched;
+        if instr >> 26 == COP0 {
+            self.execute_cop0(instr, cycle);
+        }
+
+        if !is_branch(instr) && self.cp0.interrupted() {
+            let epc = self.cpu.pc();
+            self.cp0.epc = epc;
+            // ExcCode 0, an interrupt
+            self.cp0.cause &= !0x7c;
+            self.cp0.status |= STATUS_EXL;
+            self.cpu.set_pc(self.cp0.vector());
+
+            if let Some(trace) = &mut self.trace {
+                let cause = self.cp0.cause;
+                trace.event(cycle, Event::Exception { epc, cause });
+            }
+        }
+    }
+
+    fn execute_cop0(&mut self, instr: u32, cycle: usize) {
+        let rt = (instr >> 16 & 0x1f) as usize;
+        let rd = instr >> 11 & 0x1f;
+        let sel = instr & 0x7;
+
+        match instr >> 21 & 0x1f {
+            // mfc0
+            0x00 => self.write_reg(cycle, rt, self.cp0.read(rd, sel)),
+            // mtc0
+            0x04 => self.cp0.write(rd, sel, self.reg(rt)),
+            // di and ei
+            0x0b => {
+                self.write_reg(cycle, rt, self.cp0.status);
+                if instr & 0x20 == 0 {
+                    self.cp0.status &= !STATUS_IE;
+                } else {
+                    self.cp0.status |= STATUS_IE;
+                }
+            }
+            // eret
+            0x10..=0x1f if instr & 0x3f == 0x18 => {
+                self.cpu.set_pc(self.cp0.epc);
+                self.cp0.status &= !STATUS_EXL;
+            }
+            _ => {}
+        }
+    }
+
+    // traced like the writes of the emulator, when the value changes
+    fn write_reg(&mut self, cycle: usize, r: usize, value: u32) {
+        if r == 0 || self.reg(r) == value {
+            return;
+        }
+
+        self.set_reg(r, value);
+        if let Some(trace) = &mut self.trace {
+            trace.event(cycle, Event::RegWrite { reg: r, value });
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Mips {
+    pub fn set_print_trace(&mut self, enabled: bool) {
+        self.trace = if enabled { Some(Box::new(Print)) } else { None };
+    }
+
+    pub fn clear_trace(&mut self) {
+        self.trace = None;
+    }
+
+    #[must_use]
+    pub fn pc(&self) -> u32 {
+        self.cpu.pc()
+    }
+
+    pub fn set_pc(&mut self, pc: u32) {
+        self.cpu.set_pc(pc);
+    }
+
+    #[must_use]
+    pub fn reg(&self, r: usize) -> u32 {
+        self.cpu.read_reg(r)
+    }
+
+    // writes to $0 are ignored like the hardware does
+    pub fn set_reg(&mut self, r: usize, value: u32) {
+        if r != 0 {
+            self.cpu.write_reg(r, value);
+        }
+    }
+
+    #[must_use]
+    pub fn hi(&self) -> u32 {
+        self.cpu.hi()
+    }
+
+    pub fn set_hi(&mut self, value: u32) {
+        self.cpu.set_hi(value);
+    }
+
+    #[must_use]
+    pub fn lo(&self) -> u32 {
+        self.cpu.lo()
+    }
+
+    pub fn set_lo(&mut self, value: u32) {
+        self.cpu.set_lo(value);
+    }
+
+    #[must_use]
+    pub fn status(&self) -> u32 {
+        self.cp0.status
+    }
+
+    pub fn set_status(&mut self, value: u32) {
+        self.cp0.status = value;
+    }
+
+    #[must_use]
+    pub fn cause(&self) -> u32 {
+        self.cp0.cause
+    }
+
+    #[must_use]
+    pub fn epc(&self) -> u32 {
+        self.cp0.epc
+    }
+
+    // where the CPU starts when reset, 0 by default
+    pub fn set_reset_vector(&mut self, pc: u32) {
+        self.reset_vector = pc;
+    }
+
+    #[must_use]
+    pub fn cycles(&self) -> usize {
+        self.cpu.cycle()
+    }
+
+    #[must_use]
+    pub fn instructions(&self) -> usize {
+        self.cpu.instr_count()
+    }
+
+    #[must_use]
+    pub fn state(&self) -> MipsState {
+        MipsState {
+            pc: self.pc(),
+            regs: std::array::from_fn(|r| self.reg(r)),
+            hi: self.hi(),
+            lo: self.lo(),
+            status: self.cp0.status,
+            cause: self.cp0.cause,
+            epc: self.cp0.epc,
+            cycles: self.cycles(),
+            instructions: self.instructions(),
+        }
+    }
+
+    // the counters of `state` are left alone
+    pub fn set_state(&mut self, state: &MipsState) {
+        self.set_pc(state.pc);
+        for (r, &value) in state.regs.iter().enumerate() {
+            self.set_reg(r, value);
+        }
+        self.set_hi(state.hi);
+        self.set_lo(state.lo);
+        self.cp0.status = state.status;
+        self.cp0.cause = state.cause;
+        self.cp0.epc = state.epc;
+    }
+}
+
+// Copy of the registers of a Mips, for reading and editing them from JS where
+// the component itself is out of reach once added to a Sim
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MipsState {
+    pc: u32,
+    regs: [u32; 32],
+    hi: u32,
+    lo: u32,
+    status: u32,
+    cause: u32,
+    epc: u32,
+    cycles: usize,
+    instructions: usize,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl MipsState {
+    #[must_use]
+    pub fn pc(&self) -> u32 {
+        self.pc
+    }
+
+    pub fn set_pc(&mut self, pc: u32) {
+        self.pc = pc;
+    }
+
+    #[must_use]
+    pub fn reg(&self, r: usize) -> u32 {
+        self.regs[r]
+    }
+
+    pub fn set_reg(&mut self, r: usize, value: u32) {
+        self.regs[r] = value;
+    }
+
+    #[must_use]
+    pub fn regs(&self) -> Vec<u32> {
+        self.regs.to_vec()
+    }
+
+    #[must_use]
+    pub fn hi(&self) -> u32 {
+        self.hi
+    }
+
+    pub fn set_hi(&mut self, value: u32) {
+        self.hi = value;
+    }
+
+    #[must_use]
+    pub fn lo(&self) -> u32 {
+        self.lo
+    }
+
+    pub fn set_lo(&mut self, value: u32) {
+        self.lo = value;
+    }
+
+    #[must_use]
+    pub fn status(&self) -> u32 {
+        self.status
+    }
+
+    pub fn set_status(&mut self, value: u32) {
+        self.status = value;
+    }
+
+    #[must_use]
+    pub fn cause(&self) -> u32 {
+        self.cause
+    }
+
+    // the interrupt lines overwrite IP2-IP7 on the next clock edge
+    pub fn set_cause(&mut self, value: u32) {
+        self.cause = value;
+    }
+
+    #[must_use]
+    pub fn epc(&self) -> u32 {
+        self.epc
+    }
+
+    pub fn set_epc(&mut self, value: u32) {
+        self.epc = value;
+    }
+
+    #[must_use]
+    pub fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    #[must_use]
+    pub fn instructions(&self) -> usize {
+        self.instructions
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Sim {
+    // state of the Mips added as `c`, None if it is some other component
+    #[must_use]
+    pub fn mips_state(&self, c: ComponentKey) -> Option<MipsState> {
+        self.component::<Mips>(c).map(Mips::state)
+    }
+
+    // returns whether `c` is a Mips
+    pub fn set_mips_state(&mut self, c: ComponentKey, state: &MipsState) -> bool {
+        self.component_mut::<Mips>(c)
+            .map(|m| m.set_state(state))
+            .is_some()
+    }
+}
 
 impl Component for Mips {
     fn pin_count(&self) -> usize {
-        130
+        137
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        match pin {
+            1..=32 => format!("instr_addr[{}]", pin - 1),
+            33..=64 => format!("instr[{}]", pin - 33),
+            65..=96 => format!("data_addr[{}]", pin - 65),
+            97..=128 => format!("data[{}]", pin - 97),
+            129 => "data_write".to_string(),
+            130 => "clk".to_string(),
+            131 => "reset".to_string(),
+            _ => format!("int[{}]", pin - 132),
+        }
     }
 
     fn update(&mut self, io: &mut IO) {
-        if io.is_falling_edge(130) || io.is_rising_edge(130) {
-            println!(
-                "---- CPU ({:#X}, {}, {}) ----",
-                self.0.pc(),
-                self.0.cycle(),
-                self.0.instr_count()
-            );
-            let shared_io = Rc::new(RefCell::new(&mut *io));
-            self.0.half_step(
-                &mut M {
-                    io: Rc::clone(&shared_io),
-                    addr_pins: (1..=32).collect(),
-                    data_pins: (33..=64).collect(),
-                    write_pin: None,
-                },
-                &mut M {
-                    io: shared_io,
-                    addr_pins: (65..=96).collect(),
-                    data_pins: (97..=128).collect(),
-                    write_pin: Some(129),
-                },
-            );
-
-            println!(
-                "regs: {:?}",
-                (0..32).map(|r| self.0.read_reg(r)).collect::<Vec<_>>()
-            );
-        }
-    }
-}
-
-struct M<'a> {
-    io: Rc<RefCell<&'a mut IO>>,
-    addr_pins: Vec<usize>,
-    data_pins: Vec<usize>,
+        if !io.is_falling_edge(130) && !io.is_rising_edge(130) {
+            return;
+        }
+
+        self.cp0.cause = self.cp0.cause & !0xfc00 | io.read_range(132..=137) << 10;
+        if io.read(131) {
+            self.reset();
+            return;
+        }
+
+        let Self { cpu, trace, .. } = self;
+        let cycle = cpu.cycle();
+        let pc = cpu.pc();
+        let retired = cpu.instr_count();
+        let regs = std::array::from_fn::<_, 32, _>(|r| cpu.read_reg(r));
+
+        let fetched = Cell::new(None);
+        let shared_io = RefCell::new(io);
+        let shared_trace = trace.as_mut().map(|t| RefCell::new((cycle, t)));
+        cpu.half_step(
+            &mut M {
+                io: &shared_io,
+                trace: shared_trace.as_ref(),
+                fetched: Some(&fetched),
+                addr_pins: 1..=32,
+                data_pins: 33..=64,
+                write_pin: None,
+                addr: 0,
+            },
+            &mut M {
+                io: &shared_io,
+                trace: shared_trace.as_ref(),
+                fetched: None,
+                addr_pins: 65..=96,
+                data_pins: 97..=128,
+                write_pin: Some(129),
+                addr: 0,
+            },
+        );
+
+        if let Some(trace) = trace {
+            for (reg, &old) in regs.iter().enumerate() {
+                let value = cpu.read_reg(reg);
+                if value != old {
+                    trace.event(cycle, Event::RegWrite { reg, value });
+                }
+            }
+
+            let count = cpu.instr_count();
+            if count != retired {
+                trace.event(cycle, Event::Retire { pc, count });
+            }
+        }
+
+        if let Some(instr) = fetched.get() {
+            self.fetched = instr;
+        }
+        if self.cpu.instr_count() != retired {
+            self.retire(cycle);
+        }
+    }
+}
+
+// one of the memory ports, the instruction port being the one that cannot write
+struct M<'a, 'b> {
+    io: &'a RefCell<&'a mut IO<'b>>,
+    trace: Option<&'a RefCell<(usize, &'a mut Sink)>>,
+    // set to what the instruction port reads
+    fetched: Option<&'a Cell<Option<u32>>>,
+    addr_pins: RangeInclusive<usize>,
+    data_pins: RangeInclusive<usize>,
     write_pin: Option<usize>,
+    addr: u32,
+}
+
+impl M<'_, '_> {
+    fn emit(&self, event: Event) {
+        if let Some(trace) = self.trace {
+            let (cycle, trace) = &mut *trace.borrow_mut();
+            trace.event(*cycle, event);
+        }
+    }
 }
 
-impl Mem for M<'_> {
+impl Mem for M<'_, '_> {
     fn addr(&mut self, addr: u32) {
+        self.addr = addr;
         let mut io = self.io.borrow_mut();
 
         if let Some(p) = self.write_pin {
             io.write(p, false);
         }
 
-        io.write_u32(&self.addr_pins, addr);
+        io.write_range(self.addr_pins.clone(), addr);
     }
 
     fn read(&mut self) -> u32 {
-        self.io.borrow().read_u32(&self.data_pins)
+        let value = self.io.borrow().read_range(self.data_pins.clone());
+        let addr = self.addr;
+
+        self.emit(match self.write_pin {
+            None => Event::Fetch { addr, instr: value },
+            Some(_) => Event::Load { addr, value },
+        });
+
+        match self.fetched {
+            Some(fetched) => {
+                fetched.set(Some(value));
+                if value >> 26 == COP0 {
+                    0
+                } else {
+                    value
+                }
+            }
+            None => value,
+        }
     }
 
     fn write(&mut self, data: u32) {
@@ -81,6 +620,12 @@ impl Mem for M<'_> {
             io.write(p, true);
         }
 
-        io.write_u32(&self.data_pins, data);
+        io.write_range(self.data_pins.clone(), data);
+        drop(io);
+
+        self.emit(Event::Store {
+            addr: self.addr,
+            value: data,
+        });
     }
 }
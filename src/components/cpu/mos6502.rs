@@ -0,0 +1,831 @@
+use super::*;
+
+// pins of the 40 pin DIP, the active low inputs needing to be driven high
+// (by a Static for instance) to be released as the Sim has no pull-ups
+const RDY: usize = 2;
+const PHI1: usize = 3;
+const IRQ: usize = 4;
+const NMI: usize = 6;
+const SYNC: usize = 7;
+const RW: usize = 34;
+const PHI0: usize = 37;
+const SO: usize = 38;
+const PHI2: usize = 39;
+const RES: usize = 40;
+const ADDR: [usize; 16] = [
+    9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 22, 23, 24, 25,
+];
+const DATA: [usize; 8] = [33, 32, 31, 30, 29, 28, 27, 26];
+
+const C: u8 = 0x01;
+const Z: u8 = 0x02;
+const I: u8 = 0x04;
+const D: u8 = 0x08;
+const B: u8 = 0x10;
+const U: u8 = 0x20;
+const V: u8 = 0x40;
+const N: u8 = 0x80;
+
+const NMI_VECTOR: u16 = 0xfffa;
+const RESET_VECTOR: u16 = 0xfffc;
+const IRQ_VECTOR: u16 = 0xfffe;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Regs {
+    a: u8,
+    x: u8,
+    y: u8,
+    s: u8,
+    p: u8,
+    pc: u16,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Sequence {
+    Reset,
+    Nmi,
+    Irq,
+    Instruction,
+}
+
+// one bus cycle, writing when there is data
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Access {
+    addr: u16,
+    data: Option<u8>,
+    sync: bool,
+}
+
+// the access an instruction is waiting on
+struct Pending(Access);
+
+type Step<T> = Result<T, Pending>;
+
+#[derive(Clone, Copy)]
+enum Mode {
+    Zp,
+    ZpX,
+    ZpY,
+    Abs,
+    AbsX,
+    AbsY,
+    IndX,
+    IndY,
+}
+
+// Runs a sequence from its first cycle with the data of the cycles already on
+// the bus, stopping at the first access it has no data for. Every bus cycle
+// of the NMOS part is made, dummy reads and writes included, so replaying
+// this each cycle drives the pins the way the chip does.
+struct Core<'a> {
+    r: Regs,
+    reads: &'a [u8],
+    next: usize,
+    // cycles, by bit, whose end decides whether an interrupt follows, the
+    // penultimate one when None
+    polls: Option<u8>,
+}
+
+impl Core<'_> {
+    fn access(&mut self, addr: u16, data: Option<u8>, sync: bool) -> Step<u8> {
+        if self.next < self.reads.len() {
+            self.next += 1;
+            Ok(self.reads[self.next - 1])
+        } else {
+            Err(Pending(Access { addr, data, sync }))
+        }
+    }
+
+    fn read(&mut self, addr: u16) -> Step<u8> {
+        self.access(addr, None, false)
+    }
+
+    fn write(&mut self, addr: u16, value: u8) -> Step<()> {
+        self.access(addr, Some(value), false).map(drop)
+    }
+
+    fn fetch(&mut self) -> Step<u8> {
+        let value = self.read(self.r.pc)?;
+        self.r.pc = self.r.pc.wrapping_add(1);
+        Ok(value)
+    }
+
+    fn fetch_word(&mut self) -> Step<u16> {
+        let lo = self.fetch()?;
+        Ok(u16::from_le_bytes([lo, self.fetch()?]))
+    }
+
+    // the cycle after the opcode of one byte instructions
+    fn idle(&mut self) -> Step<()> {
+        self.read(self.r.pc).map(drop)
+    }
+
+    fn push(&mut self, value: u8) -> Step<()> {
+        self.write(0x100 | u16::from(self.r.s), value)?;
+        self.r.s = self.r.s.wrapping_sub(1);
+        Ok(())
+    }
+
+    fn pull(&mut self) -> Step<u8> {
+        self.r.s = self.r.s.wrapping_add(1);
+        self.read(0x100 | u16::from(self.r.s))
+    }
+
+    fn flag(&mut self, flag: u8, value: bool) {
+        if value {
+            self.r.p |= flag;
+        } else {
+            self.r.p &= !flag;
+        }
+    }
+
+    fn nz(&mut self, value: u8) -> u8 {
+        self.flag(Z, value == 0);
+        self.flag(N, value & 0x80 != 0);
+        value
+    }
+
+    // indexing reads the unfixed address first when the page changes, and
+    // always before writing
+    fn indexed(&mut self, base: u16, index: u8, write: bool) -> Step<u16> {
+        let addr = base.wrapping_add(u16::from(index));
+        if write || addr & 0xff00 != base & 0xff00 {
+            self.read(base & 0xff00 | addr & 0xff)?;
+        }
+        Ok(addr)
+    }
+
+    fn addr(&mut self, mode: Mode, write: bool) -> Step<u16> {
+        Ok(match mode {
+            Mode::Zp => u16::from(self.fetch()?),
+            Mode::ZpX | Mode::ZpY => {
+                let base = self.fetch()?;
+                self.read(u16::from(base))?;
+                let index = if let Mode::ZpX = mode {
+                    self.r.x
+                } else {
+                    self.r.y
+                };
+                u16::from(base.wrapping_add(index))
+            }
+            Mode::Abs => self.fetch_word()?,
+            Mode::AbsX => {
+                let base = self.fetch_word()?;
+                self.indexed(base, self.r.x, write)?
+            }
+            Mode::AbsY => {
+                let base = self.fetch_word()?;
+                self.indexed(base, self.r.y, write)?
+            }
+            Mode::IndX => {
+                let ptr = self.fetch()?;
+                self.read(u16::from(ptr))?;
+                let ptr = ptr.wrapping_add(self.r.x);
+                let lo = self.read(u16::from(ptr))?;
+                u16::from_le_bytes([lo, self.read(u16::from(ptr.wrapping_add(1)))?])
+            }
+            Mode::IndY => {
+                let ptr = self.fetch()?;
+                let lo = self.read(u16::from(ptr))?;
+                let base = u16::from_le_bytes([lo, self.read(u16::from(ptr.wrapping_add(1)))?]);
+                self.indexed(base, self.r.y, write)?
+            }
+        })
+    }
+
+    // not polled, so the first instruction of the handler always runs
+    fn interrupt(&mut self, vector: u16) -> Step<()> {
+        self.polls = Some(0);
+
+        // the opcode fetched is dropped for a forced break
+        let pc = self.r.pc;
+        self.access(pc, None, true)?;
+        self.idle()?;
+
+        let [lo, hi] = pc.to_le_bytes();
+        if vector == RESET_VECTOR {
+            // the pushes happen with writing disabled
+            for _ in 0..3 {
+                self.read(0x100 | u16::from(self.r.s))?;
+                self.r.s = self.r.s.wrapping_sub(1);
+            }
+        } else {
+            self.push(hi)?;
+            self.push(lo)?;
+            self.push(self.r.p & !B | U)?;
+        }
+
+        self.r.p |= I;
+        self.jump_vector(vector)
+    }
+
+    fn jump_vector(&mut self, vector: u16) -> Step<()> {
+        let lo = self.read(vector)?;
+        self.r.pc = u16::from_le_bytes([lo, self.read(vector + 1)?]);
+        Ok(())
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn adc(&mut self, value: u8) {
+        let Regs { a, p, .. } = self.r;
+        let carry = p & C;
+        let sum = u16::from(a) + u16::from(value) + u16::from(carry);
+
+        if p & D == 0 {
+            self.flag(C, sum > 0xff);
+            self.flag(V, !(a ^ value) & (a ^ sum as u8) & 0x80 != 0);
+            self.r.a = self.nz(sum as u8);
+            return;
+        }
+
+        // the NMOS part sets N and V from the intermediate result, and Z from
+        // the binary one
+        let mut lo = (a & 0xf) + (value & 0xf) + carry;
+        if lo > 9 {
+            lo += 6;
+        }
+        let mut hi = (a >> 4) + (value >> 4) + u8::from(lo > 0xf);
+
+        self.flag(Z, sum as u8 == 0);
+        self.flag(N, hi & 0x8 != 0);
+        self.flag(V, !(a ^ value) & (a ^ hi << 4) & 0x80 != 0);
+        if hi > 9 {
+            hi += 6;
+        }
+        self.flag(C, hi > 0xf);
+        self.r.a = lo & 0xf | hi << 4;
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn sbc(&mut self, value: u8) {
+        let Regs { a, p, .. } = self.r;
+        if p & D == 0 {
+            self.adc(!value);
+            return;
+        }
+
+        let borrow = 1 - (p & C);
+        let diff = u16::from(a)
+            .wrapping_sub(u16::from(value))
+            .wrapping_sub(u16::from(borrow));
+        let mut lo = (a & 0xf).wrapping_sub(value & 0xf).wrapping_sub(borrow);
+        let lo_borrow = lo & 0x80 != 0;
+        if lo_borrow {
+            lo = lo.wrapping_sub(6);
+        }
+        let mut hi = (a >> 4)
+            .wrapping_sub(value >> 4)
+            .wrapping_sub(u8::from(lo_borrow));
+        if hi & 0x80 != 0 {
+            hi = hi.wrapping_sub(6);
+        }
+
+        self.flag(Z, diff as u8 == 0);
+        self.flag(N, diff & 0x80 != 0);
+        self.flag(V, (a ^ value) & (a ^ diff as u8) & 0x80 != 0);
+        self.flag(C, diff & 0xff00 == 0);
+        self.r.a = lo & 0xf | hi << 4;
+    }
+
+    fn compare(&mut self, reg: u8, value: u8) {
+        self.flag(C, reg >= value);
+        self.nz(reg.wrapping_sub(value));
+    }
+
+    // ASL, ROL, LSR and ROR by their aaa bits
+    fn shift(&mut self, op: u8, value: u8) -> u8 {
+        let carry = self.r.p & C;
+        let (result, out) = match op {
+            0 => (value << 1, value >> 7),
+            1 => (value << 1 | carry, value >> 7),
+            2 => (value >> 1, value & 1),
+            _ => (value >> 1 | carry << 7, value & 1),
+        };
+
+        self.flag(C, out != 0);
+        self.nz(result)
+    }
+
+    fn branch(&mut self, taken: bool) -> Step<()> {
+        let offset = self.fetch()?;
+        if !taken {
+            return Ok(());
+        }
+
+        // taken, the opcode fetch is polled instead of the penultimate
+        // cycle, along with the one before the fixup when crossing a page
+        self.polls = Some(0b1);
+        self.idle()?;
+        let pc = self.r.pc;
+        let target = pc
+            .wrapping_add(u16::from(offset))
+            .wrapping_sub(u16::from(offset & 0x80) << 1);
+        if target & 0xff00 != pc & 0xff00 {
+            self.polls = Some(0b101);
+            self.read(pc & 0xff00 | target & 0xff)?;
+        }
+        self.r.pc = target;
+        Ok(())
+    }
+
+    // read, write back unchanged as the NMOS part does, then write the result
+    fn modify(&mut self, mode: Mode, f: impl FnOnce(&mut Self, u8) -> u8) -> Step<()> {
+        let addr = self.addr(mode, true)?;
+        let value = self.read(addr)?;
+        self.write(addr, value)?;
+        let result = f(self, value);
+        self.write(addr, result)
+    }
+
+    fn step(&mut self) -> Step<()> {
+        let pc = self.r.pc;
+        let op = self.access(pc, None, true)?;
+        self.r.pc = pc.wrapping_add(1);
+
+        match op {
+            // BRK
+            0x00 => {
+                self.polls = Some(0);
+                self.fetch()?;
+                let [lo, hi] = self.r.pc.to_le_bytes();
+                self.push(hi)?;
+                self.push(lo)?;
+                self.push(self.r.p | B | U)?;
+                self.r.p |= I;
+                self.jump_vector(IRQ_VECTOR)?;
+            }
+            // JSR
+            0x20 => {
+                let lo = self.fetch()?;
+                self.read(0x100 | u16::from(self.r.s))?;
+                let [pcl, pch] = self.r.pc.to_le_bytes();
+                self.push(pch)?;
+                self.push(pcl)?;
+                let hi = self.read(self.r.pc)?;
+                self.r.pc = u16::from_le_bytes([lo, hi]);
+            }
+            // RTI
+            0x40 => {
+                self.idle()?;
+                self.read(0x100 | u16::from(self.r.s))?;
+                self.r.p = self.pull()? & !B | U;
+                let lo = self.pull()?;
+                self.r.pc = u16::from_le_bytes([lo, self.pull()?]);
+            }
+            // RTS
+            0x60 => {
+                self.idle()?;
+                self.read(0x100 | u16::from(self.r.s))?;
+                let lo = self.pull()?;
+                self.r.pc = u16::from_le_bytes([lo, self.pull()?]);
+                self.fetch()?;
+            }
+            // JMP
+            0x4c => self.r.pc = self.fetch_word()?,
+            0x6c => {
+                let ptr = self.fetch_word()?;
+                let lo = self.read(ptr)?;
+                // the pointer's high byte comes from the same page
+                let hi = self.read(ptr & 0xff00 | ptr.wrapping_add(1) & 0xff)?;
+                self.r.pc = u16::from_le_bytes([lo, hi]);
+            }
+            // PHP, PHA, PLP and PLA
+            0x08 | 0x48 => {
+                self.idle()?;
+                let value = if op == 0x08 {
+                    self.r.p | B | U
+                } else {
+                    self.r.a
+                };
+                self.push(value)?;
+            }
+            0x28 | 0x68 => {
+                self.idle()?;
+                self.read(0x100 | u16::from(self.r.s))?;
+                let value = self.pull()?;
+                if op == 0x28 {
+                    self.r.p = value & !B | U;
+                } else {
+                    self.r.a = self.nz(value);
+                }
+            }
+            // branches on N, V, C and Z, taken when the flag equals bit 5
+            op if op & 0x1f == 0x10 => {
+                let flag = [N, V, C, Z][usize::from(op >> 6)];
+                self.branch((self.r.p & flag != 0) == (op & 0x20 != 0))?;
+            }
+            // the other one byte instructions
+            0x18 | 0x38 | 0x58 | 0x78 | 0xb8 | 0xd8 | 0xf8 | 0x88 | 0x8a | 0x98 | 0x9a | 0xa8
+            | 0xaa | 0xba | 0xc8 | 0xca | 0xe8 | 0xea => {
+                self.idle()?;
+                self.implied(op);
+            }
+            // ASL, ROL, LSR and ROR on A
+            0x0a | 0x2a | 0x4a | 0x6a => {
+                self.idle()?;
+                self.r.a = self.shift(op >> 5, self.r.a);
+            }
+            _ => self.group(op)?,
+        }
+
+        Ok(())
+    }
+
+    fn implied(&mut self, op: u8) {
+        let r = self.r;
+        match op {
+            0x18 => self.flag(C, false),
+            0x38 => self.flag(C, true),
+            0x58 => self.flag(I, false),
+            0x78 => self.flag(I, true),
+            0xb8 => self.flag(V, false),
+            0xd8 => self.flag(D, false),
+            0xf8 => self.flag(D, true),
+            0x88 => self.r.y = self.nz(r.y.wrapping_sub(1)),
+            0x8a => self.r.a = self.nz(r.x),
+            0x98 => self.r.a = self.nz(r.y),
+            0x9a => self.r.s = r.x,
+            0xa8 => self.r.y = self.nz(r.a),
+            0xaa => self.r.x = self.nz(r.a),
+            0xba => self.r.x = self.nz(r.s),
+            0xc8 => self.r.y = self.nz(r.y.wrapping_add(1)),
+            0xca => self.r.x = self.nz(r.x.wrapping_sub(1)),
+            0xe8 => self.r.x = self.nz(r.x.wrapping_add(1)),
+            _ => {}
+        }
+    }
+
+    // instructions following the aaabbbcc layout, the undocumented ones taken
+    // as two cycle NOPs
+    fn group(&mut self, op: u8) -> Step<()> {
+        let (aaa, bbb, cc) = (op >> 5, op >> 2 & 0x7, op & 0x3);
+
+        let mode = match (cc, bbb) {
+            (1, 0) => Some(Mode::IndX),
+            (_, 1) => Some(Mode::Zp),
+            (_, 3) => Some(Mode::Abs),
+            (1, 4) => Some(Mode::IndY),
+            // STX and LDX index with Y
+            (2, 5) if aaa == 4 || aaa == 5 => Some(Mode::ZpY),
+            (_, 5) => Some(Mode::ZpX),
+            (1, 6) => Some(Mode::AbsY),
+            (2, 7) if aaa == 5 => Some(Mode::AbsY),
+            (_, 7) => Some(Mode::AbsX),
+            // immediate
+            _ => None,
+        };
+        let immediate = match cc {
+            0 => bbb == 0 && aaa >= 5,
+            1 => bbb == 2 && aaa != 4,
+            2 => bbb == 0 && aaa == 5,
+            _ => false,
+        };
+
+        let valid = immediate
+            || match (cc, aaa) {
+                (1, _) => bbb != 2,
+                (0 | 2, 4) => matches!(bbb, 1 | 3 | 5),
+                (0, 5) | (2, _) => matches!(bbb, 1 | 3 | 5 | 7),
+                (0, 1 | 6 | 7) => matches!(bbb, 1 | 3),
+                _ => false,
+            };
+        if !valid {
+            return self.idle();
+        }
+
+        // stores
+        if aaa == 4 {
+            let value = match cc {
+                0 => self.r.y,
+                1 => self.r.a,
+                _ => self.r.x,
+            };
+            let addr = self.addr(mode.unwrap(), true)?;
+            return self.write(addr, value);
+        }
+
+        // read, modify, write
+        if cc == 2 && aaa != 5 {
+            return self.modify(mode.unwrap(), |core, v| match aaa {
+                6 => core.nz(v.wrapping_sub(1)),
+                7 => core.nz(v.wrapping_add(1)),
+                _ => core.shift(aaa, v),
+            });
+        }
+
+        let value = match mode {
+            Some(mode) if !immediate => {
+                let addr = self.addr(mode, false)?;
+                self.read(addr)?
+            }
+            _ => self.fetch()?,
+        };
+
+        match (cc, aaa) {
+            (1, 0) => self.r.a = self.nz(self.r.a | value),
+            (1, 1) => self.r.a = self.nz(self.r.a & value),
+            (1, 2) => self.r.a = self.nz(self.r.a ^ value),
+            (1, 3) => self.adc(value),
+            (1, 5) => self.r.a = self.nz(value),
+            (1, 6) => self.compare(self.r.a, value),
+            (1, _) => self.sbc(value),
+            (2, _) => self.r.x = self.nz(value),
+            (0, 1) => {
+                self.flag(Z, self.r.a & value == 0);
+                self.flag(N, value & N != 0);
+                self.flag(V, value & V != 0);
+            }
+            (0, 5) => self.r.y = self.nz(value),
+            (0, 6) => self.compare(self.r.y, value),
+            _ => self.compare(self.r.x, value),
+        }
+        Ok(())
+    }
+}
+
+// An NMOS 6502 on the pins of its 40 pin DIP, making the same bus cycles as
+// the chip, dummy ones included. Each cycle of phi0 is one bus cycle, with the
+// address, R/W and SYNC set as phi1 rises and written data driven during
+// phi2, and read data taken at the end of phi2. RDY low stretches read
+// cycles. Interrupts are sampled as every cycle ends, one pending at the end
+// of an instruction's penultimate cycle being taken once it finishes, so CLI,
+// SEI and PLP act one instruction late while RTI does not, and a taken branch
+// that stays on its page lets the next instruction run first. BRK and the
+// interrupt sequences are not polled at all. Undocumented opcodes are two
+// cycle NOPs.
+//
+// pins:
+//  1: vss
+//  2: rdy
+//  3: phi1 (out)
+//  4: irq (active low)
+//  5: nc
+//  6: nmi (active low, edge triggered)
+//  7: sync
+//  8: vcc
+//  9-20: a0-a11
+//  21: vss
+//  22-25: a12-a15
+//  26-33: d7-d0
+//  34: r/w
+//  35-36: nc
+//  37: phi0 (in)
+//  38: so (active low, edge triggered)
+//  39: phi2 (out)
+//  40: res (active low)
+#[allow(clippy::struct_excessive_bools)]
+#[bindgen]
+pub struct Mos6502 {
+    // as of the start of the sequence in progress
+    regs: Regs,
+    sequence: Sequence,
+    reads: [u8; 8],
+    done: usize,
+    // on the bus this cycle, None while reset is held
+    access: Option<Access>,
+    // input lines as last seen, true when asserted
+    irq: bool,
+    nmi: bool,
+    nmi_pending: bool,
+    // cycles of the sequence in progress, by bit, that ended with an
+    // interrupt pending
+    polled: u8,
+    so: bool,
+    cycles: usize,
+    instructions: usize,
+}
+
+impl Default for Mos6502 {
+    fn default() -> Self {
+        Self {
+            regs: Regs {
+                a: 0,
+                x: 0,
+                y: 0,
+                s: 0,
+                p: U | I,
+                pc: 0,
+            },
+            sequence: Sequence::Reset,
+            reads: [0; 8],
+            done: 0,
+            access: None,
+            irq: false,
+            nmi: false,
+            nmi_pending: false,
+            polled: 0,
+            so: false,
+            cycles: 0,
+            instructions: 0,
+        }
+    }
+}
+
+impl Mos6502 {
+    // ends the cycle on the bus, putting the next one on it
+    fn cycle(&mut self, io: &mut IO) {
+        if let Some(access) = self.access {
+            self.cycles += 1;
+
+            // RDY only holds read cycles on the NMOS part
+            if access.data.is_none() {
+                if !io.read(RDY) {
+                    return;
+                }
+                self.reads[self.done] = io.read_u32(&DATA).to_le_bytes()[0];
+            }
+            self.done += 1;
+        }
+        self.advance();
+
+        let access = self.access.unwrap();
+        io.write_u32(&ADDR, access.addr.into());
+        io.write(RW, access.data.is_none());
+        io.write(SYNC, access.sync);
+    }
+
+    // carries the sequence on to its next bus cycle, starting the next one
+    // when it is done
+    fn advance(&mut self) {
+        loop {
+            let mut core = Core {
+                r: self.regs,
+                reads: &self.reads[..self.done],
+                next: 0,
+                polls: None,
+            };
+            let result = match self.sequence {
+                Sequence::Reset => core.interrupt(RESET_VECTOR),
+                Sequence::Nmi => core.interrupt(NMI_VECTOR),
+                Sequence::Irq => core.interrupt(IRQ_VECTOR),
+                Sequence::Instruction => core.step(),
+            };
+
+            if let Err(Pending(access)) = result {
+                // the flags as the cycle that just ended left them
+                if self.done > 0 && (self.nmi_pending || self.irq && core.r.p & I == 0) {
+                    self.polled |= 1 << (self.done - 1);
+                }
+                self.access = Some(access);
+                return;
+            }
+
+            let polls = core.polls.unwrap_or(1 << (self.done - 2));
+            let interrupt = self.polled & polls != 0;
+            self.regs = core.r;
+            self.done = 0;
+            self.polled = 0;
+            if self.sequence == Sequence::Instruction {
+                self.instructions += 1;
+            }
+
+            self.sequence = if !interrupt {
+                Sequence::Instruction
+            } else if self.nmi_pending {
+                self.nmi_pending = false;
+                Sequence::Nmi
+            } else {
+                Sequence::Irq
+            };
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Mos6502 {
+    // comes out of reset by itself, as if RES had been pulsed at power on
+    #[must_use]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // registers as of the start of the instruction in progress
+    #[must_use]
+    pub fn a(&self) -> u8 {
+        self.regs.a
+    }
+
+    #[must_use]
+    pub fn x(&self) -> u8 {
+        self.regs.x
+    }
+
+    #[must_use]
+    pub fn y(&self) -> u8 {
+        self.regs.y
+    }
+
+    #[must_use]
+    pub fn s(&self) -> u8 {
+        self.regs.s
+    }
+
+    #[must_use]
+    pub fn p(&self) -> u8 {
+        self.regs.p
+    }
+
+    #[must_use]
+    pub fn pc(&self) -> u16 {
+        self.regs.pc
+    }
+
+    // abandons the instruction in progress, fetching from `pc` next cycle
+    pub fn set_pc(&mut self, pc: u16) {
+        self.regs.pc = pc;
+        self.sequence = Sequence::Instruction;
+        self.done = 0;
+        self.polled = 0;
+        if self.access.is_some() {
+            self.advance();
+        }
+    }
+
+    // cycles of phi0
+    #[must_use]
+    pub fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    #[must_use]
+    pub fn instructions(&self) -> usize {
+        self.instructions
+    }
+}
+
+impl Component for Mos6502 {
+    fn pin_count(&self) -> usize {
+        40
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        match pin {
+            1 | 21 => "vss".to_string(),
+            RDY => "rdy".to_string(),
+            PHI1 => "phi1".to_string(),
+            IRQ => "irq".to_string(),
+            5 | 35 | 36 => "nc".to_string(),
+            NMI => "nmi".to_string(),
+            SYNC => "sync".to_string(),
+            8 => "vcc".to_string(),
+            9..=20 => format!("a{}", pin - 9),
+            22..=25 => format!("a{}", pin - 10),
+            26..=33 => format!("d{}", 33 - pin),
+            RW => "rw".to_string(),
+            PHI0 => "phi0".to_string(),
+            SO => "so".to_string(),
+            PHI2 => "phi2".to_string(),
+            _ => "res".to_string(),
+        }
+    }
+
+    fn update(&mut self, io: &mut IO) {
+        let phi0 = io.read(PHI0);
+        io.write(PHI1, !phi0);
+        io.write(PHI2, phi0);
+
+        let nmi = !io.read(NMI);
+        let so = !io.read(SO);
+
+        // the lines are only watched for edges once out of reset
+        if !io.read(RES) {
+            *self = Self {
+                regs: Regs {
+                    p: self.regs.p | I,
+                    ..self.regs
+                },
+                nmi,
+                so,
+                cycles: self.cycles,
+                instructions: self.instructions,
+                ..Self::default()
+            };
+            io.write(RW, true);
+            io.write(SYNC, false);
+            return;
+        }
+
+        if nmi && !self.nmi {
+            self.nmi_pending = true;
+        }
+        self.nmi = nmi;
+
+        if so && !self.so {
+            self.regs.p |= V;
+        }
+        self.so = so;
+
+        if io.is_falling_edge(PHI0) {
+            self.irq = !io.read(IRQ);
+            self.cycle(io);
+        } else if io.is_rising_edge(PHI0) {
+            if let Some(Access {
+                data: Some(data), ..
+            }) = self.access
+            {
+                io.write_u32(&DATA, data.into());
+            }
+        }
+    }
+}
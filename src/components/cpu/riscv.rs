@@ -0,0 +1,606 @@
+use super::*;
+
+// machine mode CSRs, the only mode there is
+const MSTATUS: u32 = 0x300;
+const MISA: u32 = 0x301;
+const MIE: u32 = 0x304;
+const MTVEC: u32 = 0x305;
+const MSCRATCH: u32 = 0x340;
+const MEPC: u32 = 0x341;
+const MCAUSE: u32 = 0x342;
+const MTVAL: u32 = 0x343;
+const MIP: u32 = 0x344;
+
+// bits of mstatus
+const STATUS_MIE: u32 = 1 << 3;
+const STATUS_MPIE: u32 = 1 << 7;
+const STATUS_MPP: u32 = 3 << 11;
+
+// exception causes
+const MISALIGNED_FETCH: u32 = 0;
+const ILLEGAL_INSTRUCTION: u32 = 2;
+const BREAKPOINT: u32 = 3;
+const MISALIGNED_LOAD: u32 = 4;
+const MISALIGNED_STORE: u32 = 6;
+const ECALL: u32 = 11;
+
+// interrupts, by their bit in mip and mie, in order of priority
+const INTERRUPTS: [u32; 3] = [11, 3, 7];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Phase {
+    // putting the pc on the instruction bus
+    Fetch,
+    // the instruction at the pc is on the instruction bus
+    Execute,
+    // the word holding `addr` is on the data bus
+    Load { rd: usize, addr: u32, funct3: u32 },
+    // the word a byte or halfword store goes into is on the data bus
+    Merge { addr: u32, value: u32, mask: u32 },
+    // lowering data_write after a store
+    Release,
+}
+
+// An RV32I core, with the M extension when asked for, taking one clock edge
+// for most instructions and one or two more for loads and stores. The data
+// bus moves whole words, so byte and halfword stores read the word they go
+// into first. Only machine mode exists, with enough of Zicsr for traps and
+// interrupts; fence and fence.i do nothing, the instructions coming from a
+// memory of their own.
+//
+// pins:
+//  1-32: instr_addr
+//  33-64: instr
+//  65-96: data_addr
+//  97-128: data
+//  129: data_write
+//  130: clk
+//  131: reset, holding the core at its reset vector while high
+//  132: external interrupt (mip.MEIP)
+//  133: timer interrupt (mip.MTIP)
+//  134: software interrupt (mip.MSIP)
+#[bindgen]
+pub struct Rv32 {
+    m: bool,
+    regs: [u32; 32],
+    pc: u32,
+    phase: Phase,
+    reset_vector: u32,
+    cycles: usize,
+    instructions: usize,
+    mstatus: u32,
+    mie: u32,
+    mip: u32,
+    mtvec: u32,
+    mscratch: u32,
+    mepc: u32,
+    mcause: u32,
+    mtval: u32,
+    trace: Option<Sink>,
+}
+
+impl Rv32 {
+    pub fn set_trace(&mut self, trace: impl Trace + Send + 'static) {
+        self.trace = Some(Box::new(trace));
+    }
+
+    fn emit(&mut self, event: Event) {
+        if let Some(trace) = &mut self.trace {
+            trace.event(self.cycles, event);
+        }
+    }
+
+    fn write_reg(&mut self, rd: usize, value: u32) {
+        if rd != 0 {
+            self.regs[rd] = value;
+            self.emit(Event::RegWrite { reg: rd, value });
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn read_csr(&self, csr: u32) -> Option<u32> {
+        let counter = |v: usize, high: bool| {
+            let v = v as u64;
+            if high {
+                (v >> 32) as u32
+            } else {
+                v as u32
+            }
+        };
+
+        Some(match csr {
+            MSTATUS => self.mstatus | STATUS_MPP,
+            // MXL 32, I and M
+            MISA => 0x4000_0100 | if self.m { 0x1000 } else { 0 },
+            MIE => self.mie,
+            MTVEC => self.mtvec,
+            MSCRATCH => self.mscratch,
+            MEPC => self.mepc,
+            MCAUSE => self.mcause,
+            MTVAL => self.mtval,
+            MIP => self.mip,
+            // mvendorid, marchid, mimpid and mhartid
+            0xf11..=0xf14 => 0,
+            // cycle, time and mcycle, and their high halves
+            0xc00 | 0xc01 | 0xb00 => counter(self.cycles, false),
+            0xc80 | 0xc81 | 0xb80 => counter(self.cycles, true),
+            // instret and minstret
+            0xc02 | 0xb02 => counter(self.instructions, false),
+            0xc82 | 0xb82 => counter(self.instructions, true),
+            _ => return None,
+        })
+    }
+
+    // false for those that cannot be written
+    fn write_csr(&mut self, csr: u32, value: u32) -> bool {
+        match csr {
+            MSTATUS => self.mstatus = value & (STATUS_MIE | STATUS_MPIE),
+            // the extensions are fixed, and the pending bits follow the
+            // interrupt pins
+            MISA | MIP => {}
+            MIE => self.mie = value & 0x888,
+            MTVEC => self.mtvec = value & !0x2,
+            MSCRATCH => self.mscratch = value,
+            MEPC => self.mepc = value & !0x3,
+            MCAUSE => self.mcause = value,
+            MTVAL => self.mtval = value,
+            _ => return false,
+        }
+        true
+    }
+
+    // jumps to the handler in mtvec, `epc` being where to return to
+    fn trap(&mut self, cause: u32, tval: u32, epc: u32) {
+        self.mepc = epc;
+        self.mcause = cause;
+        self.mtval = tval;
+
+        let mie = self.mstatus & STATUS_MIE != 0;
+        self.mstatus &= !(STATUS_MIE | STATUS_MPIE);
+        if mie {
+            self.mstatus |= STATUS_MPIE;
+        }
+
+        let base = self.mtvec & !0x3;
+        self.pc = if self.mtvec & 1 != 0 && cause >> 31 != 0 {
+            base.wrapping_add(4 * (cause & 0x7fff_ffff))
+        } else {
+            base
+        };
+
+        self.emit(Event::Exception { epc, cause });
+    }
+
+    // an exception raised by the instruction at the pc, which does not retire
+    fn raise(&mut self, io: &mut IO, cause: u32, tval: u32) -> Phase {
+        self.trap(cause, tval, self.pc);
+        self.next(io)
+    }
+
+    fn retire(&mut self, io: &mut IO, pc: u32) -> Phase {
+        self.instructions += 1;
+        let count = self.instructions;
+        self.emit(Event::Retire { pc, count });
+        self.next(io)
+    }
+
+    // takes any interrupt pending and fetches the next instruction
+    fn next(&mut self, io: &mut IO) -> Phase {
+        let pending = self.mip & self.mie;
+        if self.mstatus & STATUS_MIE != 0 && pending != 0 {
+            let code = INTERRUPTS
+                .iter()
+                .copied()
+                .find(|&i| pending & 1 << i != 0)
+                .unwrap();
+            self.trap(1 << 31 | code, 0, self.pc);
+        }
+
+        io.write_range(1..=32, self.pc);
+        Phase::Execute
+    }
+
+    // the pc being that of the instruction
+    #[allow(
+        clippy::cast_possible_wrap,
+        clippy::cast_sign_loss,
+        clippy::too_many_lines
+    )]
+    fn execute(&mut self, io: &mut IO, instr: u32) -> Phase {
+        let pc = self.pc;
+        let rd = (instr >> 7 & 0x1f) as usize;
+        let funct3 = instr >> 12 & 0x7;
+        let rs1 = (instr >> 15 & 0x1f) as usize;
+        let rs2 = (instr >> 20 & 0x1f) as usize;
+        let funct7 = instr >> 25;
+        let (a, b) = (self.regs[rs1], self.regs[rs2]);
+
+        let i_imm = (instr as i32 >> 20) as u32;
+        let s_imm = ((instr as i32 >> 25) << 5) as u32 | instr >> 7 & 0x1f;
+        let b_imm = ((instr as i32 >> 31) << 12) as u32
+            | instr << 4 & 0x800
+            | instr >> 20 & 0x7e0
+            | instr >> 7 & 0x1e;
+        let j_imm = ((instr as i32 >> 31) << 20) as u32
+            | instr & 0xf_f000
+            | instr >> 9 & 0x800
+            | instr >> 20 & 0x7fe;
+
+        let illegal = |cpu: &mut Self, io: &mut IO| cpu.raise(io, ILLEGAL_INSTRUCTION, instr);
+
+        let value = match instr & 0x7f {
+            // lui
+            0x37 => instr & 0xffff_f000,
+            // auipc
+            0x17 => pc.wrapping_add(instr & 0xffff_f000),
+            // jal and jalr
+            0x6f | 0x67 => {
+                let target = if instr & 0x7f == 0x6f {
+                    pc.wrapping_add(j_imm)
+                } else if funct3 == 0 {
+                    a.wrapping_add(i_imm) & !1
+                } else {
+                    return illegal(self, io);
+                };
+                if target & 0x3 != 0 {
+                    return self.raise(io, MISALIGNED_FETCH, target);
+                }
+
+                self.write_reg(rd, pc.wrapping_add(4));
+                self.pc = target;
+                return self.retire(io, pc);
+            }
+            // branches
+            0x63 => {
+                let taken = match funct3 {
+                    0 => a == b,
+                    1 => a != b,
+                    4 => (a as i32) < b as i32,
+                    5 => a as i32 >= b as i32,
+                    6 => a < b,
+                    7 => a >= b,
+                    _ => return illegal(self, io),
+                };
+
+                let target = pc.wrapping_add(b_imm);
+                if taken && target & 0x3 != 0 {
+                    return self.raise(io, MISALIGNED_FETCH, target);
+                }
+                self.pc = if taken { target } else { pc.wrapping_add(4) };
+                return self.retire(io, pc);
+            }
+            // loads
+            0x03 => {
+                let addr = a.wrapping_add(i_imm);
+                let align = match funct3 {
+                    0 | 4 => 1,
+                    1 | 5 => 2,
+                    2 => 4,
+                    _ => return illegal(self, io),
+                };
+                if addr % align != 0 {
+                    return self.raise(io, MISALIGNED_LOAD, addr);
+                }
+
+                io.write(129, false);
+                io.write_range(65..=96, addr);
+                self.pc = pc.wrapping_add(4);
+                return Phase::Load { rd, addr, funct3 };
+            }
+            // stores
+            0x23 => {
+                let addr = a.wrapping_add(s_imm);
+                let (mask, align) = match funct3 {
+                    0 => (0xff, 1),
+                    1 => (0xffff, 2),
+                    2 => (0xffff_ffff, 4),
+                    _ => return illegal(self, io),
+                };
+                if addr % align != 0 {
+                    return self.raise(io, MISALIGNED_STORE, addr);
+                }
+
+                self.pc = pc.wrapping_add(4);
+                io.write_range(65..=96, addr);
+                if funct3 == 2 {
+                    io.write(129, true);
+                    io.write_range(97..=128, b);
+                    self.emit(Event::Store { addr, value: b });
+                    return Phase::Release;
+                }
+
+                io.write(129, false);
+                return Phase::Merge {
+                    addr,
+                    value: b & mask,
+                    mask,
+                };
+            }
+            // immediate arithmetic
+            0x13 => match (funct3, funct7) {
+                (0, _) => a.wrapping_add(i_imm),
+                (2, _) => u32::from((a as i32) < i_imm as i32),
+                (3, _) => u32::from(a < i_imm),
+                (4, _) => a ^ i_imm,
+                (6, _) => a | i_imm,
+                (7, _) => a & i_imm,
+                (1, 0) => a << rs2,
+                (5, 0) => a >> rs2,
+                (5, 0x20) => (a as i32 >> rs2) as u32,
+                _ => return illegal(self, io),
+            },
+            // register arithmetic
+            0x33 => match (funct3, funct7) {
+                (0, 0) => a.wrapping_add(b),
+                (0, 0x20) => a.wrapping_sub(b),
+                (1, 0) => a << (b & 0x1f),
+                (2, 0) => u32::from((a as i32) < b as i32),
+                (3, 0) => u32::from(a < b),
+                (4, 0) => a ^ b,
+                (5, 0) => a >> (b & 0x1f),
+                (5, 0x20) => (a as i32 >> (b & 0x1f)) as u32,
+                (6, 0) => a | b,
+                (7, 0) => a & b,
+                (_, 1) if self.m => multiply(funct3, a, b),
+                _ => return illegal(self, io),
+            },
+            // fence and fence.i
+            0x0f => {
+                self.pc = pc.wrapping_add(4);
+                return self.retire(io, pc);
+            }
+            0x73 => return self.system(io, instr),
+            _ => return illegal(self, io),
+        };
+
+        self.write_reg(rd, value);
+        self.pc = pc.wrapping_add(4);
+        self.retire(io, pc)
+    }
+
+    fn system(&mut self, io: &mut IO, instr: u32) -> Phase {
+        let pc = self.pc;
+        let rd = (instr >> 7 & 0x1f) as usize;
+        let funct3 = instr >> 12 & 0x7;
+        let rs1 = instr >> 15 & 0x1f;
+        let csr = instr >> 20;
+
+        if funct3 == 0 {
+            match instr {
+                0x0000_0073 => return self.raise(io, ECALL, 0),
+                0x0010_0073 => return self.raise(io, BREAKPOINT, pc),
+                // mret
+                0x3020_0073 => {
+                    let mpie = self.mstatus & STATUS_MPIE != 0;
+                    self.mstatus = STATUS_MPIE | if mpie { STATUS_MIE } else { 0 };
+                    self.pc = self.mepc;
+                    return self.retire(io, pc);
+                }
+                // wfi, interrupts being checked after every instruction anyway
+                0x1050_0073 => {
+                    self.pc = pc.wrapping_add(4);
+                    return self.retire(io, pc);
+                }
+                _ => return self.raise(io, ILLEGAL_INSTRUCTION, instr),
+            }
+        }
+
+        let old = match self.read_csr(csr) {
+            Some(v) if funct3 != 4 => v,
+            // funct3 4 is reserved
+            _ => return self.raise(io, ILLEGAL_INSTRUCTION, instr),
+        };
+
+        // the immediate forms use the rs1 field as the operand
+        let operand = if funct3 & 0x4 == 0 {
+            self.regs[rs1 as usize]
+        } else {
+            rs1
+        };
+        let new = match funct3 & 0x3 {
+            1 => Some(operand),
+            // set and clear only write when rs1 is not x0
+            2 => Some(old | operand).filter(|_| rs1 != 0),
+            _ => Some(old & !operand).filter(|_| rs1 != 0),
+        };
+
+        // the top two bits of a read only CSR's number are set
+        if let Some(new) = new {
+            if csr >> 10 == 0x3 || !self.write_csr(csr, new) {
+                return self.raise(io, ILLEGAL_INSTRUCTION, instr);
+            }
+        }
+
+        self.write_reg(rd, old);
+        self.pc = pc.wrapping_add(4);
+        self.retire(io, pc)
+    }
+}
+
+#[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+fn multiply(funct3: u32, a: u32, b: u32) -> u32 {
+    let (sa, sb) = (i64::from(a as i32), i64::from(b as i32));
+    let (ua, ub) = (u64::from(a), u64::from(b));
+
+    match funct3 {
+        0 => a.wrapping_mul(b),
+        1 => ((sa * sb) >> 32) as u32,
+        2 => (sa.wrapping_mul(ub as i64) >> 32) as u32,
+        3 => ((ua * ub) >> 32) as u32,
+        // division by zero gives all ones or the dividend instead of trapping
+        4 | 5 if b == 0 => u32::MAX,
+        6 | 7 if b == 0 => a,
+        4 => (a as i32).wrapping_div(b as i32) as u32,
+        5 => a / b,
+        6 => (a as i32).wrapping_rem(b as i32) as u32,
+        _ => a % b,
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Rv32 {
+    // RV32IM with `m`, otherwise RV32I
+    #[must_use]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(m: bool) -> Self {
+        Self {
+            m,
+            regs: [0; 32],
+            pc: 0,
+            phase: Phase::Fetch,
+            reset_vector: 0,
+            cycles: 0,
+            instructions: 0,
+            mstatus: 0,
+            mie: 0,
+            mip: 0,
+            mtvec: 0,
+            mscratch: 0,
+            mepc: 0,
+            mcause: 0,
+            mtval: 0,
+            trace: None,
+        }
+    }
+
+    pub fn set_print_trace(&mut self, enabled: bool) {
+        self.trace = if enabled { Some(Box::new(Print)) } else { None };
+    }
+
+    pub fn clear_trace(&mut self) {
+        self.trace = None;
+    }
+
+    #[must_use]
+    pub fn pc(&self) -> u32 {
+        self.pc
+    }
+
+    // abandons the instruction in flight, if any
+    pub fn set_pc(&mut self, pc: u32) {
+        self.pc = pc;
+        self.phase = Phase::Fetch;
+    }
+
+    #[must_use]
+    pub fn reg(&self, r: usize) -> u32 {
+        self.regs[r]
+    }
+
+    // writes to x0 are ignored like the hardware does
+    pub fn set_reg(&mut self, r: usize, value: u32) {
+        if r != 0 {
+            self.regs[r] = value;
+        }
+    }
+
+    // None for CSRs that do not exist
+    #[must_use]
+    pub fn csr(&self, csr: u32) -> Option<u32> {
+        self.read_csr(csr)
+    }
+
+    // where the core starts when reset, 0 by default
+    pub fn set_reset_vector(&mut self, pc: u32) {
+        self.reset_vector = pc;
+    }
+
+    // clock edges, like Mips::cycles
+    #[must_use]
+    pub fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    #[must_use]
+    pub fn instructions(&self) -> usize {
+        self.instructions
+    }
+}
+
+impl Component for Rv32 {
+    fn pin_count(&self) -> usize {
+        134
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        match pin {
+            1..=32 => format!("instr_addr[{}]", pin - 1),
+            33..=64 => format!("instr[{}]", pin - 33),
+            65..=96 => format!("data_addr[{}]", pin - 65),
+            97..=128 => format!("data[{}]", pin - 97),
+            129 => "data_write".to_string(),
+            130 => "clk".to_string(),
+            131 => "reset".to_string(),
+            132 => "meip".to_string(),
+            133 => "mtip".to_string(),
+            _ => "msip".to_string(),
+        }
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap,
+        clippy::cast_sign_loss
+    )]
+    fn update(&mut self, io: &mut IO) {
+        if !io.is_falling_edge(130) && !io.is_rising_edge(130) {
+            return;
+        }
+
+        self.mip = u32::from(io.read(132)) << 11
+            | u32::from(io.read(133)) << 7
+            | u32::from(io.read(134)) << 3;
+        if io.read(131) {
+            let trace = self.trace.take();
+            *self = Self::new(self.m);
+            self.pc = self.reset_vector;
+            self.trace = trace;
+            io.write(129, false);
+            return;
+        }
+
+        self.cycles += 1;
+        self.phase = match self.phase {
+            Phase::Fetch => {
+                io.write(129, false);
+                io.write_range(1..=32, self.pc);
+                Phase::Execute
+            }
+            Phase::Execute => {
+                let instr = io.read_range(33..=64);
+                self.emit(Event::Fetch {
+                    addr: self.pc,
+                    instr,
+                });
+                self.execute(io, instr)
+            }
+            Phase::Load { rd, addr, funct3 } => {
+                let word = io.read_range(97..=128) >> (8 * (addr & 0x3));
+                let value = match funct3 {
+                    0 => word as u8 as i8 as u32,
+                    1 => word as u16 as i16 as u32,
+                    4 => word & 0xff,
+                    5 => word & 0xffff,
+                    _ => word,
+                };
+
+                self.emit(Event::Load { addr, value });
+                self.write_reg(rd, value);
+                self.retire(io, self.pc.wrapping_sub(4))
+            }
+            Phase::Merge { addr, value, mask } => {
+                let shift = 8 * (addr & 0x3);
+                let word = io.read_range(97..=128) & !(mask << shift) | value << shift;
+
+                io.write(129, true);
+                io.write_range(97..=128, word);
+                self.emit(Event::Store { addr, value });
+                Phase::Release
+            }
+            Phase::Release => {
+                io.write(129, false);
+                self.retire(io, self.pc.wrapping_sub(4))
+            }
+        };
+    }
+}
@@ -0,0 +1,164 @@
+use super::{bus::WriteStrobe, *};
+use bindgen_macro::bindgen;
+
+const CLK: usize = 66;
+const IRQ: usize = 67;
+
+// registers, the priority of line n being at PRIORITY + n
+const PENDING: u32 = 0;
+const ENABLE: u32 = 1;
+const EDGE: u32 = 2;
+const CLAIM: u32 = 3;
+const PRIORITY: u32 = 4;
+
+// Gathers up to 32 interrupt lines into the single irq of a CPU, registered
+// as a slave of a SystemBus like a Ram. The lines are sampled on each rising
+// edge of the clock, a level-triggered line being pending while it is high
+// and an edge-triggered one from when it rises until the pending bit is
+// cleared. Among the pending lines that are enabled, the one of highest
+// priority is claimed, ties going to the lowest line. Register writes take
+// effect as the write strobe falls, like those of a Uart.
+//
+// registers:
+//  0: pending, writing a bit clears it for an edge-triggered line
+//  1: enable, a bit for each line allowed to interrupt
+//  2: edge, a bit for each edge-triggered line
+//  3: claim, the line to service plus one, or 0 when none is
+//  4-35: priority of each line, higher first
+//
+// pins:
+//  1-32: addr
+//  33-64: data
+//  65: write
+//  66: clk
+//  67: irq, high while an enabled line is pending
+//  68-: lines
+#[bindgen]
+pub struct InterruptController {
+    lines: usize,
+    // levels at the last clock edge
+    levels: u32,
+    latched: u32,
+    enable: u32,
+    edge: u32,
+    priority: Vec<u32>,
+    strobe: WriteStrobe,
+}
+
+impl InterruptController {
+    fn pending(&self) -> u32 {
+        self.latched & self.edge | self.levels & !self.edge
+    }
+
+    fn claim(&self) -> u32 {
+        let active = self.pending() & self.enable;
+        (0..32)
+            .filter(|&line| active & 1 << line != 0)
+            .min_by_key(|&line| std::cmp::Reverse(self.priority[line as usize]))
+            .map_or(0, |line| line + 1)
+    }
+
+    fn read(&self, reg: u32) -> u32 {
+        match reg {
+            PENDING => self.pending(),
+            ENABLE => self.enable,
+            EDGE => self.edge,
+            CLAIM => self.claim(),
+            _ => self
+                .priority
+                .get((reg - PRIORITY) as usize)
+                .copied()
+                .unwrap_or(0),
+        }
+    }
+
+    fn write(&mut self, reg: u32, value: u32) {
+        let mask = self.mask();
+        match reg {
+            PENDING => self.latched &= !value,
+            ENABLE => self.enable = value & mask,
+            EDGE => self.edge = value & mask,
+            CLAIM => {}
+            _ => {
+                if let Some(priority) = self.priority.get_mut((reg - PRIORITY) as usize) {
+                    *priority = value;
+                }
+            }
+        }
+    }
+
+    fn mask(&self) -> u32 {
+        u32::MAX >> (32 - self.lines)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl InterruptController {
+    // `lines` from 1 to 32, all level-triggered, disabled and of priority 0
+    /// # Panics
+    ///
+    /// Will panic if `lines` is 0 or more than 32
+    #[must_use]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(lines: usize) -> Self {
+        assert!(
+            (1..=32).contains(&lines),
+            "An InterruptController has 1 to 32 lines"
+        );
+
+        Self {
+            lines,
+            levels: 0,
+            latched: 0,
+            enable: 0,
+            edge: 0,
+            priority: vec![0; lines],
+            strobe: WriteStrobe::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    // the pin of line `line`
+    #[must_use]
+    pub fn line_pin(line: usize) -> usize {
+        IRQ + 1 + line
+    }
+}
+
+impl Component for InterruptController {
+    fn pin_count(&self) -> usize {
+        IRQ + self.lines
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        match pin {
+            1..=32 => format!("addr[{}]", pin - 1),
+            33..=64 => format!("data[{}]", pin - 33),
+            65 => "write".to_string(),
+            CLK => "clk".to_string(),
+            IRQ => "irq".to_string(),
+            _ => format!("line[{}]", pin - IRQ - 1),
+        }
+    }
+
+    fn update(&mut self, io: &mut IO) {
+        if io.is_rising_edge(CLK) {
+            let levels = io.read_range(IRQ + 1..=IRQ + self.lines);
+            self.latched |= levels & !self.levels;
+            self.levels = levels;
+        }
+
+        if let Some((addr, value)) = self.strobe.update(io) {
+            self.write(addr, value);
+        }
+        if !io.read(65) {
+            io.write_range(33..=64, self.read(io.read_range(1..=32)));
+        }
+
+        io.write(IRQ, self.pending() & self.enable != 0);
+    }
+}
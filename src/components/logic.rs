@@ -1,5 +1,68 @@
 use super::*;
 use bindgen_macro::{bindgen, constrgen};
+use std::ops;
+
+// Values gates operate on, `u64` evaluating 64 independent patterns bitwise
+pub trait Logic:
+    Copy
+    + ops::Not<Output = Self>
+    + ops::BitAnd<Output = Self>
+    + ops::BitOr<Output = Self>
+    + ops::BitXor<Output = Self>
+{
+}
+
+impl Logic for bool {}
+impl Logic for u64 {}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Gate {
+    Buffer,
+    Not,
+    And,
+    Or,
+    Nand,
+    Nor,
+    Xor,
+    Xnor,
+}
+
+impl Gate {
+    #[must_use]
+    pub fn inputs(self) -> usize {
+        match self {
+            Gate::Buffer | Gate::Not => 1,
+            _ => 2,
+        }
+    }
+
+    // output for the given inputs, single input gates ignore `b`
+    #[must_use]
+    pub fn eval<T: Logic>(self, a: T, b: T) -> T {
+        match self {
+            Gate::Buffer => a,
+            Gate::Not => !a,
+            Gate::And => a & b,
+            Gate::Or => a | b,
+            Gate::Nand => !(a & b),
+            Gate::Nor => !(a | b),
+            Gate::Xor => a ^ b,
+            Gate::Xnor => !(a ^ b),
+        }
+    }
+
+    // pins:
+    //  1-N: inputs
+    //  N+1: output
+    #[must_use]
+    pub fn pin_name(self, pin: usize) -> &'static str {
+        if pin > self.inputs() {
+            "y"
+        } else {
+            ["a", "b"][pin - 1]
+        }
+    }
+}
 
 #[bindgen]
 #[constrgen]
@@ -12,6 +75,10 @@ impl Component for Buffer {
     fn update(&mut self, io: &mut IO) {
         io.write(2, io.read(1));
     }
+
+    fn gate(&self) -> Option<Gate> {
+        Some(Gate::Buffer)
+    }
 }
 
 #[bindgen]
@@ -25,6 +92,10 @@ impl Component for Not {
     fn update(&mut self, io: &mut IO) {
         io.write(2, !io.read(1));
     }
+
+    fn gate(&self) -> Option<Gate> {
+        Some(Gate::Not)
+    }
 }
 
 #[bindgen]
@@ -38,6 +109,10 @@ impl Component for And {
     fn update(&mut self, io: &mut IO) {
         io.write(3, io.read(1) & io.read(2));
     }
+
+    fn gate(&self) -> Option<Gate> {
+        Some(Gate::And)
+    }
 }
 
 #[bindgen]
@@ -51,6 +126,10 @@ impl Component for Or {
     fn update(&mut self, io: &mut IO) {
         io.write(3, io.read(1) | io.read(2));
     }
+
+    fn gate(&self) -> Option<Gate> {
+        Some(Gate::Or)
+    }
 }
 
 #[bindgen]
@@ -64,6 +143,10 @@ impl Component for Nand {
     fn update(&mut self, io: &mut IO) {
         io.write(3, !(io.read(1) & io.read(2)));
     }
+
+    fn gate(&self) -> Option<Gate> {
+        Some(Gate::Nand)
+    }
 }
 
 #[bindgen]
@@ -77,6 +160,10 @@ impl Component for Nor {
     fn update(&mut self, io: &mut IO) {
         io.write(3, !(io.read(1) | io.read(2)));
     }
+
+    fn gate(&self) -> Option<Gate> {
+        Some(Gate::Nor)
+    }
 }
 
 #[bindgen]
@@ -90,6 +177,10 @@ impl Component for Xor {
     fn update(&mut self, io: &mut IO) {
         io.write(3, io.read(1) ^ io.read(2));
     }
+
+    fn gate(&self) -> Option<Gate> {
+        Some(Gate::Xor)
+    }
 }
 
 #[bindgen]
@@ -103,4 +194,64 @@ impl Component for Xnor {
     fn update(&mut self, io: &mut IO) {
         io.write(3, !(io.read(1) ^ io.read(2)));
     }
+
+    fn gate(&self) -> Option<Gate> {
+        Some(Gate::Xnor)
+    }
+}
+
+// pins:
+//  1-N: inputs
+//  N+1: output
+//
+// bit i of the table is the output for the inputs reading i, pin 1 being the
+// least significant bit
+#[bindgen]
+pub struct Lut {
+    inputs: usize,
+    table: Vec<u32>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Lut {
+    /// # Panics
+    ///
+    /// Will panic if `inputs` is more than 32
+    #[must_use]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(inputs: usize, table: &[u32]) -> Self {
+        assert!(inputs <= 32, "A Lut has at most 32 inputs");
+
+        let mut table = table.to_vec();
+        table.resize(Self::words(inputs), 0);
+
+        Self { inputs, table }
+    }
+}
+
+impl Lut {
+    // words taken by the table of a Lut with `inputs` inputs
+    #[must_use]
+    pub fn words(inputs: usize) -> usize {
+        (1_usize << inputs).div_ceil(32)
+    }
+}
+
+impl Component for Lut {
+    fn pin_count(&self) -> usize {
+        self.inputs + 1
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        if pin > self.inputs {
+            "y".to_string()
+        } else {
+            format!("i[{}]", pin - 1)
+        }
+    }
+
+    fn update(&mut self, io: &mut IO) {
+        let i = io.read_range(1..=self.inputs) as usize;
+        io.write(self.inputs + 1, self.table[i / 32] & (1 << (i % 32)) != 0);
+    }
 }
@@ -24,6 +24,22 @@ impl Ram {
     pub fn read(&self, addr: usize) -> u32 {
         self.data[addr]
     }
+
+    pub fn write(&mut self, addr: usize, value: u32) {
+        self.data[addr] = value;
+    }
+
+    // in words
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl Ram {
+    pub(crate) fn words_mut(&mut self) -> &mut [u32] {
+        &mut self.data
+    }
 }
 
 impl Component for Ram {
@@ -31,14 +47,158 @@ impl Component for Ram {
         65
     }
 
+    fn pin_name(&self, pin: usize) -> String {
+        match pin {
+            1..=32 => format!("addr[{}]", pin - 1),
+            33..=64 => format!("data[{}]", pin - 33),
+            _ => "write".to_string(),
+        }
+    }
+
     fn update(&mut self, io: &mut IO) {
-        let addr = io.read_u32(&(1..=32).collect::<Vec<_>>()) as usize;
-        let data_pins = (33..=64).collect::<Vec<_>>();
+        let addr = io.read_range(1..=32) as usize;
 
         if io.read(65) {
-            self.data[addr] = io.read_u32(&data_pins);
+            self.data[addr] = io.read_range(33..=64);
         } else {
-            io.write_u32(&data_pins, self.data[addr]);
+            io.write_range(33..=64, self.data[addr]);
         }
     }
 }
+
+// pins:
+//  1-32: addr
+//  33-64: data
+#[bindgen]
+pub struct Rom {
+    data: Vec<u32>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Rom {
+    #[must_use]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(data: &[u32]) -> Self {
+        Self {
+            data: data.to_vec(),
+        }
+    }
+
+    #[must_use]
+    pub fn read(&self, addr: usize) -> u32 {
+        self.data[addr]
+    }
+
+    // programs the contents, which the circuit itself cannot change
+    pub fn write(&mut self, addr: usize, value: u32) {
+        self.data[addr] = value;
+    }
+
+    // in words
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl Rom {
+    pub(crate) fn words_mut(&mut self) -> &mut [u32] {
+        &mut self.data
+    }
+}
+
+impl Component for Rom {
+    fn pin_count(&self) -> usize {
+        64
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        match pin {
+            1..=32 => format!("addr[{}]", pin - 1),
+            _ => format!("data[{}]", pin - 33),
+        }
+    }
+
+    fn update(&mut self, io: &mut IO) {
+        let addr = io.read_range(1..=32) as usize;
+        io.write_range(33..=64, self.data[addr]);
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Trigger {
+    Rising,
+    Falling,
+    High,
+    Low,
+}
+
+// pins:
+//  1-N: d
+//  N+1-2N: q
+//  2N+1: clk
+//  2N+2: enable
+//  2N+3: clear
+#[bindgen]
+pub struct Register {
+    width: usize,
+    trigger: Trigger,
+    value: u32,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Register {
+    #[must_use]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(width: usize, trigger: Trigger, value: u32) -> Self {
+        Self {
+            width,
+            trigger,
+            value,
+        }
+    }
+
+    #[must_use]
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+}
+
+impl Component for Register {
+    fn pin_count(&self) -> usize {
+        2 * self.width + 3
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        let n = self.width;
+
+        match pin {
+            p if p <= n => format!("d[{}]", p - 1),
+            p if p <= 2 * n => format!("q[{}]", p - n - 1),
+            p if p == 2 * n + 1 => "clk".to_string(),
+            p if p == 2 * n + 2 => "enable".to_string(),
+            _ => "clear".to_string(),
+        }
+    }
+
+    fn update(&mut self, io: &mut IO) {
+        let n = self.width;
+        let clk = 2 * n + 1;
+
+        let triggered = match self.trigger {
+            Trigger::Rising => io.is_rising_edge(clk),
+            Trigger::Falling => io.is_falling_edge(clk),
+            Trigger::High => io.read(clk),
+            Trigger::Low => !io.read(clk),
+        };
+
+        if io.read(2 * n + 3) {
+            self.value = 0;
+        } else if triggered && io.read(2 * n + 2) {
+            self.value = io.read_range(1..=n);
+        }
+
+        io.write_range(n + 1..=2 * n, self.value);
+    }
+}
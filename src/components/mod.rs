@@ -1,8 +1,13 @@
 use super::*;
 
+pub mod bus;
 pub mod cpu;
+pub mod intc;
 pub mod logic;
 pub mod mem;
+pub mod port;
+pub mod timer;
+pub mod uart;
 
 pub struct Static<const N: usize>(pub u32);
 impl<const N: usize> Component for Static<N> {
@@ -11,7 +16,7 @@ impl<const N: usize> Component for Static<N> {
     }
 
     fn update(&mut self, io: &mut IO) {
-        io.write_u32(&(1..=N).collect::<Vec<_>>(), self.0);
+        io.write_range(1..=N, self.0);
     }
 }
 
@@ -0,0 +1,54 @@
+use super::*;
+use bindgen_macro::bindgen;
+
+// pins:
+//  1-N: port bits (bit n => pin n + 1)
+#[bindgen]
+pub struct Input {
+    width: usize,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Input {
+    #[must_use]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(width: usize) -> Self {
+        Self { width }
+    }
+}
+
+impl Component for Input {
+    fn pin_count(&self) -> usize {
+        self.width
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        (pin - 1).to_string()
+    }
+}
+
+// pins:
+//  1-N: port bits (bit n => pin n + 1)
+#[bindgen]
+pub struct Output {
+    width: usize,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Output {
+    #[must_use]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(width: usize) -> Self {
+        Self { width }
+    }
+}
+
+impl Component for Output {
+    fn pin_count(&self) -> usize {
+        self.width
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        (pin - 1).to_string()
+    }
+}
@@ -0,0 +1,149 @@
+use super::{bus::WriteStrobe, *};
+use bindgen_macro::bindgen;
+
+const CLK: usize = 66;
+const IRQ: usize = 67;
+
+// registers, by the lowest three bits of the address
+const COUNT: u32 = 0;
+const COMPARE: u32 = 1;
+const PRESCALER: u32 = 2;
+const CONTROL: u32 = 3;
+const STATUS: u32 = 4;
+
+// bits of CONTROL
+const ENABLE: u32 = 1;
+const MATCH_INT: u32 = 1 << 1;
+const RESTART: u32 = 1 << 2;
+
+// bits of STATUS
+const MATCHED: u32 = 1;
+
+// A counter registered as a slave of a SystemBus like a Ram, counting once
+// every prescaler + 1 rising edges of its clock and flagging each time it
+// reaches the compare value. Restarting from 0 on a match, it interrupts
+// every compare * (prescaler + 1) clock cycles. Register writes take effect
+// as the write strobe falls, like those of a Uart.
+//
+// registers:
+//  0: count, writing it also restarts the prescaler
+//  1: compare
+//  2: prescaler, clock cycles per count less one
+//  3: control, bit 0 counting, 1 interrupting while matched and 2 restarting
+//     from 0 on a match
+//  4: status, bit 0 matched since last cleared by writing it
+//
+// pins:
+//  1-32: addr
+//  33-64: data
+//  65: write
+//  66: clk
+//  67: irq
+#[bindgen]
+#[derive(Default)]
+pub struct Timer {
+    count: u32,
+    compare: u32,
+    prescaler: u32,
+    // clock cycles since the last count
+    tick: u32,
+    control: u32,
+    matched: bool,
+    strobe: WriteStrobe,
+}
+
+impl Timer {
+    fn read(&self, reg: u32) -> u32 {
+        match reg {
+            COUNT => self.count,
+            COMPARE => self.compare,
+            PRESCALER => self.prescaler,
+            CONTROL => self.control,
+            STATUS => self.matched.into(),
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, reg: u32, value: u32) {
+        match reg {
+            COUNT => {
+                self.count = value;
+                self.tick = 0;
+            }
+            COMPARE => self.compare = value,
+            PRESCALER => self.prescaler = value,
+            CONTROL => self.control = value & (ENABLE | MATCH_INT | RESTART),
+            STATUS if value & MATCHED != 0 => self.matched = false,
+            _ => {}
+        }
+    }
+
+    fn clock(&mut self) {
+        if self.control & ENABLE == 0 {
+            return;
+        }
+        if self.tick < self.prescaler {
+            self.tick += 1;
+            return;
+        }
+        self.tick = 0;
+
+        self.count = self.count.wrapping_add(1);
+        if self.count == self.compare {
+            self.matched = true;
+            if self.control & RESTART != 0 {
+                self.count = 0;
+            }
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Timer {
+    #[must_use]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    #[must_use]
+    pub fn is_matched(&self) -> bool {
+        self.matched
+    }
+}
+
+impl Component for Timer {
+    fn pin_count(&self) -> usize {
+        67
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        match pin {
+            1..=32 => format!("addr[{}]", pin - 1),
+            33..=64 => format!("data[{}]", pin - 33),
+            65 => "write".to_string(),
+            CLK => "clk".to_string(),
+            _ => "irq".to_string(),
+        }
+    }
+
+    fn update(&mut self, io: &mut IO) {
+        if io.is_rising_edge(CLK) {
+            self.clock();
+        }
+
+        if let Some((addr, value)) = self.strobe.update(io) {
+            self.write(addr & 7, value);
+        }
+        if !io.read(65) {
+            io.write_range(33..=64, self.read(io.read_range(1..=32) & 7));
+        }
+
+        io.write(IRQ, self.matched && self.control & MATCH_INT != 0);
+    }
+}
@@ -0,0 +1,390 @@
+use super::{bus::WriteStrobe, *};
+use bindgen_macro::bindgen;
+use std::collections::VecDeque;
+
+const CLK: usize = 66;
+const TX: usize = 67;
+const RX: usize = 68;
+const IRQ: usize = 69;
+
+// registers, by the lowest two bits of the address
+const DATA: u32 = 0;
+const STATUS: u32 = 1;
+const DIVISOR: u32 = 2;
+const CONTROL: u32 = 3;
+
+// bits of STATUS
+const RX_READY: u32 = 1;
+const TX_READY: u32 = 1 << 1;
+const TX_IDLE: u32 = 1 << 2;
+const OVERRUN: u32 = 1 << 3;
+
+// bits of CONTROL
+const RX_INT: u32 = 1;
+const TX_INT: u32 = 1 << 1;
+
+const FIFO: usize = 16;
+
+// receives the bytes a Uart transmits, each once its stop bit is out
+pub trait Console {
+    fn byte(&mut self, byte: u8);
+}
+
+impl<F: FnMut(u8)> Console for F {
+    fn byte(&mut self, byte: u8) {
+        self(byte);
+    }
+}
+
+// prints them a line at a time, to stdout or the browser console
+#[derive(Default)]
+pub struct Stdout {
+    line: Vec<u8>,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str);
+}
+
+impl Console for Stdout {
+    fn byte(&mut self, byte: u8) {
+        if byte != b'\n' {
+            self.line.push(byte);
+            return;
+        }
+
+        let line = String::from_utf8_lossy(&self.line);
+        #[cfg(not(target_arch = "wasm32"))]
+        println!("{line}");
+        #[cfg(target_arch = "wasm32")]
+        log(&line);
+        self.line.clear();
+    }
+}
+
+// calls a JS function with each byte
+#[cfg(target_arch = "wasm32")]
+struct Callback(js_sys::Function);
+
+#[cfg(target_arch = "wasm32")]
+impl Console for Callback {
+    fn byte(&mut self, byte: u8) {
+        // nothing is there to report a throw to
+        let _ = self.0.call1(&JsValue::NULL, &JsValue::from(byte));
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+type Sink = Box<dyn Console + Send>;
+#[cfg(target_arch = "wasm32")]
+type Sink = Box<dyn Console>;
+
+// a frame on the line, one start bit, 8 data bits LSB first and a stop bit
+#[derive(Clone, Copy)]
+struct Frame {
+    byte: u8,
+    // bits done, the start bit being 0
+    bit: u32,
+    // clock cycles until the next bit
+    wait: u32,
+}
+
+// A serial port with 16 byte FIFOs each way, registered as a slave of a
+// SystemBus like a Ram. Reads have no side effects, so a received byte stays
+// in DATA until it is taken by writing RX_READY to STATUS. Register writes
+// take effect as the write strobe falls, with the address and data it was
+// last high with.
+//
+// registers:
+//  0: data, the oldest byte received (0 when none) or a byte to transmit,
+//     dropped if the transmit FIFO is full
+//  1: status, bit 0 a byte received, 1 room to transmit, 2 all transmitted
+//     and 3 a byte lost to a full receive FIFO; writing bit 0 takes the
+//     received byte and bit 3 clears it
+//  2: divisor, clock cycles per bit
+//  3: control, bit 0 interrupting while a byte is received and 1 while there
+//     is nothing left to transmit
+//
+// pins:
+//  1-32: addr
+//  33-64: data
+//  65: write
+//  66: clk
+//  67: tx, idling high
+//  68: rx, only listened to once it has been high
+//  69: irq
+#[bindgen]
+pub struct Uart {
+    divisor: u32,
+    control: u32,
+    overrun: bool,
+    tx_fifo: VecDeque<u8>,
+    rx_fifo: VecDeque<u8>,
+    tx: Option<Frame>,
+    rx: Option<Frame>,
+    rx_line: bool,
+    // from the host, coming in a frame apart
+    input: VecDeque<u8>,
+    input_wait: u32,
+    // transmitted while there is no console
+    output: Vec<u8>,
+    console: Option<Sink>,
+    strobe: WriteStrobe,
+}
+
+impl Uart {
+    pub fn set_console(&mut self, console: impl Console + Send + 'static) {
+        self.console = Some(Box::new(console));
+    }
+
+    fn status(&self) -> u32 {
+        let mut status = 0;
+        if !self.rx_fifo.is_empty() {
+            status |= RX_READY;
+        }
+        if self.tx_fifo.len() < FIFO {
+            status |= TX_READY;
+        }
+        if self.tx_fifo.is_empty() && self.tx.is_none() {
+            status |= TX_IDLE;
+        }
+        if self.overrun {
+            status |= OVERRUN;
+        }
+        status
+    }
+
+    fn read(&self, reg: u32) -> u32 {
+        match reg {
+            DATA => self.rx_fifo.front().copied().unwrap_or(0).into(),
+            STATUS => self.status(),
+            DIVISOR => self.divisor,
+            CONTROL => self.control,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, reg: u32, value: u32) {
+        match reg {
+            DATA if self.tx_fifo.len() < FIFO => self.tx_fifo.push_back(value.to_le_bytes()[0]),
+            STATUS => {
+                if value & RX_READY != 0 {
+                    self.rx_fifo.pop_front();
+                }
+                if value & OVERRUN != 0 {
+                    self.overrun = false;
+                }
+            }
+            DIVISOR => self.divisor = value.max(1),
+            CONTROL => self.control = value & (RX_INT | TX_INT),
+            _ => {}
+        }
+    }
+
+    fn receive(&mut self, byte: u8) {
+        if self.rx_fifo.len() < FIFO {
+            self.rx_fifo.push_back(byte);
+        } else {
+            self.overrun = true;
+        }
+    }
+
+    fn transmit(&mut self) {
+        let divisor = self.divisor;
+        match &mut self.tx {
+            None => {
+                if let Some(byte) = self.tx_fifo.pop_front() {
+                    self.tx = Some(Frame {
+                        byte,
+                        bit: 0,
+                        wait: divisor,
+                    });
+                }
+            }
+            Some(frame) => {
+                frame.wait -= 1;
+                if frame.wait > 0 {
+                    return;
+                }
+                frame.bit += 1;
+                frame.wait = divisor;
+
+                if frame.bit == 10 {
+                    let byte = frame.byte;
+                    self.tx = None;
+                    match &mut self.console {
+                        Some(console) => console.byte(byte),
+                        None => self.output.push(byte),
+                    }
+                    self.transmit();
+                }
+            }
+        }
+    }
+
+    // samples the middle of each bit, dropping frames without a stop bit
+    fn listen(&mut self, line: bool) {
+        if self.rx.is_none() && self.rx_line && !line {
+            self.rx = Some(Frame {
+                byte: 0,
+                bit: 0,
+                wait: self.divisor / 2,
+            });
+        }
+        self.rx_line = line;
+
+        let frame = match &mut self.rx {
+            Some(frame) if frame.wait > 0 => {
+                frame.wait -= 1;
+                return;
+            }
+            Some(frame) => frame,
+            None => return,
+        };
+        frame.wait = self.divisor - 1;
+
+        match frame.bit {
+            // a glitch rather than a start bit
+            0 if line => self.rx = None,
+            1..=8 => frame.byte |= u8::from(line) << (frame.bit - 1),
+            9 => {
+                let byte = frame.byte;
+                self.rx = None;
+                if line {
+                    self.receive(byte);
+                }
+                return;
+            }
+            _ => {}
+        }
+        if let Some(frame) = &mut self.rx {
+            frame.bit += 1;
+        }
+    }
+
+    fn line(&self) -> bool {
+        match self.tx {
+            Some(Frame { bit: 0, .. }) => false,
+            Some(Frame { byte, bit, .. }) if bit <= 8 => byte >> (bit - 1) & 1 != 0,
+            _ => true,
+        }
+    }
+
+    fn irq(&self) -> bool {
+        let status = self.status();
+        (self.control & RX_INT != 0 && status & RX_READY != 0)
+            || (self.control & TX_INT != 0 && status & TX_IDLE != 0)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Uart {
+    // `divisor` clock cycles per bit
+    #[must_use]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(divisor: u32) -> Self {
+        Self {
+            divisor: divisor.max(1),
+            control: 0,
+            overrun: false,
+            tx_fifo: VecDeque::new(),
+            rx_fifo: VecDeque::new(),
+            tx: None,
+            rx: None,
+            rx_line: false,
+            input: VecDeque::new(),
+            input_wait: 0,
+            output: Vec::new(),
+            console: None,
+            strobe: WriteStrobe::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn divisor(&self) -> u32 {
+        self.divisor
+    }
+
+    pub fn set_print_output(&mut self, enabled: bool) {
+        self.console = if enabled {
+            Some(Box::new(Stdout::default()))
+        } else {
+            None
+        };
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen(js_name = "set_console")]
+    pub fn set_console_js(&mut self, callback: js_sys::Function) {
+        self.console = Some(Box::new(Callback(callback)));
+    }
+
+    pub fn clear_console(&mut self) {
+        self.console = None;
+    }
+
+    // bytes to receive, as if sent at the same rate and only as fast as the
+    // receive FIFO is emptied
+    pub fn push_input(&mut self, bytes: &[u8]) {
+        self.input.extend(bytes);
+    }
+
+    // what was transmitted since the last call while there was no console
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+
+    // with nothing left to send or receive
+    #[must_use]
+    pub fn is_idle(&self) -> bool {
+        self.status() & TX_IDLE != 0 && self.rx.is_none() && self.input.is_empty()
+    }
+}
+
+impl Component for Uart {
+    fn pin_count(&self) -> usize {
+        69
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        match pin {
+            1..=32 => format!("addr[{}]", pin - 1),
+            33..=64 => format!("data[{}]", pin - 33),
+            65 => "write".to_string(),
+            CLK => "clk".to_string(),
+            TX => "tx".to_string(),
+            RX => "rx".to_string(),
+            _ => "irq".to_string(),
+        }
+    }
+
+    fn update(&mut self, io: &mut IO) {
+        if io.is_rising_edge(CLK) {
+            self.transmit();
+            self.listen(io.read(RX));
+
+            if self.input_wait > 0 {
+                self.input_wait -= 1;
+            } else if self.rx_fifo.len() < FIFO {
+                if let Some(byte) = self.input.pop_front() {
+                    self.receive(byte);
+                    // ten bits a frame, for whatever divisor the guest wrote
+                    self.input_wait = self.divisor.saturating_mul(10) - 1;
+                }
+            }
+        }
+
+        if let Some((addr, value)) = self.strobe.update(io) {
+            self.write(addr & 3, value);
+        }
+        if !io.read(65) {
+            io.write_range(33..=64, self.read(io.read_range(1..=32) & 3));
+        }
+
+        io.write(TX, self.line());
+        io.write(IRQ, self.irq());
+    }
+}
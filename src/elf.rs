@@ -0,0 +1,403 @@
+// Loader for 32-bit MIPS and RISC-V ELF executables. PT_LOAD segments are
+// copied by virtual address, the one a Mips or Rv32 puts on its buses, into Ram
+// or Rom components mapped into its address space, and symbols are kept so
+// addresses can be shown as function names.
+
+use crate::{
+    components::{
+        cpu::{Mips, Rv32},
+        mem::{Ram, Rom},
+    },
+    ComponentKey, Sim,
+};
+use std::fmt;
+
+const ET_EXEC: u16 = 2;
+const EM_MIPS: u16 = 8;
+const EM_RISCV: u16 = 243;
+const PT_LOAD: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+
+#[derive(Debug)]
+pub struct Error {
+    message: String,
+}
+
+impl Error {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Clone, Debug)]
+pub struct Segment {
+    addr: u32,
+    data: Vec<u8>,
+    // in memory, the bytes past `data` being zero
+    size: u32,
+}
+
+impl Segment {
+    #[must_use]
+    pub fn addr(&self) -> u32 {
+        self.addr
+    }
+
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    #[must_use]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Symbol {
+    name: String,
+    addr: u32,
+    size: u32,
+    function: bool,
+}
+
+impl Symbol {
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn addr(&self) -> u32 {
+        self.addr
+    }
+
+    #[must_use]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    #[must_use]
+    pub fn is_function(&self) -> bool {
+        self.function
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Elf {
+    entry: u32,
+    big_endian: bool,
+    segments: Vec<Segment>,
+    // functions and objects, ordered by address
+    symbols: Vec<Symbol>,
+}
+
+impl Elf {
+    /// # Errors
+    ///
+    /// Will return `Err` if `bytes` is not a 32-bit MIPS or RISC-V executable
+    pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < 52 || bytes[..4] != *b"\x7fELF" {
+            return Err(Error::new("not an ELF file"));
+        }
+        if bytes[4] != 1 {
+            return Err(Error::new("not a 32-bit ELF file"));
+        }
+
+        let r = Reader {
+            bytes,
+            big_endian: match bytes[5] {
+                1 => false,
+                2 => true,
+                _ => return Err(Error::new("unknown byte order")),
+            },
+        };
+        // relocatable objects and shared libraries have nowhere to go yet
+        if r.u16(16)? != ET_EXEC {
+            return Err(Error::new("not an executable"));
+        }
+        if !matches!(r.u16(18)?, EM_MIPS | EM_RISCV) {
+            return Err(Error::new("not a MIPS or RISC-V executable"));
+        }
+
+        let entry = r.u32(24)?;
+        let segments = r
+            .table(r.offset(28)?, r.u16(42)?, r.u16(44)?)?
+            .filter(|&ph| matches!(r.u32(ph), Ok(PT_LOAD)))
+            .map(|ph| {
+                let size = r.u32(ph + 20)?;
+                let data = r.slice(r.offset(ph + 4)?, r.offset(ph + 16)?)?;
+                if data.len() > size as usize {
+                    return Err(Error::new("segment larger in the file than in memory"));
+                }
+
+                Ok(Segment {
+                    addr: r.u32(ph + 8)?,
+                    data: data.to_vec(),
+                    size,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut elf = Self {
+            entry,
+            big_endian: r.big_endian,
+            segments,
+            symbols: Vec::new(),
+        };
+        elf.read_symbols(&r)?;
+        Ok(elf)
+    }
+
+    // stripped executables simply have none
+    fn read_symbols(&mut self, r: &Reader<'_>) -> Result<(), Error> {
+        let sections = r
+            .table(r.offset(32)?, r.u16(46)?, r.u16(48)?)?
+            .collect::<Vec<_>>();
+
+        for &sh in &sections {
+            if r.u32(sh + 4)? != SHT_SYMTAB {
+                continue;
+            }
+
+            let strings = *sections
+                .get(r.offset(sh + 24)?)
+                .ok_or_else(|| Error::new("symbol table without strings"))?;
+            let strings = r.slice(r.offset(strings + 16)?, r.offset(strings + 20)?)?;
+
+            let table = r.offset(sh + 16)?;
+            for i in 0..r.offset(sh + 20)? / 16 {
+                let sym = table + 16 * i;
+                let kind = r.slice(sym + 12, 1)?[0] & 0xf;
+                if kind != STT_FUNC && kind != STT_OBJECT {
+                    continue;
+                }
+
+                let name = strings
+                    .get(r.offset(sym)?..)
+                    .and_then(|s| s.split(|&b| b == 0).next())
+                    .ok_or_else(|| Error::new("symbol name out of bounds"))?;
+                if name.is_empty() {
+                    continue;
+                }
+
+                self.symbols.push(Symbol {
+                    name: String::from_utf8_lossy(name).into_owned(),
+                    addr: r.u32(sym + 4)?,
+                    size: r.u32(sym + 8)?,
+                    function: kind == STT_FUNC,
+                });
+            }
+        }
+
+        self.symbols.sort_by_key(|s| s.addr);
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn entry(&self) -> u32 {
+        self.entry
+    }
+
+    #[must_use]
+    pub fn is_big_endian(&self) -> bool {
+        self.big_endian
+    }
+
+    #[must_use]
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    #[must_use]
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    #[must_use]
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|s| s.name == name)
+    }
+
+    // the symbol `addr` falls within, functions of unknown size extending up
+    // to the next symbol
+    #[must_use]
+    pub fn lookup(&self, addr: u32) -> Option<&Symbol> {
+        let before = self.symbols.partition_point(|s| s.addr <= addr);
+
+        self.symbols[..before]
+            .iter()
+            .rev()
+            .enumerate()
+            .find(|&(i, s)| {
+                if s.size == 0 {
+                    i == 0 && s.function
+                } else {
+                    addr - s.addr < s.size
+                }
+            })
+            .map(|(_, s)| s)
+    }
+
+    // `addr` as `symbol+offset` when it has one
+    #[must_use]
+    pub fn describe(&self, addr: u32) -> String {
+        match self.lookup(addr) {
+            Some(s) if s.addr == addr => s.name.clone(),
+            Some(s) => format!("{}+{:#x}", s.name, addr - s.addr),
+            None => format!("{addr:#010x}"),
+        }
+    }
+
+    /// Copies every segment into the Ram and Rom components in `memory`, given
+    /// as pairs of a component and the address of its first word, and points
+    /// `cpu` at the entry point, also making it where a reset starts. Regions
+    /// should not overlap.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a segment is not entirely within the regions or
+    /// `cpu` is not a Mips or Rv32
+    // offsets all fit in 32 bits, the wider type only avoiding overflow at the
+    // top of the address space
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn load(
+        &self,
+        sim: &mut Sim,
+        cpu: ComponentKey,
+        memory: &[(ComponentKey, u32)],
+    ) -> Result<(), Error> {
+        for segment in &self.segments {
+            let start = u64::from(segment.addr);
+            let end = start + u64::from(segment.size);
+            let mut placed = 0;
+
+            for &(k, base) in memory {
+                let words = words(sim, k)
+                    .ok_or_else(|| Error::new(format!("component {k} is not a Ram or Rom")))?;
+                let base = u64::from(base);
+                let from = start.max(base);
+                let to = end.min(base + 4 * words.len() as u64);
+
+                for addr in from..to {
+                    let byte = segment
+                        .data
+                        .get((addr - start) as usize)
+                        .copied()
+                        .unwrap_or(0);
+                    let word = &mut words[((addr - base) / 4) as usize];
+                    let lane = (addr % 4) as usize;
+
+                    *word = if self.big_endian {
+                        let mut bytes = word.to_be_bytes();
+                        bytes[lane] = byte;
+                        u32::from_be_bytes(bytes)
+                    } else {
+                        let mut bytes = word.to_le_bytes();
+                        bytes[lane] = byte;
+                        u32::from_le_bytes(bytes)
+                    };
+                }
+                placed += to.saturating_sub(from);
+            }
+
+            if placed != end - start {
+                return Err(Error::new(format!(
+                    "segment at {:#010x} is not within the memory map",
+                    segment.addr
+                )));
+            }
+        }
+
+        if let Some(mips) = sim.component_mut::<Mips>(cpu) {
+            mips.set_pc(self.entry);
+            mips.set_reset_vector(self.entry);
+        } else if let Some(rv32) = sim.component_mut::<Rv32>(cpu) {
+            rv32.set_pc(self.entry);
+            rv32.set_reset_vector(self.entry);
+        } else {
+            return Err(Error::new("cpu is not a Mips or Rv32"));
+        }
+        Ok(())
+    }
+}
+
+fn words(sim: &mut Sim, k: ComponentKey) -> Option<&mut [u32]> {
+    if sim.component::<Ram>(k).is_some() {
+        return sim.component_mut::<Ram>(k).map(Ram::words_mut);
+    }
+
+    sim.component_mut::<Rom>(k).map(Rom::words_mut)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn slice(&self, offset: usize, len: usize) -> Result<&'a [u8], Error> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(offset..end))
+            .ok_or_else(|| Error::new("truncated file"))
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, Error> {
+        let b = self.slice(offset, 2)?;
+        let b = [b[0], b[1]];
+
+        Ok(if self.big_endian {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        })
+    }
+
+    // a u32 used as an offset or length into the file
+    fn offset(&self, offset: usize) -> Result<usize, Error> {
+        self.u32(offset).map(|v| v as usize)
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, Error> {
+        let b = self.slice(offset, 4)?;
+        let b = [b[0], b[1], b[2], b[3]];
+
+        Ok(if self.big_endian {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        })
+    }
+
+    // offsets of the entries of a header table
+    fn table(
+        &self,
+        offset: usize,
+        size: u16,
+        count: u16,
+    ) -> Result<impl Iterator<Item = usize>, Error> {
+        let (size, count) = (usize::from(size), usize::from(count));
+        self.slice(offset, size * count)?;
+
+        Ok((0..count).map(move |i| offset + i * size))
+    }
+}
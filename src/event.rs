@@ -0,0 +1,236 @@
+use crate::{
+    compiled::Program,
+    component::{Boxed, Wrapper},
+    ComponentKey, PinId,
+};
+use graph::Graph;
+use slab::Slab;
+
+// The parts of a Sim an event driven pass only reads, shared between threads
+// when partitions run in parallel
+#[derive(Clone, Copy)]
+pub(crate) struct Netlist<'a> {
+    pub(crate) graph: &'a Graph,
+    pub(crate) pin_to_component: &'a [Option<ComponentKey>],
+    // root of the clock net
+    pub(crate) clock: usize,
+    // whether a program is kept in sync with the nets written
+    pub(crate) compiled: bool,
+}
+
+// net values and components a pass may modify, indexed by net root and key
+pub(crate) trait State {
+    fn get(&self, net: usize) -> Option<bool>;
+    fn set(&mut self, net: usize, value: bool);
+    fn component(&self, k: ComponentKey) -> &Wrapper;
+    fn component_mut(&mut self, k: ComponentKey) -> &mut Wrapper;
+
+    // whether the component is part of this state, and updated by its passes
+    fn owns(&self, _k: ComponentKey) -> bool {
+        true
+    }
+}
+
+// the whole Sim
+pub(crate) struct Whole<'a> {
+    pub(crate) values: &'a mut [Option<bool>],
+    pub(crate) components: &'a mut Slab<Boxed>,
+}
+
+impl State for Whole<'_> {
+    fn get(&self, net: usize) -> Option<bool> {
+        self.values[net]
+    }
+
+    fn set(&mut self, net: usize, value: bool) {
+        self.values[net] = Some(value);
+    }
+
+    fn component(&self, k: ComponentKey) -> &Wrapper {
+        &self.components[k]
+    }
+
+    fn component_mut(&mut self, k: ComponentKey) -> &mut Wrapper {
+        &mut self.components[k]
+    }
+}
+
+// Buffers reused across passes, one frame per level of recursion, so steady
+// state propagation allocates nothing
+#[derive(Default)]
+pub(crate) struct Scratch {
+    frames: Vec<Frame>,
+    // nets changed by the last run of the program
+    outputs: Vec<usize>,
+    pub(crate) touched: Vec<usize>,
+    // nets written, for telling whether the circuit is stable
+    pub(crate) writes: usize,
+    // last value a component wrote to the clock, for the Sim to note and,
+    // after a pass on threads, to apply
+    pub(crate) clock: Option<bool>,
+}
+
+impl Scratch {
+    pub(crate) fn merge(&mut self, other: Scratch) {
+        self.touched.extend(other.touched);
+        self.writes += other.writes;
+        self.clock = other.clock.or(self.clock);
+    }
+}
+
+#[derive(Default)]
+struct Frame {
+    input: Vec<bool>,
+    changes: Vec<(PinId, bool)>,
+    affected: Vec<ComponentKey>,
+}
+
+// pins sharing a net with `pin`
+#[cfg(not(feature = "linear-scan"))]
+fn net_members(graph: &Graph, pin: PinId) -> impl Iterator<Item = PinId> + '_ {
+    graph.members(pin).iter().copied()
+}
+
+// the lookup from before nets kept their member lists, scanning every pin,
+// kept to benchmark against
+#[cfg(feature = "linear-scan")]
+fn net_members(graph: &Graph, pin: PinId) -> impl Iterator<Item = PinId> + '_ {
+    let net = graph.find(pin);
+    graph
+        .nodes()
+        .iter()
+        .copied()
+        .filter(move |&p| graph.find(p) == net)
+}
+
+// Event driven propagation, updating every component reading a changed net
+// until nothing changes. Given the program, the gates it compiled are run
+// from it as soon as their inputs change, where the event driven engine would
+// update them. Otherwise, nets written while a program exists are collected
+// so it can be told about them afterwards.
+pub(crate) struct Pass<'a, S> {
+    netlist: Netlist<'a>,
+    program: Option<&'a mut Program>,
+    state: S,
+    scratch: Scratch,
+    depth: usize,
+}
+
+impl<'a, S: State> Pass<'a, S> {
+    pub(crate) fn new(
+        netlist: Netlist<'a>,
+        program: Option<&'a mut Program>,
+        state: S,
+        scratch: Scratch,
+    ) -> Self {
+        Self {
+            netlist,
+            program,
+            state,
+            scratch,
+            depth: 0,
+        }
+    }
+
+    // the buffers back, their `touched` listing the nets written
+    pub(crate) fn finish(self) -> Scratch {
+        self.scratch
+    }
+
+    fn read(&self, pin: PinId) -> Option<bool> {
+        self.state.get(self.netlist.graph.find(pin))
+    }
+
+    fn write(&mut self, pin: PinId, value: bool) {
+        let net = self.netlist.graph.find(pin);
+        self.state.set(net, value);
+        self.scratch.writes += 1;
+        if net == self.netlist.clock && self.depth > 0 {
+            self.scratch.clock = Some(value);
+        }
+
+        if let Some(program) = self.program.as_deref_mut() {
+            program.touch(net);
+        } else if self.netlist.compiled {
+            self.scratch.touched.push(net);
+        }
+    }
+
+    pub(crate) fn update_component(&mut self, key: ComponentKey) {
+        if self.scratch.frames.len() <= self.depth {
+            self.scratch.frames.push(Frame::default());
+        }
+        let mut frame = std::mem::take(&mut self.scratch.frames[self.depth]);
+        self.depth += 1;
+
+        frame.input.clear();
+        let pins = self.state.component(key).pins();
+        frame
+            .input
+            .extend(pins.iter().map(|&p| self.read(p).unwrap_or(false)));
+
+        let c = self.state.component_mut(key);
+        c.set_input(&frame.input);
+        frame.changes.clear();
+        c.update(&mut frame.changes);
+
+        self.apply(&frame.changes, false, &mut frame.affected);
+        for &k in &frame.affected {
+            self.update_component(k);
+        }
+
+        self.depth -= 1;
+        self.scratch.frames[self.depth] = frame;
+    }
+
+    // writes the changes and lists the components they affect in `affected`,
+    // ordered by key
+    pub(crate) fn apply(
+        &mut self,
+        changes: &[(PinId, bool)],
+        update_self: bool,
+        affected: &mut Vec<ComponentKey>,
+    ) {
+        let Netlist {
+            graph,
+            pin_to_component,
+            ..
+        } = self.netlist;
+        affected.clear();
+
+        for &(pin, value) in changes {
+            if self.read(pin) == Some(value) {
+                continue;
+            }
+            self.write(pin, value);
+
+            let program = self.program.as_deref();
+            let state = &self.state;
+            affected.extend(
+                net_members(graph, pin)
+                    .filter(|&p| update_self || p != pin) // the updated pin should not trigger an update on itself
+                    .filter_map(|p| pin_to_component[p])
+                    .filter(|&k| pin == 0 || update_self || Some(k) != pin_to_component[pin]) // prevent self updates
+                    .filter(|&k| !program.is_some_and(|p| p.is_compiled(k)))
+                    .filter(|&k| state.owns(k)),
+            );
+        }
+
+        if let Some(program) = self.program.as_deref_mut() {
+            let outputs = &mut self.scratch.outputs;
+            program.run(&mut self.state, outputs);
+            self.scratch.writes += outputs.len();
+
+            for net in outputs.drain(..) {
+                affected.extend(
+                    net_members(graph, net)
+                        .filter_map(|p| pin_to_component[p])
+                        .filter(|&k| !program.is_compiled(k)),
+                );
+            }
+        }
+
+        affected.sort_unstable();
+        affected.dedup();
+    }
+}
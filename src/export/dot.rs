@@ -0,0 +1,134 @@
+use crate::{ComponentKey, ScopeId, Sim};
+use fnv::FnvHashMap;
+use std::fmt::Write;
+
+pub(crate) fn dot(sim: &Sim, clusters: bool, values: bool) -> String {
+    let mut d = Dot {
+        sim,
+        out: String::new(),
+        components: FnvHashMap::default(),
+        scopes: FnvHashMap::default(),
+    };
+
+    for k in sim.keys() {
+        let scope = if clusters { sim.scope_of(k) } else { None };
+        d.components.entry(scope).or_default().push(k);
+    }
+    if clusters {
+        for id in 0..sim.scopes().len() {
+            d.scopes.entry(sim.scope(id).parent()).or_default().push(id);
+        }
+    }
+
+    d.out
+        .push_str("graph sim {\n  rankdir=LR;\n  node [shape=record];\n");
+    d.scope(None, 1);
+    d.nets(values);
+    d.out.push_str("}\n");
+    d.out
+}
+
+// characters with a meaning inside record labels
+fn escape(s: &str) -> String {
+    s.chars()
+        .flat_map(|c| {
+            let escaped = matches!(c, '{' | '}' | '|' | '<' | '>' | '"' | '\\' | ' ');
+            Some('\\').filter(|_| escaped).into_iter().chain(Some(c))
+        })
+        .collect()
+}
+
+struct Dot<'a> {
+    sim: &'a Sim,
+    out: String,
+    components: FnvHashMap<Option<ScopeId>, Vec<ComponentKey>>,
+    scopes: FnvHashMap<Option<ScopeId>, Vec<ScopeId>>,
+}
+
+impl Dot<'_> {
+    fn scope(&mut self, scope: Option<ScopeId>, depth: usize) {
+        let indent = "  ".repeat(depth);
+
+        for id in self.scopes.get(&scope).cloned().unwrap_or_default() {
+            let s = self.sim.scope(id);
+            writeln!(self.out, "{indent}subgraph cluster_{id} {{").unwrap();
+            writeln!(
+                self.out,
+                "{}  label=\"{} : {}\";",
+                indent,
+                s.name().replace('"', "\\\""),
+                s.module().replace('"', "\\\"")
+            )
+            .unwrap();
+            self.scope(Some(id), depth + 1);
+            writeln!(self.out, "{indent}}}").unwrap();
+        }
+
+        for k in self.components.get(&scope).cloned().unwrap_or_default() {
+            let c = self.sim.get(k);
+            let title = match self.sim.label(k) {
+                Some(label) => format!("{}\\n{}", escape(label), c.name()),
+                None => c.name().to_string(),
+            };
+            let pins = (1..=c.pin_count())
+                .map(|p| format!("<p{}> {}", p, escape(&c.pin_name(p))))
+                .collect::<Vec<_>>()
+                .join("|");
+
+            writeln!(self.out, "{indent}c{k} [label=\"{{{title}|{{{pins}}}}}\"];").unwrap();
+        }
+    }
+
+    // nets joining two pins become an edge, wider ones a point all pins connect to
+    fn nets(&mut self, values: bool) {
+        let mut order = Vec::new();
+        let mut members = FnvHashMap::<usize, Vec<String>>::default();
+
+        let clk = self.sim.net(0);
+        order.push(clk);
+        members.insert(clk, vec!["clk".to_string()]);
+
+        for k in self.sim.keys() {
+            for (i, &p) in self.sim.pins(k).iter().enumerate() {
+                let net = self.sim.net(p);
+                members
+                    .entry(net)
+                    .or_insert_with(|| {
+                        order.push(net);
+                        Vec::new()
+                    })
+                    .push(format!("c{}:p{}", k, i + 1));
+            }
+        }
+
+        if members[&clk].len() > 1 {
+            self.out.push_str("  clk [shape=circle];\n");
+        }
+
+        for net in order {
+            let pins = &members[&net];
+            if pins.len() < 2 {
+                continue;
+            }
+
+            let style = if values {
+                match self.sim.net_value(net) {
+                    Some(true) => " [color=green3, penwidth=2]",
+                    Some(false) => " [color=black]",
+                    None => " [color=gray, style=dashed]",
+                }
+            } else {
+                ""
+            };
+
+            if let [a, b] = pins.as_slice() {
+                writeln!(self.out, "  {a} -- {b}{style};").unwrap();
+            } else {
+                writeln!(self.out, "  n{net} [shape=point];").unwrap();
+                for p in pins {
+                    writeln!(self.out, "  {p} -- n{net}{style};").unwrap();
+                }
+            }
+        }
+    }
+}
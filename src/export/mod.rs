@@ -0,0 +1,5 @@
+mod dot;
+mod verilog;
+
+pub(crate) use dot::dot;
+pub(crate) use verilog::verilog;
@@ -0,0 +1,526 @@
+use crate::{
+    components::{
+        logic::Gate,
+        port::{Input, Output},
+        Static,
+    },
+    Component, ComponentKey, ScopeId, Sim,
+};
+use fnv::{FnvHashMap, FnvHashSet};
+use std::fmt::Write;
+
+const KEYWORDS: [&str; 24] = [
+    "module",
+    "endmodule",
+    "input",
+    "output",
+    "inout",
+    "wire",
+    "reg",
+    "assign",
+    "always",
+    "initial",
+    "begin",
+    "end",
+    "if",
+    "else",
+    "case",
+    "buf",
+    "not",
+    "and",
+    "or",
+    "nand",
+    "nor",
+    "xor",
+    "xnor",
+    "parameter",
+];
+
+pub(crate) fn verilog(sim: &Sim, top: &str) -> String {
+    let keys = sim.keys().collect::<Vec<_>>();
+
+    let mut members = FnvHashMap::<usize, Vec<ComponentKey>>::default();
+    for &k in &keys {
+        for &p in sim.pins(k) {
+            members.entry(sim.net(p)).or_default().push(k);
+        }
+    }
+
+    let mut e = Exporter {
+        sim,
+        keys,
+        members,
+        clk: sim.net(0),
+        modules: Vec::new(),
+        bodies: FnvHashMap::default(),
+        module_names: FnvHashSet::default(),
+        stubs: FnvHashMap::default(),
+        stub_defs: Vec::new(),
+    };
+
+    e.module(None, top);
+
+    e.stub_defs
+        .iter()
+        .chain(&e.modules)
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn ident(s: &str) -> String {
+    let valid = matches!(s.chars().next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        && !KEYWORDS.contains(&s);
+
+    if valid {
+        s.to_string()
+    } else {
+        // escaped identifiers end at the next whitespace
+        format!("\\{} ", s.replace(char::is_whitespace, "_"))
+    }
+}
+
+fn primitive(gate: Gate) -> &'static str {
+    match gate {
+        Gate::Buffer => "buf",
+        Gate::Not => "not",
+        Gate::And => "and",
+        Gate::Or => "or",
+        Gate::Nand => "nand",
+        Gate::Nor => "nor",
+        Gate::Xor => "xor",
+        Gate::Xnor => "xnor",
+    }
+}
+
+// "data[3]" => ("data", Some(3))
+fn split_bus(name: &str) -> (&str, Option<usize>) {
+    name.strip_suffix(']')
+        .and_then(|n| n.rfind('[').map(|i| (&n[..i], n[i + 1..].parse().ok())))
+        .filter(|(_, i)| i.is_some())
+        .unwrap_or((name, None))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Dir {
+    Input,
+    Output,
+    Inout,
+}
+
+impl Dir {
+    fn keyword(self) -> &'static str {
+        match self {
+            Dir::Input => "input",
+            Dir::Output => "output",
+            Dir::Inout => "inout",
+        }
+    }
+}
+
+// ports of a behavioural component, grouped into buses: (name, pins with their bit index)
+type Bus = (String, Vec<(usize, Option<usize>)>);
+
+#[derive(Default)]
+struct Module {
+    names: FnvHashMap<usize, String>,
+    instances: FnvHashSet<String>,
+    header: Vec<String>,
+    decls: String,
+    body: String,
+    ports: Vec<usize>,
+}
+
+struct Exporter<'a> {
+    sim: &'a Sim,
+    keys: Vec<ComponentKey>,
+    members: FnvHashMap<usize, Vec<ComponentKey>>,
+    clk: usize,
+    modules: Vec<String>,
+    bodies: FnvHashMap<String, String>,
+    module_names: FnvHashSet<String>,
+    // module names by component name and port signature
+    stubs: FnvHashMap<(&'static str, String), String>,
+    stub_defs: Vec<String>,
+}
+
+impl Exporter<'_> {
+    fn within(&self, c: ComponentKey, scope: Option<ScopeId>) -> bool {
+        if scope.is_none() {
+            return true;
+        }
+
+        let mut s = self.sim.scope_of(c);
+        while let Some(id) = s {
+            if s == scope {
+                return true;
+            }
+            s = self.sim.scope(id).parent();
+        }
+
+        false
+    }
+
+    // direction of a pin as seen from outside its component, if it has any
+    fn role(c: &dyn Component, pin: usize) -> Option<Dir> {
+        let any = c.as_any();
+
+        if any.is::<Input>() || any.is::<Output>() {
+            None
+        } else if any.is::<Static<1>>() {
+            Some(Dir::Output)
+        } else if let Some(g) = c.gate() {
+            Some(if pin > g.inputs() {
+                Dir::Output
+            } else {
+                Dir::Input
+            })
+        } else {
+            Some(Dir::Inout)
+        }
+    }
+
+    fn unique_module_name(&mut self, base: &str) -> String {
+        let mut name = ident(base);
+        let mut n = 1;
+        while self.module_names.contains(&name) {
+            name = ident(&format!("{base}_{n}"));
+            n += 1;
+        }
+
+        self.module_names.insert(name.clone());
+        name
+    }
+
+    fn buses(c: &dyn Component) -> Vec<Bus> {
+        let mut buses: Vec<Bus> = Vec::new();
+
+        for pin in 1..=c.pin_count() {
+            let name = c.pin_name(pin);
+            let (base, bit) = split_bus(&name);
+
+            match buses.iter_mut().find(|(b, _)| b == base) {
+                Some((_, pins)) if bit.is_some() => pins.push((pin, bit)),
+                _ => buses.push((base.to_string(), vec![(pin, bit)])),
+            }
+        }
+
+        buses
+    }
+
+    fn stub(&mut self, c: &dyn Component) -> String {
+        let buses = Self::buses(c);
+        let signature = buses
+            .iter()
+            .map(|(name, pins)| match pins[0].1 {
+                Some(_) => format!("{}[{}]", name, pins.len()),
+                None => name.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let key = (c.name(), signature);
+        if let Some(name) = self.stubs.get(&key) {
+            return name.clone();
+        }
+
+        let name = self.unique_module_name(c.name());
+
+        let mut def = format!(
+            "module {} ({});\n",
+            name,
+            buses
+                .iter()
+                .map(|(n, _)| ident(n))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        for (n, pins) in &buses {
+            match pins.iter().filter_map(|(_, b)| *b).max() {
+                Some(msb) => writeln!(def, "  inout [{}:0] {};", msb, ident(n)).unwrap(),
+                None => writeln!(def, "  inout {};", ident(n)).unwrap(),
+            }
+        }
+        def.push_str("endmodule\n");
+
+        self.stub_defs.push(def);
+        self.stubs.insert(key, name.clone());
+        name
+    }
+
+    // emits the module for `scope` and returns its name and port nets
+    fn module(&mut self, scope: Option<ScopeId>, top: &str) -> (String, Vec<usize>) {
+        let inside = self
+            .keys
+            .iter()
+            .copied()
+            .filter(|&k| self.within(k, scope))
+            .collect::<Vec<_>>();
+
+        let mut m = Module::default();
+        if scope.is_none() {
+            self.top_ports(&inside, &mut m);
+        }
+        self.nets(scope, &inside, &mut m);
+        self.instances(scope, &inside, &mut m);
+        self.children(scope, &inside, top, &mut m);
+
+        let text = format!(
+            "({});\n{}{}endmodule\n",
+            m.header.join(", "),
+            m.decls,
+            m.body
+        );
+
+        let name = match scope {
+            None => self.unique_module_name(top),
+            Some(s) => {
+                if let Some(name) = self.bodies.get(&text) {
+                    return (name.clone(), m.ports);
+                }
+
+                let name = self.unique_module_name(self.sim.scope(s).module());
+                self.bodies.insert(text.clone(), name.clone());
+                name
+            }
+        };
+
+        self.modules.push(format!("module {name} {text}"));
+        (name, m.ports)
+    }
+
+    // Input and Output components of the top module become its ports
+    fn top_ports(&self, inside: &[ComponentKey], m: &mut Module) {
+        let sim = self.sim;
+
+        for &k in inside.iter().filter(|&&k| sim.scope_of(k).is_none()) {
+            let c = sim.get(k);
+            let dir = if c.as_any().is::<Input>() {
+                Dir::Input
+            } else if c.as_any().is::<Output>() {
+                Dir::Output
+            } else {
+                continue;
+            };
+
+            let label = ident(
+                &sim.label(k)
+                    .map_or_else(|| format!("port{k}"), str::to_string),
+            );
+            let pins = sim.pins(k);
+
+            if pins.len() == 1 {
+                writeln!(m.decls, "  {} {};", dir.keyword(), label).unwrap();
+            } else {
+                writeln!(
+                    m.decls,
+                    "  {} [{}:0] {};",
+                    dir.keyword(),
+                    pins.len() - 1,
+                    label
+                )
+                .unwrap();
+            }
+            m.header.push(label.clone());
+
+            for (i, &p) in pins.iter().enumerate() {
+                let bit = if pins.len() == 1 {
+                    label.clone()
+                } else {
+                    format!("{label}[{i}]")
+                };
+
+                match m.names.get(&sim.net(p)) {
+                    Some(n) if dir == Dir::Output => {
+                        writeln!(m.body, "  assign {bit} = {n};").unwrap();
+                    }
+                    Some(_) => {}
+                    None => {
+                        m.names.insert(sim.net(p), bit);
+                    }
+                }
+            }
+        }
+    }
+
+    // child scope of `scope` holding `c`, or None if `c` belongs to `scope` itself
+    fn child_of(&self, c: ComponentKey, scope: Option<ScopeId>) -> Option<ScopeId> {
+        let mut s = self.sim.scope_of(c);
+        while let Some(id) = s {
+            if self.sim.scope(id).parent() == scope {
+                return s;
+            }
+            s = self.sim.scope(id).parent();
+        }
+
+        None
+    }
+
+    // nets shared with components outside `scope` become ports, nets used by
+    // its own components or shared between its children become wires
+    fn nets(&self, scope: Option<ScopeId>, inside: &[ComponentKey], m: &mut Module) {
+        let sim = self.sim;
+        let mut wires = 0;
+
+        for &k in inside {
+            for &p in sim.pins(k) {
+                let net = sim.net(p);
+                if m.names.contains_key(&net) {
+                    continue;
+                }
+
+                let crossing = scope.is_some()
+                    && (net == self.clk
+                        || self.members[&net].iter().any(|&c| !self.within(c, scope)));
+
+                let used = crossing || {
+                    let child = self.child_of(k, scope);
+                    child.is_none()
+                        || self.members[&net]
+                            .iter()
+                            .any(|&c| self.child_of(c, scope) != child)
+                };
+
+                if !used {
+                    continue;
+                }
+
+                let name = if crossing {
+                    let name = format!("p{}", m.ports.len());
+                    let driven = |dir| {
+                        self.members[&net].iter().any(|&c| {
+                            self.within(c, scope)
+                                && sim.pins(c).iter().enumerate().any(|(i, &q)| {
+                                    sim.net(q) == net && Self::role(sim.get(c), i + 1) == Some(dir)
+                                })
+                        })
+                    };
+                    let dir = if driven(Dir::Output) {
+                        Dir::Output
+                    } else if driven(Dir::Inout) {
+                        Dir::Inout
+                    } else {
+                        Dir::Input
+                    };
+
+                    writeln!(m.decls, "  {} {};", dir.keyword(), name).unwrap();
+                    m.header.push(name.clone());
+                    m.ports.push(net);
+                    name
+                } else if net == self.clk {
+                    m.decls.push_str("  input clk;\n");
+                    m.header.push("clk".to_string());
+                    "clk".to_string()
+                } else {
+                    let name = format!("w{wires}");
+                    wires += 1;
+                    writeln!(m.decls, "  wire {name};").unwrap();
+                    name
+                };
+
+                m.names.insert(net, name);
+            }
+        }
+    }
+
+    fn instance_name(&self, k: ComponentKey, prefix: &str, m: &mut Module) -> String {
+        // unlabelled instances are numbered per module so identical scopes export identically
+        let mut name = ident(&self.sim.label(k).map_or_else(
+            || format!("{}{}", prefix, m.instances.len()),
+            str::to_string,
+        ));
+
+        if !m.instances.insert(name.clone()) {
+            name = ident(&format!("{}_{}", name.trim_end(), k));
+            m.instances.insert(name.clone());
+        }
+
+        name
+    }
+
+    fn instances(&mut self, scope: Option<ScopeId>, inside: &[ComponentKey], m: &mut Module) {
+        let sim = self.sim;
+
+        for &k in inside.iter().filter(|&&k| sim.scope_of(k) == scope) {
+            let c = sim.get(k);
+            let pins = sim.pins(k);
+            let net = |pin: usize| m.names[&sim.net(pins[pin - 1])].clone();
+
+            let line = if c.as_any().is::<Input>() || c.as_any().is::<Output>() {
+                continue;
+            } else if let Some(s) = c.as_any().downcast_ref::<Static<1>>() {
+                format!("  assign {} = 1'b{};\n", net(1), s.0 & 1)
+            } else if let Some(g) = c.gate() {
+                let terminals = std::iter::once(g.inputs() + 1)
+                    .chain(1..=g.inputs())
+                    .map(net)
+                    .collect::<Vec<_>>();
+
+                format!(
+                    "  {} {} ({});\n",
+                    primitive(g),
+                    self.instance_name(k, "g", m),
+                    terminals.join(", ")
+                )
+            } else {
+                let connections = Self::buses(c)
+                    .iter()
+                    .map(|(n, bits)| {
+                        let expr = if bits[0].1.is_some() {
+                            let mut bits = bits.clone();
+                            bits.sort_by_key(|(_, b)| std::cmp::Reverse(*b));
+                            let bits = bits.iter().map(|(p, _)| net(*p)).collect::<Vec<_>>();
+                            format!("{{{}}}", bits.join(", "))
+                        } else {
+                            net(bits[0].0)
+                        };
+                        format!(".{}({})", ident(n), expr)
+                    })
+                    .collect::<Vec<_>>();
+
+                format!(
+                    "  {} {} ({});\n",
+                    self.stub(c),
+                    self.instance_name(k, "u", m),
+                    connections.join(", ")
+                )
+            };
+
+            m.body.push_str(&line);
+        }
+    }
+
+    fn children(
+        &mut self,
+        scope: Option<ScopeId>,
+        inside: &[ComponentKey],
+        top: &str,
+        m: &mut Module,
+    ) {
+        let sim = self.sim;
+        let children = (0..sim.scopes().len())
+            .filter(|&s| sim.scope(s).parent() == scope)
+            .filter(|&s| inside.iter().any(|&k| self.within(k, Some(s))))
+            .collect::<Vec<_>>();
+
+        for s in children {
+            let (module, ports) = self.module(Some(s), top);
+            let connections = ports
+                .iter()
+                .enumerate()
+                .map(|(i, n)| format!(".p{}({})", i, m.names[n]))
+                .collect::<Vec<_>>();
+
+            writeln!(
+                m.body,
+                "  {} {} ({});",
+                module,
+                ident(sim.scope(s).name()),
+                connections.join(", ")
+            )
+            .unwrap();
+        }
+    }
+}
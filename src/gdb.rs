@@ -0,0 +1,454 @@
+// GDB remote serial protocol stub for a Mips in a Sim. Memory is whatever Ram
+// components are mapped into the CPU's address space, read as big endian
+// words, and breakpoints of either kind are checked against the PC whenever an
+// instruction retires.
+//
+//     gdb-multiarch -ex 'set endian big' -ex 'target remote :1234' program.elf
+
+use crate::{
+    components::{cpu::Mips, mem::Ram},
+    ComponentKey, Sim, StopReason,
+};
+use std::{
+    convert::TryFrom,
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+
+// ticks between checks for an interrupt from the debugger while running
+const POLL_TICKS: usize = 4096;
+
+// gdb's numbering of the MIPS registers this stub knows about
+const STATUS: usize = 32;
+const LO: usize = 33;
+const HI: usize = 34;
+const CAUSE: usize = 36;
+const PC: usize = 37;
+const REGISTERS: usize = 38;
+
+// connection to the debugger, able to tell whether it asked to stop
+pub trait Link: Read + Write {
+    fn interrupted(&mut self) -> bool;
+}
+
+// A TcpStream as a Link. Checking for an interrupt takes a byte off the
+// stream, which is kept for the next read when it is something else, such as
+// the start of a packet.
+pub struct TcpLink {
+    stream: TcpStream,
+    pushback: Option<u8>,
+}
+
+impl TcpLink {
+    #[must_use]
+    pub fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            pushback: None,
+        }
+    }
+}
+
+impl Read for TcpLink {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match (self.pushback, buf.first_mut()) {
+            (Some(byte), Some(first)) => {
+                *first = byte;
+                self.pushback = None;
+                Ok(1)
+            }
+            _ => self.stream.read(buf),
+        }
+    }
+}
+
+impl Write for TcpLink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl Link for TcpLink {
+    // a byte already kept back is not an interrupt, and comes first anyway
+    fn interrupted(&mut self) -> bool {
+        let mut byte = [0];
+
+        if self.pushback.is_some() || self.stream.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let read = self.stream.read(&mut byte);
+        self.stream.set_nonblocking(false).ok();
+
+        match read {
+            Ok(1) if byte[0] == 0x03 => true,
+            Ok(1) => {
+                self.pushback = Some(byte[0]);
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+// a reader and a writer used together, such as stdin and stdout when gdb
+// starts the stub with `target remote | command`; it cannot be interrupted
+pub struct Pipe<R, W>(pub R, pub W);
+
+impl<R: Read, W> Read for Pipe<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<R, W: Write> Write for Pipe<R, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.1.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.1.flush()
+    }
+}
+
+impl<R: Read, W: Write> Link for Pipe<R, W> {
+    fn interrupted(&mut self) -> bool {
+        false
+    }
+}
+
+pub struct GdbStub {
+    cpu: ComponentKey,
+    // Ram components and the address of their first word
+    memory: Vec<(ComponentKey, u32)>,
+    breakpoints: Vec<u32>,
+    ack: bool,
+}
+
+impl GdbStub {
+    #[must_use]
+    pub fn new(cpu: ComponentKey) -> Self {
+        Self {
+            cpu,
+            memory: Vec::new(),
+            breakpoints: Vec::new(),
+            ack: true,
+        }
+    }
+
+    // makes `ram` visible to the debugger from `base` on, earlier mappings
+    // taking precedence where they overlap
+    pub fn map_ram(&mut self, ram: ComponentKey, base: u32) {
+        self.memory.push((ram, base));
+    }
+
+    /// Serves a single debugger connecting to `addr`, returning once it
+    /// detaches or kills the program.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the socket fails
+    pub fn listen(&mut self, sim: &mut Sim, addr: impl ToSocketAddrs) -> io::Result<()> {
+        let (stream, _) = TcpListener::bind(addr)?.accept()?;
+        stream.set_nodelay(true)?;
+        self.serve(sim, &mut TcpLink::new(stream))
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if reading from or writing to `link` fails
+    pub fn serve(&mut self, sim: &mut Sim, link: &mut impl Link) -> io::Result<()> {
+        while let Some(packet) = self.receive(link)? {
+            match self.handle(sim, link, &packet) {
+                Some(reply) => send(link, &reply)?,
+                // gdb expects no reply to a kill
+                None if packet == "k" => return Ok(()),
+                None => return send(link, "OK"),
+            }
+        }
+
+        Ok(())
+    }
+
+    // the reply to a packet, None when the session ends
+    fn handle(&mut self, sim: &mut Sim, link: &mut impl Link, packet: &str) -> Option<String> {
+        let mut chars = packet.chars();
+        // unsupported, like any unknown command
+        let Some(command) = chars.next() else {
+            return Some(String::new());
+        };
+        let args = chars.as_str();
+
+        Some(match command {
+            '?' => stop(SIGTRAP),
+            'g' => (0..REGISTERS)
+                .map(|r| word(self.register(sim, r)))
+                .collect(),
+            'G' => {
+                for (r, chunk) in args.as_bytes().chunks(8).enumerate() {
+                    if let Some(v) = parse_word(chunk) {
+                        self.set_register(sim, r, v);
+                    }
+                }
+                "OK".to_string()
+            }
+            'p' => match usize::from_str_radix(args, 16) {
+                Ok(r) if r < REGISTERS => word(self.register(sim, r)),
+                Ok(_) => "xxxxxxxx".to_string(),
+                Err(_) => error(),
+            },
+            'P' => match args.split_once('=').and_then(|(r, v)| {
+                Some((
+                    usize::from_str_radix(r, 16).ok()?,
+                    parse_word(v.as_bytes())?,
+                ))
+            }) {
+                Some((r, v)) => {
+                    self.set_register(sim, r, v);
+                    "OK".to_string()
+                }
+                None => error(),
+            },
+            'm' => self.read_memory(sim, args).unwrap_or_else(error),
+            'M' => self.write_memory(sim, args).unwrap_or_else(error),
+            'Z' | 'z' => match breakpoint(args) {
+                Some(addr) if command == 'Z' => {
+                    self.breakpoints.push(addr);
+                    "OK".to_string()
+                }
+                Some(addr) => {
+                    self.breakpoints.retain(|&b| b != addr);
+                    "OK".to_string()
+                }
+                // watchpoints are left to Sim::add_watchpoint
+                None => String::new(),
+            },
+            's' => self.resume(sim, link, true),
+            'c' => self.resume(sim, link, false),
+            'H' => "OK".to_string(),
+            'q' if args.starts_with("Supported") => "PacketSize=4000;QStartNoAckMode+".to_string(),
+            'q' if args == "Attached" => "1".to_string(),
+            'q' if args == "C" => "QC1".to_string(),
+            'q' if args == "fThreadInfo" => "m1".to_string(),
+            'q' if args == "sThreadInfo" => "l".to_string(),
+            'Q' if args == "StartNoAckMode" => {
+                self.ack = false;
+                "OK".to_string()
+            }
+            'D' | 'k' => return None,
+            _ => String::new(),
+        })
+    }
+
+    fn mips<'s>(&self, sim: &'s Sim) -> &'s Mips {
+        sim.component::<Mips>(self.cpu)
+            .expect("GdbStub cpu is not a Mips")
+    }
+
+    fn mips_mut<'s>(&self, sim: &'s mut Sim) -> &'s mut Mips {
+        sim.component_mut::<Mips>(self.cpu)
+            .expect("GdbStub cpu is not a Mips")
+    }
+
+    // registers without a counterpart, such as BadVAddr and the FPU ones, read as 0
+    fn register(&self, sim: &Sim, r: usize) -> u32 {
+        let mips = self.mips(sim);
+
+        match r {
+            0..=31 => mips.reg(r),
+            STATUS => mips.status(),
+            LO => mips.lo(),
+            HI => mips.hi(),
+            CAUSE => mips.cause(),
+            PC => mips.pc(),
+            _ => 0,
+        }
+    }
+
+    fn set_register(&self, sim: &mut Sim, r: usize, value: u32) {
+        let mips = self.mips_mut(sim);
+
+        match r {
+            0..=31 => mips.set_reg(r, value),
+            STATUS => mips.set_status(value),
+            LO => mips.set_lo(value),
+            HI => mips.set_hi(value),
+            PC => mips.set_pc(value),
+            _ => {}
+        }
+    }
+
+    // the mapped Ram holding `addr`, and the index of its word there
+    fn locate(&self, sim: &Sim, addr: u32) -> Option<(ComponentKey, usize)> {
+        self.memory.iter().find_map(|&(k, base)| {
+            let word = usize::try_from(addr.checked_sub(base)? / 4).ok()?;
+            let size = sim.component::<Ram>(k)?.size();
+            Some((k, word)).filter(|_| word < size)
+        })
+    }
+
+    fn read_byte(&self, sim: &Sim, addr: u32) -> Option<u8> {
+        let (k, word) = self.locate(sim, addr)?;
+        let value = sim.component::<Ram>(k)?.read(word);
+        Some(value.to_be_bytes()[(addr % 4) as usize])
+    }
+
+    fn write_byte(&self, sim: &mut Sim, addr: u32, byte: u8) -> Option<()> {
+        let (k, word) = self.locate(sim, addr)?;
+        let ram = sim.component_mut::<Ram>(k)?;
+
+        let mut bytes = ram.read(word).to_be_bytes();
+        bytes[(addr % 4) as usize] = byte;
+        ram.write(word, u32::from_be_bytes(bytes));
+        Some(())
+    }
+
+    // `addr,length`
+    fn read_memory(&self, sim: &Sim, args: &str) -> Option<String> {
+        let (addr, len) = range(args)?;
+
+        (0..len)
+            .map(|i| self.read_byte(sim, addr.wrapping_add(i)))
+            .map(|b| b.map(|b| format!("{b:02x}")))
+            .collect()
+    }
+
+    // `addr,length:bytes`
+    fn write_memory(&self, sim: &mut Sim, args: &str) -> Option<String> {
+        let (range_args, data) = args.split_once(':')?;
+        let (addr, len) = range(range_args)?;
+
+        let bytes = data
+            .as_bytes()
+            .chunks(2)
+            .map(|b| u8::from_str_radix(std::str::from_utf8(b).ok()?, 16).ok())
+            .collect::<Option<Vec<_>>>()?;
+        if bytes.len() != len as usize {
+            return None;
+        }
+
+        for (i, &b) in (0..).zip(&bytes) {
+            self.write_byte(sim, addr.wrapping_add(i), b)?;
+        }
+        Some("OK".to_string())
+    }
+
+    // Ticks until an instruction retires when stepping, or until one retires at
+    // a breakpoint otherwise. Sim watchpoints stop either.
+    fn resume(&self, sim: &mut Sim, link: &mut impl Link, step: bool) -> String {
+        let mut retired = self.mips(sim).instructions();
+        let mut interrupted = false;
+        let mut ticks = 0;
+
+        loop {
+            let reason = sim.run_until(|s| {
+                let mips = self.mips(s);
+                if mips.instructions() != retired {
+                    retired = mips.instructions();
+                    if step || self.breakpoints.contains(&mips.pc()) {
+                        return true;
+                    }
+                }
+
+                ticks += 1;
+                interrupted = ticks % POLL_TICKS == 0 && link.interrupted();
+                interrupted
+            });
+
+            if step || !matches!(reason, StopReason::Limit) {
+                return stop(if interrupted { SIGINT } else { SIGTRAP });
+            }
+        }
+    }
+
+    // the next packet's contents, acknowledged, or None once the link closes
+    fn receive(&self, link: &mut impl Link) -> io::Result<Option<String>> {
+        let mut byte = [0];
+
+        loop {
+            // anything before the start of a packet, acks and stray interrupts
+            loop {
+                match link.read(&mut byte) {
+                    Ok(0) => return Ok(None),
+                    Ok(_) if byte[0] == b'$' => break,
+                    Ok(_) => {}
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+
+            let mut packet = Vec::new();
+            loop {
+                link.read_exact(&mut byte)?;
+                if byte[0] == b'#' {
+                    break;
+                }
+                packet.push(byte[0]);
+            }
+
+            let mut checksum = [0; 2];
+            link.read_exact(&mut checksum)?;
+            let valid = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+                == Some(sum(&packet));
+
+            if self.ack {
+                link.write_all(if valid { b"+" } else { b"-" })?;
+            }
+            if valid {
+                return Ok(Some(String::from_utf8_lossy(&packet).into_owned()));
+            }
+        }
+    }
+}
+
+fn send(link: &mut impl Link, data: &str) -> io::Result<()> {
+    write!(link, "${}#{:02x}", data, sum(data.as_bytes()))?;
+    link.flush()
+}
+
+fn sum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |acc: u8, &b| acc.wrapping_add(b))
+}
+
+fn stop(signal: u8) -> String {
+    format!("S{signal:02x}")
+}
+
+// big endian, as registers are sent
+fn word(value: u32) -> String {
+    format!("{value:08x}")
+}
+
+fn error() -> String {
+    "E01".to_string()
+}
+
+fn parse_word(hex: &[u8]) -> Option<u32> {
+    u32::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+}
+
+// `addr,length`
+fn range(args: &str) -> Option<(u32, u32)> {
+    let (addr, len) = args.split_once(',')?;
+    Some((
+        u32::from_str_radix(addr, 16).ok()?,
+        u32::from_str_radix(len, 16).ok()?,
+    ))
+}
+
+// address of a `Z`/`z` software or hardware breakpoint, `type,addr,kind`
+fn breakpoint(args: &str) -> Option<u32> {
+    let mut parts = args.split(',');
+    match parts.next()? {
+        "0" | "1" => u32::from_str_radix(parts.next()?, 16).ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,464 @@
+use super::{Error, NetId, Nets, Ports};
+use crate::{
+    components::{
+        logic::Lut,
+        mem::{Register, Trigger},
+        port::{Input, Output},
+    },
+    Sim,
+};
+use fnv::{FnvHashMap, FnvHashSet};
+
+// widest `.names` cover turned into a lookup table
+const MAX_INPUTS: usize = 16;
+
+// commands carrying timing or naming information only
+const IGNORED: [&str; 12] = [
+    ".area",
+    ".delay",
+    ".wire_load_slope",
+    ".wire",
+    ".input_arrival",
+    ".default_input_arrival",
+    ".output_required",
+    ".default_output_required",
+    ".input_drive",
+    ".default_input_drive",
+    ".cname",
+    ".attr",
+];
+
+/// Builds the model `top` (or the first model of the file) with a `Lut` per
+/// `.names` cover and a `Register` per `.latch`, flattening `.subckt`
+/// instances into scopes of `sim`.
+///
+/// Latches without a control signal, and primary inputs used as latch
+/// controls or listed in `.clock`, are driven by the clock of `sim` and get
+/// no port.
+///
+/// # Errors
+///
+/// Will return an error pointing at the offending line if the source uses
+/// unsupported commands or is otherwise malformed
+pub fn load(sim: &mut Sim, source: &str, top: Option<&str>) -> Result<Ports, Error> {
+    super::build(sim, |sim| elaborate(sim, source, top))
+}
+
+fn elaborate(sim: &mut Sim, source: &str, top: Option<&str>) -> Result<Ports, Error> {
+    let models = parse(source)?;
+
+    let top = match top {
+        Some(name) => models
+            .iter()
+            .find(|m| m.name == name)
+            .ok_or_else(|| Error::new(0, format!("unknown model `{name}`")))?,
+        None => models
+            .first()
+            .ok_or_else(|| Error::new(0, "no models found"))?,
+    };
+
+    let mut elab = Elab {
+        sim,
+        models: &models,
+        nets: Nets::new(),
+        stack: vec![top.name],
+    };
+
+    let clocks = top.clocks();
+    let mut signals = FnvHashMap::default();
+    let mut ports = Ports::default();
+
+    for &name in &top.inputs {
+        let net = elab.nets.add();
+        signals.insert(name, net);
+
+        if clocks.contains(name) {
+            let clk = elab.nets.clock();
+            elab.nets.alias(net, clk);
+        } else {
+            let c = elab.sim.add_component(Input::new(1));
+            elab.sim.set_label(c, name);
+            elab.nets.attach(net, c, 1);
+            ports.insert(name.to_string(), c);
+        }
+    }
+
+    for &name in &top.outputs {
+        let net = *signals.entry(name).or_insert_with(|| elab.nets.add());
+        let c = elab.sim.add_component(Output::new(1));
+        elab.sim.set_label(c, name);
+        elab.nets.attach(net, c, 1);
+        ports.insert(name.to_string(), c);
+    }
+
+    elab.model(top, signals)?;
+
+    let Elab { sim, nets, .. } = elab;
+    nets.connect(sim);
+
+    Ok(ports)
+}
+
+enum Item<'a> {
+    // input patterns of each cube and whether the cover lists the on-set
+    Names {
+        signals: Vec<&'a str>,
+        cover: Vec<&'a str>,
+        on: bool,
+        line: usize,
+    },
+    Latch {
+        input: &'a str,
+        output: &'a str,
+        trigger: Trigger,
+        control: Option<&'a str>,
+        init: bool,
+    },
+    Subckt {
+        model: &'a str,
+        conns: Vec<(&'a str, &'a str)>,
+        line: usize,
+    },
+    Conn(&'a str, &'a str),
+}
+
+struct Model<'a> {
+    name: &'a str,
+    inputs: Vec<&'a str>,
+    outputs: Vec<&'a str>,
+    clocks: Vec<&'a str>,
+    items: Vec<Item<'a>>,
+}
+
+impl<'a> Model<'a> {
+    fn new(name: &'a str) -> Self {
+        Self {
+            name,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            clocks: Vec::new(),
+            items: Vec::new(),
+        }
+    }
+
+    // signals driving the clock inputs of latches
+    fn clocks(&self) -> FnvHashSet<&'a str> {
+        let controls = self.items.iter().filter_map(|i| match i {
+            Item::Latch { control, .. } => *control,
+            _ => None,
+        });
+
+        self.clocks.iter().copied().chain(controls).collect()
+    }
+}
+
+// logical lines with continuations joined, comments stripped and their line number
+fn lines(source: &str) -> Vec<(usize, Vec<&str>)> {
+    let mut lines = Vec::new();
+    let mut current: Option<(usize, Vec<&str>)> = None;
+
+    for (n, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let (line, continued) = match line.trim_end().strip_suffix('\\') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+
+        let (_, tokens) = current.get_or_insert_with(|| (n + 1, Vec::new()));
+        tokens.extend(line.split_whitespace());
+
+        if !continued {
+            match current.take() {
+                Some((_, tokens)) if tokens.is_empty() => {}
+                Some(l) => lines.push(l),
+                None => {}
+            }
+        }
+    }
+
+    lines.extend(current.filter(|(_, tokens)| !tokens.is_empty()));
+    lines
+}
+
+fn parse(source: &str) -> Result<Vec<Model<'_>>, Error> {
+    let mut models: Vec<Model> = Vec::new();
+    let mut model = None;
+    let mut lines = lines(source).into_iter().peekable();
+
+    while let Some((line, tokens)) = lines.next() {
+        let (command, args) = (tokens[0], &tokens[1..]);
+
+        if command == ".model" {
+            let name = args.first().copied().unwrap_or("");
+            if models.iter().any(|m| m.name == name) {
+                return Err(Error::new(line, format!("duplicate model `{name}`")));
+            }
+            models.extend(model.replace(Model::new(name)));
+            continue;
+        }
+
+        let m = model.get_or_insert_with(|| Model::new(""));
+        match command {
+            ".inputs" => m.inputs.extend(args),
+            ".outputs" => m.outputs.extend(args),
+            ".clock" => m.clocks.extend(args),
+            ".end" => models.extend(model.take()),
+            ".names" => {
+                let (&output, inputs) = args
+                    .split_last()
+                    .ok_or_else(|| Error::new(line, "`.names` without signals"))?;
+                let mut cover = Vec::new();
+                let mut on = None;
+
+                while let Some((n, cube)) = lines.next_if(|(_, t)| !t[0].starts_with('.')) {
+                    let (pattern, value) = match (inputs.len(), cube.as_slice()) {
+                        (0, &[value]) => ("", value),
+                        (_, &[pattern, value]) if pattern.len() == inputs.len() => (pattern, value),
+                        _ => return Err(Error::new(n, "malformed cover")),
+                    };
+
+                    if pattern.chars().any(|c| !matches!(c, '0' | '1' | '-')) {
+                        return Err(Error::new(n, format!("invalid cube `{pattern}`")));
+                    }
+                    let value = match value {
+                        "0" => false,
+                        "1" => true,
+                        _ => return Err(Error::new(n, format!("invalid output `{value}`"))),
+                    };
+                    if *on.get_or_insert(value) != value {
+                        return Err(Error::new(n, "cover mixes on-set and off-set cubes"));
+                    }
+
+                    cover.push(pattern);
+                }
+
+                let mut signals = inputs.to_vec();
+                signals.push(output);
+                m.items.push(Item::Names {
+                    signals,
+                    cover,
+                    on: on.unwrap_or(true),
+                    line,
+                });
+            }
+            ".latch" => m.items.push(latch(line, args)?),
+            ".subckt" => {
+                let (&model, formals) = args
+                    .split_first()
+                    .ok_or_else(|| Error::new(line, "`.subckt` without a model"))?;
+                let conns = formals
+                    .iter()
+                    .map(|c| {
+                        let p = c.find('=').ok_or_else(|| {
+                            Error::new(line, format!("malformed connection `{c}`"))
+                        })?;
+                        Ok((&c[..p], &c[p + 1..]))
+                    })
+                    .collect::<Result<_, Error>>()?;
+
+                m.items.push(Item::Subckt { model, conns, line });
+            }
+            ".conn" => match args {
+                &[a, b] => m.items.push(Item::Conn(a, b)),
+                _ => return Err(Error::new(line, "`.conn` takes two signals")),
+            },
+            c if IGNORED.contains(&c) => {}
+            c => return Err(Error::new(line, format!("unsupported command `{c}`"))),
+        }
+    }
+
+    models.extend(model);
+    Ok(models)
+}
+
+// .latch <input> <output> [<type> <control>] [<init>]
+fn latch<'a>(line: usize, args: &[&'a str]) -> Result<Item<'a>, Error> {
+    let (input, output, kind, control, init) = match *args {
+        [i, o] => (i, o, "re", "NIL", "3"),
+        [i, o, init] => (i, o, "re", "NIL", init),
+        [i, o, kind, control] => (i, o, kind, control, "3"),
+        [i, o, kind, control, init] => (i, o, kind, control, init),
+        _ => return Err(Error::new(line, "malformed `.latch`")),
+    };
+
+    let trigger = match kind {
+        "re" => Trigger::Rising,
+        "fe" => Trigger::Falling,
+        "ah" => Trigger::High,
+        "al" => Trigger::Low,
+        t => return Err(Error::new(line, format!("unsupported latch type `{t}`"))),
+    };
+
+    // don't care and unknown initial values start low
+    let init = match init {
+        "1" => true,
+        "0" | "2" | "3" => false,
+        v => return Err(Error::new(line, format!("invalid initial value `{v}`"))),
+    };
+
+    Ok(Item::Latch {
+        input,
+        output,
+        trigger,
+        control: Some(control).filter(|&c| c != "NIL"),
+        init,
+    })
+}
+
+// truth table of a cover, bit i holding the output for the inputs reading i
+fn table(inputs: usize, cover: &[&str], on: bool) -> Vec<u32> {
+    let mut table = vec![0; Lut::words(inputs)];
+
+    for i in 0..1_usize << inputs {
+        let hit = cover.iter().any(|cube| {
+            cube.chars()
+                .enumerate()
+                .all(|(j, c)| c == '-' || (c == '1') == (i & (1 << j) != 0))
+        });
+
+        if hit == on {
+            table[i / 32] |= 1 << (i % 32);
+        }
+    }
+
+    table
+}
+
+struct Elab<'a, 's> {
+    sim: &'a mut Sim,
+    models: &'a [Model<'s>],
+    nets: Nets,
+    stack: Vec<&'s str>,
+}
+
+impl<'s> Elab<'_, 's> {
+    fn model(
+        &mut self,
+        model: &Model<'s>,
+        mut signals: FnvHashMap<&'s str, NetId>,
+    ) -> Result<(), Error> {
+        for item in &model.items {
+            match item {
+                Item::Names {
+                    signals: names,
+                    cover,
+                    on,
+                    line,
+                } => {
+                    let nets = names
+                        .iter()
+                        .map(|s| self.signal(&mut signals, s))
+                        .collect::<Vec<_>>();
+                    let (&y, inputs) = nets.split_last().unwrap();
+
+                    if inputs.is_empty() {
+                        let value = self.nets.constant(self.sim, cover.is_empty() != *on);
+                        self.nets.alias(y, value);
+                        continue;
+                    }
+                    if inputs.len() > MAX_INPUTS {
+                        return Err(Error::new(
+                            *line,
+                            format!("covers over more than {MAX_INPUTS} inputs are not supported"),
+                        ));
+                    }
+
+                    let c = self
+                        .sim
+                        .add_component(Lut::new(inputs.len(), &table(inputs.len(), cover, *on)));
+                    self.sim.set_label(c, names.last().unwrap());
+
+                    for (i, &net) in nets.iter().enumerate() {
+                        self.nets.attach(net, c, i + 1);
+                    }
+                }
+                Item::Latch {
+                    input,
+                    output,
+                    trigger,
+                    control,
+                    init,
+                } => {
+                    let c = self
+                        .sim
+                        .add_component(Register::new(1, *trigger, u32::from(*init)));
+                    self.sim.set_label(c, output);
+
+                    let clk = match control {
+                        Some(control) => self.signal(&mut signals, control),
+                        None => self.nets.clock(),
+                    };
+                    let pins = [
+                        self.signal(&mut signals, input),
+                        self.signal(&mut signals, output),
+                        clk,
+                        self.nets.constant(self.sim, true),
+                        self.nets.constant(self.sim, false),
+                    ];
+
+                    for (i, &net) in pins.iter().enumerate() {
+                        self.nets.attach(net, c, i + 1);
+                    }
+                }
+                Item::Subckt {
+                    model: name,
+                    conns,
+                    line,
+                } => self.subckt(&mut signals, name, conns, *line)?,
+                Item::Conn(a, b) => {
+                    let (a, b) = (self.signal(&mut signals, a), self.signal(&mut signals, b));
+                    self.nets.alias(a, b);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn subckt(
+        &mut self,
+        signals: &mut FnvHashMap<&'s str, NetId>,
+        name: &'s str,
+        conns: &[(&'s str, &'s str)],
+        line: usize,
+    ) -> Result<(), Error> {
+        let models = self.models;
+        let model = models
+            .iter()
+            .find(|m| m.name == name)
+            .ok_or_else(|| Error::new(line, format!("unknown model `{name}`")))?;
+
+        if self.stack.contains(&name) {
+            return Err(Error::new(
+                line,
+                format!("model `{name}` instantiates itself"),
+            ));
+        }
+
+        let mut inner = FnvHashMap::default();
+        for &(formal, actual) in conns {
+            if !model.inputs.contains(&formal) && !model.outputs.contains(&formal) {
+                return Err(Error::new(
+                    line,
+                    format!("model `{name}` has no port `{formal}`"),
+                ));
+            }
+
+            inner.insert(formal, self.signal(signals, actual));
+        }
+
+        let instance = format!("{name}_{line}");
+        self.stack.push(name);
+        self.sim.enter_scope(&instance, name);
+        let result = self.model(model, inner);
+        self.sim.exit_scope();
+        self.stack.pop();
+
+        result
+    }
+
+    fn signal(&mut self, signals: &mut FnvHashMap<&'s str, NetId>, name: &'s str) -> NetId {
+        let nets = &mut self.nets;
+        *signals.entry(name).or_insert_with(|| nets.add())
+    }
+}
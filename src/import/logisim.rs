@@ -0,0 +1,1062 @@
+use super::{Error, NetId, Nets, Ports};
+use crate::{
+    components::{
+        logic::Gate,
+        mem::{Register, Trigger},
+        port::{Input, Output},
+    },
+    Component, PinIO, Sim, IO,
+};
+use fnv::FnvHashMap;
+use roxmltree::{Document, Node};
+use std::convert::TryFrom;
+
+type Point = (i32, i32);
+
+/// Builds the circuit `top` (or the project's main circuit) of a Logisim
+/// Evolution `.circ` file, flattening subcircuits into scopes of `sim`.
+///
+/// Component geometry follows the classic Logisim appearance; subcircuits
+/// must use the classic or a custom appearance.
+///
+/// # Errors
+///
+/// Will return an error pointing at the offending element if the file is not
+/// valid XML or uses library components that have no `sim-rs` counterpart
+pub fn load(sim: &mut Sim, source: &str, top: Option<&str>) -> Result<Ports, Error> {
+    super::build(sim, |sim| elaborate(sim, source, top))
+}
+
+fn elaborate(sim: &mut Sim, source: &str, top: Option<&str>) -> Result<Ports, Error> {
+    let doc =
+        Document::parse(source).map_err(|e| Error::new(e.pos().row as usize, e.to_string()))?;
+    let project = doc.root_element();
+
+    let libs = project
+        .children()
+        .filter(|n| n.has_tag_name("lib"))
+        .filter_map(|n| Some((n.attribute("name")?, n.attribute("desc")?)))
+        .collect::<FnvHashMap<_, _>>();
+
+    let mut circuits = FnvHashMap::default();
+    for n in project.children().filter(|n| n.has_tag_name("circuit")) {
+        let c = Circuit::parse(&doc, n, &libs)?;
+        circuits.insert(c.name, c);
+    }
+
+    let main = project
+        .children()
+        .find(|n| n.has_tag_name("main"))
+        .and_then(|n| n.attribute("name"));
+    let name = top
+        .or(main)
+        .or_else(|| circuits.keys().next().copied())
+        .ok_or_else(|| Error::new(line(&doc, project), "the project has no circuits"))?;
+    let circuit = circuits
+        .get(name)
+        .ok_or_else(|| Error::new(line(&doc, project), format!("unknown circuit `{name}`")))?;
+
+    let mut elab = Elab {
+        sim,
+        circuits: &circuits,
+        nets: Nets::new(),
+        stack: vec![name],
+    };
+
+    let mut ports = Ports::default();
+    elab.circuit(circuit, Some(&mut ports))?;
+
+    let Elab { sim, nets, .. } = elab;
+    nets.connect(sim);
+
+    Ok(ports)
+}
+
+fn line(doc: &Document, n: Node) -> usize {
+    doc.text_pos_at(n.range().start).row as usize
+}
+
+fn point(s: &str) -> Option<Point> {
+    let mut coords = s.trim().strip_prefix('(')?.strip_suffix(')')?.split(',');
+    let x = coords.next()?.trim().parse().ok()?;
+    let y = coords.next()?.trim().parse().ok()?;
+    Some((x, y))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Facing {
+    East,
+    North,
+    West,
+    South,
+}
+
+impl Facing {
+    fn degrees(self) -> i32 {
+        match self {
+            Facing::East => 0,
+            Facing::North => 90,
+            Facing::West => 180,
+            Facing::South => 270,
+        }
+    }
+
+    fn reverse(self) -> Self {
+        match self {
+            Facing::East => Facing::West,
+            Facing::North => Facing::South,
+            Facing::West => Facing::East,
+            Facing::South => Facing::North,
+        }
+    }
+
+    // rotates an offset drawn facing `from` so it faces `self`
+    fn rotate(self, from: Facing, (dx, dy): Point) -> Point {
+        match (self.degrees() - from.degrees()).rem_euclid(360) {
+            90 => (dy, -dx),
+            180 => (-dx, -dy),
+            270 => (-dy, dx),
+            _ => (dx, dy),
+        }
+    }
+}
+
+struct Comp<'d> {
+    lib: Option<&'d str>,
+    name: &'d str,
+    loc: Point,
+    attrs: FnvHashMap<&'d str, &'d str>,
+    line: usize,
+}
+
+impl Comp<'_> {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.get(name).copied()
+    }
+
+    fn int(&self, name: &str, default: i64) -> Result<i64, Error> {
+        self.attr(name).map_or(Ok(default), |v| {
+            let v = v.trim();
+            match v.strip_prefix("0x") {
+                Some(hex) => i64::from_str_radix(hex, 16),
+                None => v.parse(),
+            }
+            .map_err(|_| Error::new(self.line, format!("invalid `{name}` value `{v}`")))
+        })
+    }
+
+    fn size(&self, name: &str, default: usize) -> Result<usize, Error> {
+        usize::try_from(self.int(name, i64::try_from(default).unwrap())?)
+            .map_err(|_| Error::new(self.line, format!("invalid `{name}`")))
+    }
+
+    fn width(&self) -> Result<usize, Error> {
+        self.size("width", 1)
+    }
+
+    fn facing(&self, default: Facing) -> Facing {
+        match self.attr("facing") {
+            Some("east") => Facing::East,
+            Some("north") => Facing::North,
+            Some("west") => Facing::West,
+            Some("south") => Facing::South,
+            _ => default,
+        }
+    }
+
+    fn is_output_pin(&self) -> bool {
+        self.attr("output") == Some("true") || self.attr("type") == Some("output")
+    }
+
+    fn unsupported(&self) -> Error {
+        Error::new(
+            self.line,
+            format!(
+                "unsupported component `{}` from library `{}`",
+                self.name,
+                self.lib.unwrap_or("")
+            ),
+        )
+    }
+}
+
+enum Appearance {
+    Classic,
+    Evolution,
+    // pin location => port location, relative to the anchor
+    Custom(Facing, Vec<(Point, Point)>),
+}
+
+struct Circuit<'d> {
+    name: &'d str,
+    comps: Vec<Comp<'d>>,
+    wires: Vec<(Point, Point)>,
+    appearance: Appearance,
+}
+
+impl<'d> Circuit<'d> {
+    fn parse(
+        doc: &'d Document,
+        n: Node<'d, 'd>,
+        libs: &FnvHashMap<&str, &'d str>,
+    ) -> Result<Self, Error> {
+        let name = n
+            .attribute("name")
+            .ok_or_else(|| Error::new(line(doc, n), "circuit without a name"))?;
+        let mut c = Circuit {
+            name,
+            comps: Vec::new(),
+            wires: Vec::new(),
+            appearance: Appearance::Classic,
+        };
+
+        for e in n.children().filter(Node::is_element) {
+            let l = line(doc, e);
+            let invalid = || Error::new(l, format!("malformed `{}`", e.tag_name().name()));
+
+            match e.tag_name().name() {
+                "a" if e.attribute("name") == Some("appearance")
+                    && e.attribute("val") == Some("logisim_evolution") =>
+                {
+                    c.appearance = Appearance::Evolution;
+                }
+                "wire" => {
+                    let from = e.attribute("from").and_then(point).ok_or_else(invalid)?;
+                    let to = e.attribute("to").and_then(point).ok_or_else(invalid)?;
+                    c.wires.push((from, to));
+                }
+                "comp" => c.comps.push(Comp {
+                    lib: match e.attribute("lib") {
+                        Some(lib) => Some(*libs.get(lib).ok_or_else(invalid)?),
+                        None => None,
+                    },
+                    name: e.attribute("name").ok_or_else(invalid)?,
+                    loc: e.attribute("loc").and_then(point).ok_or_else(invalid)?,
+                    attrs: e
+                        .children()
+                        .filter(|a| a.has_tag_name("a"))
+                        // memory contents are saved as text rather than a `val`
+                        .filter_map(|a| {
+                            Some((
+                                a.attribute("name")?,
+                                a.attribute("val").or_else(|| a.text())?,
+                            ))
+                        })
+                        .collect(),
+                    line: l,
+                }),
+                "appear" => c.appearance = Self::custom_appearance(e).ok_or_else(invalid)?,
+                _ => {}
+            }
+        }
+
+        Ok(c)
+    }
+
+    fn custom_appearance(n: Node) -> Option<Appearance> {
+        let anchor = n.children().find(|a| a.has_tag_name("circ-anchor"))?;
+        let origin = (
+            anchor.attribute("x")?.parse::<i32>().ok()?,
+            anchor.attribute("y")?.parse::<i32>().ok()?,
+        );
+        let facing = match anchor.attribute("facing") {
+            Some("north") => Facing::North,
+            Some("west") => Facing::West,
+            Some("south") => Facing::South,
+            _ => Facing::East,
+        };
+
+        let mut ports = Vec::new();
+        for p in n.children().filter(|p| p.has_tag_name("circ-port")) {
+            let pin = point(p.attribute("pin")?)?;
+            let x = p.attribute("x")?.parse::<i32>().ok()?;
+            let y = p.attribute("y")?.parse::<i32>().ok()?;
+            ports.push((pin, (x - origin.0, y - origin.1)));
+        }
+
+        Some(Appearance::Custom(facing, ports))
+    }
+
+    fn pins(&self) -> impl Iterator<Item = &Comp<'d>> {
+        self.comps
+            .iter()
+            .filter(|c| c.lib == Some("#Wiring") && c.name == "Pin")
+    }
+
+    // port offsets of an instance, keyed by the location of the pin inside the circuit
+    fn ports(&self, instance: &Comp) -> Result<Vec<(Point, Point)>, Error> {
+        let facing = instance.facing(Facing::East);
+
+        match &self.appearance {
+            Appearance::Custom(from, ports) => Ok(ports
+                .iter()
+                .map(|&(pin, offset)| (pin, facing.rotate(*from, offset)))
+                .collect()),
+            Appearance::Classic => Ok(self
+                .classic_ports()
+                .into_iter()
+                .map(|(pin, offset)| (pin, facing.rotate(Facing::East, offset)))
+                .collect()),
+            Appearance::Evolution => Err(Error::new(
+                instance.line,
+                format!(
+                    "subcircuit `{}` uses the Logisim Evolution appearance, set it to classic or custom",
+                    self.name
+                ),
+            )),
+        }
+    }
+
+    // the default classic appearance places each pin on the box edge opposite to its facing
+    fn classic_ports(&self) -> Vec<(Point, Point)> {
+        let mut edges: [Vec<Point>; 4] = Default::default();
+        for p in self.pins() {
+            let default = if p.is_output_pin() {
+                Facing::West
+            } else {
+                Facing::East
+            };
+            let edge = p.facing(default).reverse();
+            edges[usize::try_from(edge.degrees() / 90).unwrap()].push(p.loc);
+        }
+
+        let [east, north, west, south] = &mut edges;
+        east.sort_by_key(|&(x, y)| (y, x));
+        west.sort_by_key(|&(x, y)| (y, x));
+        north.sort_unstable();
+        south.sort_unstable();
+
+        let count = |e: &Vec<Point>| i32::try_from(e.len()).unwrap();
+        let (n, s, e, w) = (count(north), count(south), count(east), count(west));
+        let (vertical, horizontal) = (n.max(s), e.max(w));
+
+        let offset = |facing: i32, opposite: i32, others: i32| {
+            let this = facing.max(opposite);
+            let base = match this {
+                0 | 1 if others == 0 => 15,
+                0..=2 => 10,
+                _ if others == 0 => 5,
+                _ => 10,
+            };
+            base + 10 * ((this - facing) / 2)
+        };
+        let dimension = |this: i32, others: i32| {
+            if this < 3 {
+                30
+            } else if others == 0 {
+                10 * this
+            } else {
+                10 * this + 10
+            }
+        };
+
+        let (off_n, off_s) = (offset(n, s, horizontal), offset(s, n, horizontal));
+        let (off_e, off_w) = (offset(e, w, vertical), offset(w, e, vertical));
+        let (width, height) = (
+            dimension(vertical, horizontal),
+            dimension(horizontal, vertical),
+        );
+
+        let anchor = if e > 0 {
+            (width, off_e)
+        } else if n > 0 {
+            (off_n, 0)
+        } else if w > 0 {
+            (0, off_w)
+        } else if s > 0 {
+            (off_s, height)
+        } else {
+            (0, 0)
+        };
+
+        let place = |pins: &Vec<Point>, (x, y): Point, (dx, dy): Point| {
+            (0..)
+                .zip(pins)
+                .map(|(i, &pin)| (pin, (x + i * dx - anchor.0, y + i * dy - anchor.1)))
+                .collect::<Vec<_>>()
+        };
+
+        let mut ports = place(west, (0, off_w), (0, 10));
+        ports.extend(place(east, (width, off_e), (0, 10)));
+        ports.extend(place(north, (off_n, 0), (10, 0)));
+        ports.extend(place(south, (off_s, height), (10, 0)));
+        ports
+    }
+}
+
+enum Kind<'d> {
+    Pin,
+    Tunnel,
+    Constant(u64),
+    Clock,
+    Gate(Gate),
+    Splitter(Vec<Option<usize>>),
+    Register,
+    Rom,
+    Ram,
+    Subcircuit(&'d Circuit<'d>),
+    Ignored,
+}
+
+struct Port {
+    at: Point,
+    width: usize,
+}
+
+fn port(loc: Point, (dx, dy): Point, width: usize) -> Port {
+    Port {
+        at: (loc.0 + dx, loc.1 + dy),
+        width,
+    }
+}
+
+// offset of input `index` from the output of a gate, following Logisim's AbstractGate
+fn gate_input(
+    gate: Gate,
+    size: i32,
+    inputs: i32,
+    index: i32,
+    negated: bool,
+    facing: Facing,
+) -> Point {
+    let bonus = match gate {
+        Gate::Xor | Gate::Nand | Gate::Nor => 10,
+        Gate::Xnor => 20,
+        _ => 0,
+    };
+
+    let (start, dist, lower_even) = if inputs <= 3 {
+        if size < 40 {
+            (-5, 10, 10)
+        } else if size < 60 || inputs <= 2 {
+            (-10, 20, 20)
+        } else {
+            (-15, 30, 30)
+        }
+    } else if inputs == 4 && size >= 60 {
+        (-5, 20, 0)
+    } else {
+        (-5, 10, 10)
+    };
+
+    let dy = if inputs % 2 == 1 {
+        start * (inputs - 1) + dist * index
+    } else if index >= inputs / 2 {
+        start * inputs + dist * index + lower_even
+    } else {
+        start * inputs + dist * index
+    };
+    let dx = size + bonus + if negated { 10 } else { 0 };
+
+    match facing {
+        Facing::North => (dy, dx),
+        Facing::South => (dy, -dx),
+        Facing::West => (dx, dy),
+        Facing::East => (-dx, dy),
+    }
+}
+
+// bit => end index, following Logisim's default splitter distribution
+fn splitter_bits(c: &Comp, fanout: usize, incoming: usize) -> Result<Vec<Option<usize>>, Error> {
+    let per_end = incoming / fanout.max(1);
+    let mut extra = incoming % fanout.max(1);
+    let (mut end, mut left) = (0, 0);
+
+    (0..incoming)
+        .map(|i| {
+            let default = if fanout >= incoming {
+                i
+            } else {
+                if left == 0 {
+                    if i > 0 {
+                        end += 1;
+                    }
+                    left = per_end;
+                    if extra > 0 {
+                        left += 1;
+                        extra -= 1;
+                    }
+                }
+                left -= 1;
+                end
+            };
+
+            match c.attr(&format!("bit{i}")) {
+                Some("none") => Ok(None),
+                Some(_) => c.size(&format!("bit{i}"), 0).map(Some),
+                None => Ok(Some(default)),
+            }
+        })
+        .collect()
+}
+
+fn splitter_ends(c: &Comp, fanout: i32) -> Result<Vec<Point>, Error> {
+    let spacing =
+        i32::try_from(c.int("spacing", 1)?).map_err(|_| Error::new(c.line, "invalid `spacing`"))?;
+    let justify = match c.attr("appear") {
+        Some("center" | "legacy") => 0,
+        Some("right") => 1,
+        _ => -1,
+    };
+
+    let (start, step) = match c.facing(Facing::East) {
+        f @ (Facing::North | Facing::South) => {
+            let m = if f == Facing::North { 1 } else { -1 };
+            let dx = if justify == 0 {
+                10 * spacing * ((fanout + 1) / 2 - 1)
+            } else if m * justify < 0 {
+                -10 * spacing
+            } else {
+                10 * spacing * fanout
+            };
+            ((dx, -m * 20), (-10 * spacing, 0))
+        }
+        f => {
+            let m = if f == Facing::West { -1 } else { 1 };
+            let dy = if justify == 0 {
+                -10 * spacing * (fanout / 2)
+            } else if m * justify > 0 {
+                10 * spacing
+            } else {
+                -10 * spacing * fanout
+            };
+            ((m * 20, dy), (0, 10 * spacing))
+        }
+    };
+
+    Ok((0..fanout)
+        .map(|i| (start.0 + i * step.0, start.1 + i * step.1))
+        .collect())
+}
+
+// Logisim's "addr/data: A D" image format, with run-length "N*V" entries
+fn contents(c: &Comp, addr_width: usize) -> Result<Vec<u32>, Error> {
+    let invalid = || Error::new(c.line, "malformed memory contents");
+    let size = 1 << addr_width;
+    let mut data = Vec::new();
+
+    if let Some(text) = c.attr("contents") {
+        for word in text.lines().skip(1).flat_map(str::split_whitespace) {
+            let (count, value) = match word.find('*') {
+                Some(p) => (word[..p].parse().map_err(|_| invalid())?, &word[p + 1..]),
+                None => (1, word),
+            };
+            let value = u32::from_str_radix(value, 16).map_err(|_| invalid())?;
+            // runs past the end of the memory are cut short
+            let count = count.min(size - data.len());
+            data.extend(std::iter::repeat_n(value, count));
+        }
+    }
+
+    data.resize(size, 0);
+    Ok(data)
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Loc<'d> {
+    Point(Point),
+    Tunnel(&'d str),
+}
+
+// locations joined by wires and tunnels
+#[derive(Default)]
+struct Groups<'d> {
+    ids: FnvHashMap<Loc<'d>, usize>,
+    parent: Vec<usize>,
+}
+
+impl<'d> Groups<'d> {
+    fn get(&mut self, loc: Loc<'d>) -> usize {
+        if let Some(&id) = self.ids.get(&loc) {
+            return self.find(id);
+        }
+
+        let id = self.parent.len();
+        self.parent.push(id);
+        self.ids.insert(loc, id);
+        id
+    }
+
+    fn find(&mut self, mut id: usize) -> usize {
+        while self.parent[id] != id {
+            self.parent[id] = self.parent[self.parent[id]];
+            id = self.parent[id];
+        }
+        id
+    }
+
+    fn join(&mut self, a: Loc<'d>, b: Loc<'d>) {
+        let (a, b) = (self.get(a), self.get(b));
+        self.parent[b] = a;
+    }
+}
+
+struct Elab<'a, 'd> {
+    sim: &'a mut Sim,
+    circuits: &'d FnvHashMap<&'d str, Circuit<'d>>,
+    nets: Nets,
+    stack: Vec<&'d str>,
+}
+
+impl<'d> Elab<'_, 'd> {
+    fn kind(&self, c: &Comp<'d>) -> Result<Kind<'d>, Error> {
+        let gate = |g| Ok(Kind::Gate(g));
+
+        match (c.lib, c.name) {
+            (Some("#Wiring"), "Pin") => Ok(Kind::Pin),
+            (Some("#Wiring"), "Tunnel") => Ok(Kind::Tunnel),
+            (Some("#Wiring"), "Constant") => Ok(Kind::Constant(
+                u64::try_from(c.int("value", 1)?).map_err(|_| c.unsupported())?,
+            )),
+            (Some("#Wiring"), "Power") => Ok(Kind::Constant(u64::MAX)),
+            (Some("#Wiring"), "Ground") => Ok(Kind::Constant(0)),
+            (Some("#Wiring"), "Clock") => Ok(Kind::Clock),
+            (Some("#Wiring"), "Splitter") => Ok(Kind::Splitter(splitter_bits(
+                c,
+                c.size("fanout", 2)?,
+                c.size("incoming", 2)?,
+            )?)),
+            (Some("#Wiring"), "Probe") | (Some("#Base"), _) => Ok(Kind::Ignored),
+            (Some("#Gates"), "AND Gate") => gate(Gate::And),
+            (Some("#Gates"), "OR Gate") => gate(Gate::Or),
+            (Some("#Gates"), "NAND Gate") => gate(Gate::Nand),
+            (Some("#Gates"), "NOR Gate") => gate(Gate::Nor),
+            (Some("#Gates"), "XOR Gate") => gate(Gate::Xor),
+            (Some("#Gates"), "XNOR Gate") => gate(Gate::Xnor),
+            (Some("#Gates"), "NOT Gate") => gate(Gate::Not),
+            (Some("#Gates"), "Buffer") => gate(Gate::Buffer),
+            (Some("#Memory"), "Register") => Ok(Kind::Register),
+            (Some("#Memory"), "ROM") => Ok(Kind::Rom),
+            (Some("#Memory"), "RAM") if c.attr("bus") != Some("separate") => Ok(Kind::Ram),
+            (None, name) => match self.circuits.get(name) {
+                Some(_) if self.stack.contains(&name) => Err(Error::new(
+                    c.line,
+                    format!("circuit `{name}` contains itself"),
+                )),
+                Some(circuit) => Ok(Kind::Subcircuit(circuit)),
+                None => Err(Error::new(c.line, format!("unknown circuit `{name}`"))),
+            },
+            _ => Err(c.unsupported()),
+        }
+    }
+
+    fn ports(c: &Comp, kind: &Kind) -> Result<Vec<Port>, Error> {
+        let loc = c.loc;
+
+        Ok(match kind {
+            Kind::Pin | Kind::Tunnel | Kind::Constant(_) => vec![port(loc, (0, 0), c.width()?)],
+            Kind::Clock => vec![port(loc, (0, 0), 1)],
+            Kind::Gate(g) if g.inputs() == 1 => {
+                let size = if *g == Gate::Not {
+                    i32::try_from(c.int("size", 30)?).map_err(|_| c.unsupported())?
+                } else {
+                    20
+                };
+                let facing = c.facing(Facing::East);
+
+                vec![
+                    port(loc, facing.rotate(Facing::East, (-size, 0)), c.width()?),
+                    port(loc, (0, 0), c.width()?),
+                ]
+            }
+            Kind::Gate(g) => {
+                let size = i32::try_from(c.int("size", 50)?).map_err(|_| c.unsupported())?;
+                let inputs = i32::try_from(c.int("inputs", 2)?).map_err(|_| c.unsupported())?;
+                let facing = c.facing(Facing::East);
+
+                let mut ports = (0..inputs)
+                    .map(|i| {
+                        let negated = c.attr(&format!("negate{i}")) == Some("true");
+                        Ok(port(
+                            loc,
+                            gate_input(*g, size, inputs, i, negated, facing),
+                            c.width()?,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                ports.push(port(loc, (0, 0), c.width()?));
+                ports
+            }
+            Kind::Splitter(bits) => {
+                let fanout = c.size("fanout", 2)?;
+                let ends = splitter_ends(c, i32::try_from(fanout).map_err(|_| c.unsupported())?)?;
+
+                std::iter::once(port(loc, (0, 0), bits.len()))
+                    .chain(ends.into_iter().enumerate().map(|(e, offset)| {
+                        port(loc, offset, bits.iter().filter(|&&b| b == Some(e)).count())
+                    }))
+                    .collect()
+            }
+            // d, q, clk, enable, clear
+            Kind::Register => {
+                let width = c.size("width", 8)?;
+                vec![
+                    port(loc, (-30, 0), width),
+                    port(loc, (20, 0), width),
+                    port(loc, (-20, 20), 1),
+                    port(loc, (-30, 10), 1),
+                    port(loc, (0, 20), 1),
+                ]
+            }
+            // addr, data, select, load, clear, clk
+            Kind::Rom | Kind::Ram => {
+                let mut ports = vec![
+                    port(loc, (-140, 0), c.size("addrWidth", 8)?),
+                    port(loc, (0, 0), c.size("dataWidth", 8)?),
+                    port(loc, (-90, 40), 1),
+                ];
+                if let Kind::Ram = kind {
+                    ports.push(port(loc, (-50, 40), 1));
+                    ports.push(port(loc, (-30, 40), 1));
+                    ports.push(port(loc, (-70, 40), 1));
+                }
+                ports
+            }
+            Kind::Subcircuit(circuit) => {
+                let widths = circuit
+                    .pins()
+                    .map(|p| Ok((p.loc, p.width()?)))
+                    .collect::<Result<FnvHashMap<_, _>, Error>>()?;
+
+                circuit
+                    .ports(c)?
+                    .into_iter()
+                    .map(|(pin, offset)| port(loc, offset, widths.get(&pin).copied().unwrap_or(1)))
+                    .collect()
+            }
+            Kind::Ignored => Vec::new(),
+        })
+    }
+
+    // builds `circuit` and returns the nets behind each of its pins, keyed by location
+    fn circuit(
+        &mut self,
+        circuit: &'d Circuit<'d>,
+        top: Option<&mut Ports>,
+    ) -> Result<FnvHashMap<Point, Vec<NetId>>, Error> {
+        let mut groups = Groups::default();
+        for &(a, b) in &circuit.wires {
+            groups.join(Loc::Point(a), Loc::Point(b));
+        }
+
+        let mut placed = Vec::new();
+        for c in &circuit.comps {
+            let kind = self.kind(c)?;
+            if let (Kind::Tunnel, Some(label)) = (&kind, c.attr("label")) {
+                groups.join(Loc::Point(c.loc), Loc::Tunnel(label));
+            }
+
+            let ports = Self::ports(c, &kind)?;
+            placed.push((c, kind, ports));
+        }
+
+        // every location shared by several ports carries one bus of a single width
+        let mut widths = FnvHashMap::<usize, (usize, usize)>::default();
+        for (c, _, ports) in &placed {
+            for p in ports.iter().filter(|p| p.width > 0) {
+                let g = groups.get(Loc::Point(p.at));
+                let (width, count) = widths.entry(g).or_insert((p.width, 0));
+
+                if *width != p.width {
+                    return Err(Error::new(
+                        c.line,
+                        format!(
+                            "width mismatch at ({}, {}): {} and {} bits",
+                            p.at.0, p.at.1, width, p.width
+                        ),
+                    ));
+                }
+                *count += 1;
+            }
+        }
+
+        let mut buses = FnvHashMap::<usize, Vec<NetId>>::default();
+        let mut pins = FnvHashMap::default();
+        let mut top = top;
+
+        for (c, kind, ports) in &placed {
+            let mut bits = Vec::new();
+            let mut connected = Vec::new();
+            for p in ports {
+                let g = groups.get(Loc::Point(p.at));
+                let nets = &mut self.nets;
+                bits.push(
+                    buses
+                        .entry(g)
+                        .or_insert_with(|| (0..p.width).map(|_| nets.add()).collect())
+                        .clone(),
+                );
+                connected.push(widths.get(&g).is_some_and(|&(_, n)| n > 1));
+            }
+
+            self.build(c, kind, &bits, &connected, top.as_deref_mut(), &mut pins)?;
+        }
+
+        Ok(pins)
+    }
+
+    fn build(
+        &mut self,
+        c: &Comp<'d>,
+        kind: &Kind<'d>,
+        bits: &[Vec<NetId>],
+        connected: &[bool],
+        top: Option<&mut Ports>,
+        pins: &mut FnvHashMap<Point, Vec<NetId>>,
+    ) -> Result<(), Error> {
+        match kind {
+            Kind::Pin => {
+                if let Some(ports) = top {
+                    let label = c
+                        .attr("label")
+                        .map_or_else(|| format!("pin_{}_{}", c.loc.0, c.loc.1), str::to_string);
+
+                    let k = if c.is_output_pin() {
+                        self.sim.add_component(Output::new(bits[0].len()))
+                    } else {
+                        self.sim.add_component(Input::new(bits[0].len()))
+                    };
+                    self.sim.set_label(k, &label);
+
+                    for (i, &net) in bits[0].iter().enumerate() {
+                        self.nets.attach(net, k, i + 1);
+                    }
+                    ports.insert(label, k);
+                }
+
+                pins.insert(c.loc, bits[0].clone());
+            }
+            Kind::Constant(value) => {
+                for (i, &net) in bits[0].iter().enumerate() {
+                    let bit = self
+                        .nets
+                        .constant(self.sim, i < 64 && value & (1 << i) != 0);
+                    self.nets.alias(net, bit);
+                }
+            }
+            Kind::Clock => {
+                let clk = self.nets.clock();
+                self.nets.alias(bits[0][0], clk);
+            }
+            Kind::Gate(g) => self.gate(c, *g, bits, connected),
+            Kind::Splitter(map) => {
+                let mut taken = vec![0; bits.len()];
+                for (i, end) in map.iter().enumerate() {
+                    if let Some(e) = end {
+                        let end = bits.get(e + 1).ok_or_else(|| {
+                            Error::new(c.line, "splitter bit mapped to a missing end")
+                        })?;
+                        self.nets.alias(bits[0][i], end[taken[e + 1]]);
+                        taken[e + 1] += 1;
+                    }
+                }
+            }
+            Kind::Register => self.register(c, bits, connected)?,
+            Kind::Rom | Kind::Ram => self.memory(c, kind, bits, connected)?,
+            Kind::Subcircuit(circuit) => self.subcircuit(c, circuit, bits)?,
+            Kind::Tunnel | Kind::Ignored => {}
+        }
+
+        Ok(())
+    }
+
+    fn gate(&mut self, c: &Comp, gate: Gate, bits: &[Vec<NetId>], connected: &[bool]) {
+        let (output, inputs) = bits.split_last().unwrap();
+        let inputs = inputs
+            .iter()
+            .zip(connected)
+            .enumerate()
+            .filter(|(_, (_, &connected))| connected)
+            .collect::<Vec<_>>();
+
+        // Logisim ignores unconnected gate inputs
+        if inputs.is_empty() {
+            return;
+        }
+
+        for (b, &y) in output.iter().enumerate() {
+            let mut terms = Vec::new();
+            for (i, (input, _)) in &inputs {
+                if c.attr(&format!("negate{i}")) == Some("true") {
+                    let t = self.nets.add();
+                    self.nets.gate(self.sim, Gate::Not, t, &[input[b]]);
+                    terms.push(t);
+                } else {
+                    terms.push(input[b]);
+                }
+            }
+
+            let k = self.nets.gate(self.sim, gate, y, &terms);
+            if let Some(label) = c.attr("label") {
+                self.sim.set_label(k, label);
+            }
+        }
+    }
+
+    fn register(&mut self, c: &Comp, bits: &[Vec<NetId>], connected: &[bool]) -> Result<(), Error> {
+        let width = bits[0].len();
+        if width > 32 {
+            return Err(too_wide(c));
+        }
+
+        let trigger = match c.attr("trigger") {
+            Some("falling") => Trigger::Falling,
+            Some("high") => Trigger::High,
+            Some("low") => Trigger::Low,
+            _ => Trigger::Rising,
+        };
+
+        let k = self.sim.add_component(Register::new(width, trigger, 0));
+        if let Some(label) = c.attr("label") {
+            self.sim.set_label(k, label);
+        }
+
+        for (i, (&d, &q)) in bits[0].iter().zip(&bits[1]).enumerate() {
+            self.nets.attach(d, k, i + 1);
+            self.nets.attach(q, k, width + i + 1);
+        }
+        self.nets.attach(bits[2][0], k, 2 * width + 1);
+        // an unconnected enable input leaves the register enabled
+        let enable = if connected[3] {
+            bits[3][0]
+        } else {
+            self.nets.constant(self.sim, true)
+        };
+        self.nets.attach(enable, k, 2 * width + 2);
+        self.nets.attach(bits[4][0], k, 2 * width + 3);
+
+        Ok(())
+    }
+
+    fn memory(
+        &mut self,
+        c: &Comp,
+        kind: &Kind,
+        bits: &[Vec<NetId>],
+        connected: &[bool],
+    ) -> Result<(), Error> {
+        let (addr, data) = (&bits[0], &bits[1]);
+        if addr.len() > 24 || data.len() > 32 {
+            return Err(too_wide(c));
+        }
+
+        let writable = matches!(kind, Kind::Ram);
+        let k = self.sim.add_component(Memory {
+            data: contents(c, addr.len())?,
+            addr_width: addr.len(),
+            data_width: data.len(),
+            writable,
+        });
+        if let Some(label) = c.attr("label") {
+            self.sim.set_label(k, label);
+        }
+
+        let width = addr.len() + data.len();
+        for (i, &net) in addr.iter().chain(data).enumerate() {
+            self.nets.attach(net, k, i + 1);
+        }
+
+        // sel, then ld, clr and clk for a RAM. An unconnected select leaves the
+        // memory selected and an unconnected load leaves a RAM driving the data.
+        for (i, (b, &connected)) in bits.iter().zip(connected).enumerate().skip(2) {
+            let net = if connected || i > 3 {
+                b[0]
+            } else {
+                self.nets.constant(self.sim, true)
+            };
+            self.nets.attach(net, k, width + i - 1);
+        }
+
+        Ok(())
+    }
+
+    fn subcircuit(
+        &mut self,
+        c: &Comp,
+        circuit: &'d Circuit<'d>,
+        bits: &[Vec<NetId>],
+    ) -> Result<(), Error> {
+        let name = c.attr("label").map_or_else(
+            || format!("{}_{}_{}", circuit.name, c.loc.0, c.loc.1),
+            str::to_string,
+        );
+
+        self.stack.push(circuit.name);
+        self.sim.enter_scope(&name, circuit.name);
+        let inner = self.circuit(circuit, None);
+        self.sim.exit_scope();
+        self.stack.pop();
+        let inner = inner?;
+
+        for ((pin, _), outer) in circuit.ports(c)?.iter().zip(bits) {
+            if let Some(inner) = inner.get(pin) {
+                for (&a, &b) in inner.iter().zip(outer) {
+                    self.nets.alias(a, b);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Logisim's ROM, or RAM with one bidirectional data bus. The data is only
+// driven while selected and, for a RAM, loading, which otherwise stores the
+// data bus on the rising edge of its clock while selected. Clear empties it.
+//
+// pins:
+//  1-A: addr
+//  A+1-A+D: data
+//  A+D+1: sel
+//  A+D+2: ld, RAM only
+//  A+D+3: clr, RAM only
+//  A+D+4: clk, RAM only
+struct Memory {
+    data: Vec<u32>,
+    addr_width: usize,
+    data_width: usize,
+    writable: bool,
+}
+
+impl Component for Memory {
+    fn pin_count(&self) -> usize {
+        self.addr_width + self.data_width + if self.writable { 4 } else { 1 }
+    }
+
+    fn pin_name(&self, pin: usize) -> String {
+        let (a, d) = (self.addr_width, self.data_width);
+
+        match pin - 1 {
+            p if p < a => format!("addr[{p}]"),
+            p if p < a + d => format!("data[{}]", p - a),
+            p => ["sel", "ld", "clr", "clk"][p - a - d].to_string(),
+        }
+    }
+
+    fn update(&mut self, io: &mut IO) {
+        let (a, d) = (self.addr_width, self.data_width);
+        let addr = io.read_range(1..=a) as usize;
+        let data = a + 1..=a + d;
+        let selected = io.read(a + d + 1);
+
+        if self.writable {
+            let load = io.read(a + d + 2);
+            if io.read(a + d + 3) {
+                self.data.iter_mut().for_each(|w| *w = 0);
+            } else if io.is_rising_edge(a + d + 4) && selected && !load {
+                self.data[addr] = io.read_range(data.clone());
+            }
+            if !load {
+                return;
+            }
+        }
+
+        if selected {
+            io.write_range(data, self.data[addr]);
+        }
+    }
+}
+
+fn too_wide(c: &Comp) -> Error {
+    Error::new(c.line, "components wider than 32 bits are not supported")
+}
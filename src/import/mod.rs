@@ -0,0 +1,205 @@
+use crate::{
+    components::{
+        logic::{And, Buffer, Gate, Nand, Nor, Not, Or, Xnor, Xor},
+        Static,
+    },
+    ComponentKey, PinId, Sim,
+};
+use fnv::FnvHashMap;
+use std::fmt;
+
+pub mod blif;
+pub mod logisim;
+pub mod verilog;
+
+// top level port name => Input/Output component holding its bits
+pub type Ports = FnvHashMap<String, ComponentKey>;
+
+#[derive(Debug)]
+pub struct Error {
+    line: usize,
+    message: String,
+}
+
+impl Error {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+
+    #[must_use]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+type NetId = usize;
+
+// Nets of the source netlist, aliased with a union-find and only turned into
+// Sim connections once every pin attached to them is known.
+struct Nets {
+    parent: Vec<NetId>,
+    pins: Vec<Vec<(ComponentKey, PinId)>>,
+    constants: [Option<NetId>; 2],
+    clock: Option<NetId>,
+}
+
+impl Nets {
+    fn new() -> Self {
+        Self {
+            parent: Vec::new(),
+            pins: Vec::new(),
+            constants: [None; 2],
+            clock: None,
+        }
+    }
+
+    fn add(&mut self) -> NetId {
+        let id = self.parent.len();
+        self.parent.push(id);
+        self.pins.push(Vec::new());
+        id
+    }
+
+    fn find(&mut self, mut net: NetId) -> NetId {
+        while self.parent[net] != net {
+            self.parent[net] = self.parent[self.parent[net]];
+            net = self.parent[net];
+        }
+
+        net
+    }
+
+    fn alias(&mut self, a: NetId, b: NetId) {
+        let (a, b) = (self.find(a), self.find(b));
+
+        if a != b {
+            self.parent[b] = a;
+            let pins = std::mem::take(&mut self.pins[b]);
+            self.pins[a].extend(pins);
+        }
+    }
+
+    fn attach(&mut self, net: NetId, c: ComponentKey, pin: PinId) {
+        let net = self.find(net);
+        self.pins[net].push((c, pin));
+    }
+
+    fn constant(&mut self, sim: &mut Sim, value: bool) -> NetId {
+        if let Some(net) = self.constants[usize::from(value)] {
+            return net;
+        }
+
+        let c = sim.add_component(Static::<1>(value.into()));
+        sim.set_label(c, if value { "1'b1" } else { "1'b0" });
+
+        let net = self.add();
+        self.attach(net, c, 1);
+        self.constants[usize::from(value)] = Some(net);
+        net
+    }
+
+    // net joined to the global clock of the Sim
+    fn clock(&mut self) -> NetId {
+        if let Some(net) = self.clock {
+            return net;
+        }
+
+        let net = self.add();
+        self.clock = Some(net);
+        net
+    }
+
+    // drives `y` with `gate` applied over all `inputs`, chaining wider gates
+    // through their non-inverting version
+    fn gate(&mut self, sim: &mut Sim, gate: Gate, y: NetId, inputs: &[NetId]) -> ComponentKey {
+        let (base, inverted) = match gate {
+            Gate::Nand => (Gate::And, true),
+            Gate::Nor => (Gate::Or, true),
+            Gate::Xnor => (Gate::Xor, true),
+            Gate::Not => (Gate::Buffer, true),
+            g => (g, false),
+        };
+
+        let (last, rest) = inputs.split_last().unwrap();
+        let gate = match rest.first() {
+            None if inverted => Gate::Not,
+            None => Gate::Buffer,
+            Some(_) => gate,
+        };
+
+        let acc = rest.iter().skip(1).fold(rest.first().copied(), |acc, &i| {
+            let t = self.add();
+            let c = add_gate(sim, base);
+            self.attach(acc.unwrap(), c, 1);
+            self.attach(i, c, 2);
+            self.attach(t, c, 3);
+            Some(t)
+        });
+
+        let c = add_gate(sim, gate);
+        if let Some(a) = acc {
+            self.attach(a, c, 1);
+        }
+        self.attach(*last, c, gate.inputs());
+        self.attach(y, c, gate.inputs() + 1);
+        c
+    }
+
+    fn connect(mut self, sim: &mut Sim) {
+        let clock = self.clock.map(|n| self.find(n));
+
+        self.pins
+            .iter()
+            .filter(|pins| pins.len() > 1)
+            .for_each(|pins| {
+                pins.windows(2)
+                    .for_each(|w| sim.connect(w[0].0, w[0].1, w[1].0, w[1].1));
+            });
+
+        if let Some(&(c, pin)) = clock.and_then(|n| self.pins[n].first()) {
+            sim.connect_to_clk(c, pin);
+        }
+    }
+}
+
+// runs an import as a single build, unless the caller is already batching
+// its own
+fn build<T>(sim: &mut Sim, import: impl FnOnce(&mut Sim) -> T) -> T {
+    if sim.is_building() {
+        return import(sim);
+    }
+
+    sim.begin_build();
+    let result = import(sim);
+    sim.finish_build();
+    result
+}
+
+fn add_gate(sim: &mut Sim, gate: Gate) -> ComponentKey {
+    match gate {
+        Gate::Buffer => sim.add_component(Buffer),
+        Gate::Not => sim.add_component(Not),
+        Gate::And => sim.add_component(And),
+        Gate::Or => sim.add_component(Or),
+        Gate::Nand => sim.add_component(Nand),
+        Gate::Nor => sim.add_component(Nor),
+        Gate::Xor => sim.add_component(Xor),
+        Gate::Xnor => sim.add_component(Xnor),
+    }
+}
@@ -0,0 +1,1178 @@
+use super::{Error, NetId, Nets, Ports};
+use crate::{
+    components::{
+        logic::Gate,
+        port::{Input, Output},
+    },
+    Sim,
+};
+use fnv::{FnvHashMap, FnvHashSet};
+use std::convert::TryFrom;
+
+const GATES: [&str; 8] = ["and", "or", "nand", "nor", "xor", "xnor", "not", "buf"];
+
+// widest vector or literal accepted, the smallest limit the standard lets tools set
+const MAX_WIDTH: u64 = 1 << 16;
+
+// keywords outside the supported subset, reported instead of being parsed as module instances
+const UNSUPPORTED: [&str; 26] = [
+    "always",
+    "initial",
+    "reg",
+    "integer",
+    "real",
+    "time",
+    "parameter",
+    "localparam",
+    "defparam",
+    "generate",
+    "genvar",
+    "function",
+    "task",
+    "specify",
+    "supply0",
+    "supply1",
+    "tri",
+    "wand",
+    "wor",
+    "bufif0",
+    "bufif1",
+    "notif0",
+    "notif1",
+    "pullup",
+    "pulldown",
+    "primitive",
+];
+
+/// Builds the module `top` (or the only module that is not instantiated by
+/// any other) out of `components::logic` gates, flattening module instances
+/// into scopes of `sim`.
+///
+/// # Errors
+///
+/// Will return an error pointing at the offending line if the source uses
+/// constructs outside the structural subset or is otherwise malformed
+pub fn load(sim: &mut Sim, source: &str, top: Option<&str>) -> Result<Ports, Error> {
+    super::build(sim, |sim| elaborate(sim, source, top))
+}
+
+fn elaborate(sim: &mut Sim, source: &str, top: Option<&str>) -> Result<Ports, Error> {
+    let modules = Parser::new(lex(source)?).modules()?;
+    // where errors about the source as a whole point
+    let end = source.lines().count().max(1);
+
+    let top = match top {
+        Some(name) => modules
+            .get(name)
+            .ok_or_else(|| Error::new(end, format!("unknown top module `{name}`")))?,
+        None => find_top(&modules, end)?,
+    };
+
+    let mut elab = Elab {
+        sim,
+        modules: &modules,
+        nets: Nets::new(),
+        stack: vec![top.name.clone()],
+    };
+
+    let signals = elab.instantiate(top)?;
+
+    let mut ports = Ports::default();
+    for name in &top.ports {
+        let dir = top.decl(name).and_then(|d| d.dir);
+        let bits = &signals[name].bits;
+
+        let c = if dir == Some(Dir::Input) {
+            elab.sim.add_component(Input::new(bits.len()))
+        } else {
+            elab.sim.add_component(Output::new(bits.len()))
+        };
+        elab.sim.set_label(c, name);
+
+        bits.iter()
+            .enumerate()
+            .for_each(|(i, &net)| elab.nets.attach(net, c, i + 1));
+        ports.insert(name.clone(), c);
+    }
+
+    let Elab { sim, nets, .. } = elab;
+    nets.connect(sim);
+
+    Ok(ports)
+}
+
+// the error points at the second candidate, or at the first module when
+// they all instantiate each other
+fn find_top(modules: &FnvHashMap<String, Module>, end: usize) -> Result<&Module, Error> {
+    let used = modules
+        .values()
+        .flat_map(|m| &m.items)
+        .filter_map(|i| match i {
+            Item::Instance { module, .. } => Some(module.as_str()),
+            _ => None,
+        })
+        .collect::<FnvHashSet<_>>();
+
+    let mut candidates = modules
+        .values()
+        .filter(|m| !used.contains(m.name.as_str()))
+        .collect::<Vec<_>>();
+    candidates.sort_unstable_by_key(|m| m.line);
+
+    match candidates.len() {
+        1 => Ok(candidates[0]),
+        0 => Err(match modules.values().map(|m| m.line).min() {
+            Some(line) => Error::new(line, "every module is instantiated by another"),
+            None => Error::new(end, "no modules found"),
+        }),
+        _ => {
+            let names = candidates
+                .iter()
+                .map(|m| m.name.as_str())
+                .collect::<Vec<_>>();
+
+            Err(Error::new(
+                candidates[1].line,
+                format!("cannot pick a top module among [{}]", names.join(", ")),
+            ))
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 17] = [
+    "~^", "^~", "~&", "~|", "(", ")", "[", "]", "{", "}", ",", ";", ":", ".", "=", "#", "~",
+];
+
+fn lex(source: &str) -> Result<Vec<(Token, usize)>, Error> {
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    let skip_until = |i: &mut usize, line: &mut usize, end: &[char]| {
+        while *i < chars.len() && !chars[*i..].starts_with(end) {
+            if chars[*i] == '\n' {
+                *line += 1;
+            }
+            *i += 1;
+        }
+        *i += end.len();
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let rest = &chars[i..];
+
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if rest.starts_with(&['/', '/']) || c == '`' {
+            // comments and compiler directives run until the end of the line
+            skip_until(&mut i, &mut line, &['\n']);
+            line += 1;
+        } else if rest.starts_with(&['/', '*']) {
+            skip_until(&mut i, &mut line, &['*', '/']);
+        } else if rest.starts_with(&['(', '*']) && !rest.starts_with(&['(', '*', ')']) {
+            // attributes
+            skip_until(&mut i, &mut line, &['*', ')']);
+        } else if c.is_ascii_alphabetic() || c == '_' || c == '\\' {
+            let start = if c == '\\' { i + 1 } else { i };
+            i = start;
+            while i < chars.len()
+                && if c == '\\' {
+                    !chars[i].is_whitespace()
+                } else {
+                    chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '$'
+                }
+            {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().collect()), line));
+        } else if c.is_ascii_digit() || c == '\'' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '_') {
+                i += 1;
+            }
+            if i < chars.len() && chars[i] == '\'' {
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+            }
+            tokens.push((Token::Number(chars[start..i].iter().collect()), line));
+        } else if let Some(s) = SYMBOLS
+            .iter()
+            .find(|s| rest.starts_with(&s.chars().collect::<Vec<_>>()))
+        {
+            tokens.push((Token::Symbol(s), line));
+            i += s.len();
+        } else if let Some(s) = ["&", "|", "^"].iter().find(|s| s.starts_with(c)) {
+            tokens.push((Token::Symbol(s), line));
+            i += 1;
+        } else {
+            return Err(Error::new(line, format!("unexpected character `{c}`")));
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_int(raw: &str, line: usize) -> Result<i64, Error> {
+    raw.replace('_', "")
+        .parse()
+        .map_err(|_| Error::new(line, format!("expected an integer, found `{raw}`")))
+}
+
+// bit n => bits[n]
+fn parse_number(raw: &str, line: usize) -> Result<Vec<bool>, Error> {
+    let raw = raw.replace('_', "");
+    let invalid = || Error::new(line, format!("invalid number `{raw}`"));
+
+    let (size, base, digits) = match raw.find('\'') {
+        Some(p) => {
+            let size = if p == 0 {
+                32
+            } else {
+                raw[..p].parse::<usize>().map_err(|_| invalid())?
+            };
+            if size as u64 > MAX_WIDTH {
+                return Err(Error::new(
+                    line,
+                    format!("`{raw}` is wider than {MAX_WIDTH} bits"),
+                ));
+            }
+            let spec = raw[p + 1..].trim_start_matches(&['s', 'S'][..]);
+            let base = spec.chars().next().ok_or_else(invalid)?;
+            (size, base.to_ascii_lowercase(), &spec[1..])
+        }
+        None => (32, 'd', raw.as_str()),
+    };
+
+    if digits.chars().any(|c| "xXzZ?".contains(c)) {
+        return Err(Error::new(line, "x and z values are not supported"));
+    }
+
+    let mut bits = match base {
+        'd' => {
+            let value = digits.parse::<u128>().map_err(|_| invalid())?;
+            (0..128).map(|i| value & (1 << i) != 0).collect::<Vec<_>>()
+        }
+        'b' | 'o' | 'h' => {
+            let (radix, width) = match base {
+                'b' => (2, 1),
+                'o' => (8, 3),
+                _ => (16, 4),
+            };
+
+            let mut bits = Vec::new();
+            for d in digits.chars().rev() {
+                let v = d.to_digit(radix).ok_or_else(invalid)?;
+                bits.extend((0..width).map(|i| v & (1 << i) != 0));
+            }
+            bits
+        }
+        _ => return Err(invalid()),
+    };
+
+    if bits.is_empty() {
+        return Err(invalid());
+    }
+
+    bits.resize(size, false);
+    Ok(bits)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Dir {
+    Input,
+    Output,
+}
+
+struct Decl {
+    name: String,
+    dir: Option<Dir>,
+    range: Option<(i64, i64)>,
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    And,
+    Or,
+    Xor,
+    Xnor,
+}
+
+enum Expr {
+    Ident(String),
+    Index(String, i64),
+    Slice(String, i64, i64),
+    Constant(Vec<bool>),
+    Concat(Vec<Expr>),
+    Replicate(usize, Box<Expr>),
+    Not(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+enum Connections {
+    Positional(Vec<Option<Expr>>),
+    Named(Vec<(String, Option<Expr>)>),
+}
+
+enum Item {
+    Gate {
+        kind: String,
+        name: Option<String>,
+        terminals: Vec<Expr>,
+        line: usize,
+    },
+    Instance {
+        module: String,
+        name: String,
+        connections: Connections,
+        line: usize,
+    },
+    Assign {
+        lhs: Expr,
+        rhs: Expr,
+        line: usize,
+    },
+}
+
+struct Module {
+    name: String,
+    line: usize,
+    ports: Vec<String>,
+    decls: Vec<Decl>,
+    // position in decls of the first declaration of each name
+    index: FnvHashMap<String, usize>,
+    items: Vec<Item>,
+}
+
+impl Module {
+    fn decl(&self, name: &str) -> Option<&Decl> {
+        self.index.get(name).map(|&i| &self.decls[i])
+    }
+
+    fn decl_mut(&mut self, name: &str) -> Option<&mut Decl> {
+        let i = *self.index.get(name)?;
+        Some(&mut self.decls[i])
+    }
+
+    fn declare(&mut self, decl: Decl) {
+        self.index
+            .entry(decl.name.clone())
+            .or_insert(self.decls.len());
+        self.decls.push(decl);
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(tokens: Vec<(Token, usize)>) -> Self {
+        Self { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map_or(0, |(_, l)| *l)
+    }
+
+    fn next(&mut self) -> Result<Token, Error> {
+        let t = self
+            .tokens
+            .get(self.pos)
+            .map(|(t, _)| t.clone())
+            .ok_or_else(|| Error::new(self.line(), "unexpected end of file"))?;
+        self.pos += 1;
+        Ok(t)
+    }
+
+    fn is(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(s)) if s == keyword)
+    }
+
+    fn eat(&mut self, symbol: &str) -> bool {
+        let found = self.is(symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), Error> {
+        let line = self.line();
+        match self.next()? {
+            Token::Symbol(s) if s == symbol => Ok(()),
+            t => Err(Error::new(
+                line,
+                format!("expected `{}`, found {}", symbol, describe(&t)),
+            )),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, Error> {
+        let line = self.line();
+        match self.next()? {
+            Token::Ident(s) => Ok(s),
+            t => Err(Error::new(
+                line,
+                format!("expected an identifier, found {}", describe(&t)),
+            )),
+        }
+    }
+
+    fn int(&mut self) -> Result<i64, Error> {
+        let line = self.line();
+        match self.next()? {
+            Token::Number(n) => parse_int(&n, line),
+            t => Err(Error::new(
+                line,
+                format!("expected an integer, found {}", describe(&t)),
+            )),
+        }
+    }
+
+    fn modules(mut self) -> Result<FnvHashMap<String, Module>, Error> {
+        let mut modules = FnvHashMap::default();
+
+        while self.peek().is_some() {
+            let line = self.line();
+            match self.ident()?.as_str() {
+                "module" => {
+                    let m = self.module(line)?;
+                    if modules.contains_key(&m.name) {
+                        return Err(Error::new(line, format!("module `{}` redefined", m.name)));
+                    }
+                    modules.insert(m.name.clone(), m);
+                }
+                other => {
+                    return Err(Error::new(
+                        line,
+                        format!("unsupported construct `{other}` outside a module"),
+                    ))
+                }
+            }
+        }
+
+        Ok(modules)
+    }
+
+    fn direction(&mut self) -> Result<Option<Dir>, Error> {
+        let line = self.line();
+        let dir = match self.peek() {
+            Some(Token::Ident(s)) if s == "input" => Some(Dir::Input),
+            Some(Token::Ident(s)) if s == "output" => Some(Dir::Output),
+            Some(Token::Ident(s)) if s == "inout" => {
+                return Err(Error::new(line, "inout ports are not supported"))
+            }
+            _ => None,
+        };
+
+        if dir.is_some() {
+            self.pos += 1;
+            if self.is_keyword("reg") {
+                return Err(Error::new(line, "unsupported construct `reg`"));
+            }
+            if self.is_keyword("wire") {
+                self.pos += 1;
+            }
+        }
+
+        Ok(dir)
+    }
+
+    fn range(&mut self) -> Result<Option<(i64, i64)>, Error> {
+        let line = self.line();
+        if self.eat("[") {
+            let msb = self.int()?;
+            self.expect(":")?;
+            let lsb = self.int()?;
+            self.expect("]")?;
+            if msb.abs_diff(lsb) >= MAX_WIDTH {
+                return Err(Error::new(
+                    line,
+                    format!("range [{msb}:{lsb}] is wider than {MAX_WIDTH} bits"),
+                ));
+            }
+            Ok(Some((msb, lsb)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn module(&mut self, line: usize) -> Result<Module, Error> {
+        let mut m = Module {
+            name: self.ident()?,
+            line,
+            ports: Vec::new(),
+            decls: Vec::new(),
+            index: FnvHashMap::default(),
+            items: Vec::new(),
+        };
+
+        if self.is("#") {
+            return Err(Error::new(
+                self.line(),
+                "module parameters are not supported",
+            ));
+        }
+
+        if self.eat("(") && !self.eat(")") {
+            // ANSI headers carry the direction and range over to the following names
+            let (mut dir, mut range) = (None, None);
+            loop {
+                if let Some(d) = self.direction()? {
+                    dir = Some(d);
+                    range = self.range()?;
+                }
+
+                let name = self.ident()?;
+                if dir.is_some() {
+                    m.declare(Decl {
+                        name: name.clone(),
+                        dir,
+                        range,
+                    });
+                }
+                m.ports.push(name);
+
+                if !self.eat(",") {
+                    break;
+                }
+            }
+            self.expect(")")?;
+        }
+        self.expect(";")?;
+
+        loop {
+            let line = self.line();
+            let keyword = self.ident()?;
+
+            match keyword.as_str() {
+                "endmodule" => break,
+                "input" | "output" | "inout" | "wire" => {
+                    self.pos -= 1;
+                    self.declaration(&mut m, line)?;
+                }
+                "assign" => loop {
+                    let lhs = self.expr()?;
+                    self.expect("=")?;
+                    let rhs = self.expr()?;
+                    m.items.push(Item::Assign { lhs, rhs, line });
+
+                    if !self.eat(",") {
+                        self.expect(";")?;
+                        break;
+                    }
+                },
+                k if GATES.contains(&k) => self.gates(&mut m, k, line)?,
+                k if UNSUPPORTED.contains(&k) || k == "module" => {
+                    return Err(Error::new(line, format!("unsupported construct `{k}`")));
+                }
+                _ => self.instances(&mut m, &keyword, line)?,
+            }
+        }
+
+        Ok(m)
+    }
+
+    fn declaration(&mut self, m: &mut Module, line: usize) -> Result<(), Error> {
+        let dir = if self.is_keyword("wire") {
+            self.pos += 1;
+            None
+        } else {
+            self.direction()?
+        };
+        let range = self.range()?;
+
+        loop {
+            let name = self.ident()?;
+            if self.eat("=") {
+                let rhs = self.expr()?;
+                m.items.push(Item::Assign {
+                    lhs: Expr::Ident(name.clone()),
+                    rhs,
+                    line,
+                });
+            }
+
+            match m.decl_mut(&name) {
+                Some(d) if d.range == range && (d.dir.is_none() || dir.is_none()) => {
+                    d.dir = d.dir.or(dir);
+                }
+                Some(_) => {
+                    return Err(Error::new(
+                        line,
+                        format!("conflicting declarations of `{name}`"),
+                    ))
+                }
+                None => m.declare(Decl { name, dir, range }),
+            }
+
+            if !self.eat(",") {
+                break self.expect(";");
+            }
+        }
+    }
+
+    fn gates(&mut self, m: &mut Module, kind: &str, line: usize) -> Result<(), Error> {
+        if self.is("#") {
+            return Err(Error::new(line, "gate delays are not supported"));
+        }
+
+        loop {
+            let line = self.line();
+            let name = match self.peek() {
+                Some(Token::Ident(_)) => Some(self.ident()?),
+                _ => None,
+            };
+            if self.is("[") {
+                return Err(Error::new(line, "gate instance arrays are not supported"));
+            }
+
+            self.expect("(")?;
+            let mut terminals = vec![self.expr()?];
+            while self.eat(",") {
+                terminals.push(self.expr()?);
+            }
+            self.expect(")")?;
+
+            if terminals.len() < 2 {
+                return Err(Error::new(
+                    line,
+                    format!("`{kind}` gate needs at least two terminals"),
+                ));
+            }
+
+            m.items.push(Item::Gate {
+                kind: kind.to_string(),
+                name,
+                terminals,
+                line,
+            });
+
+            if !self.eat(",") {
+                break self.expect(";");
+            }
+        }
+    }
+
+    fn instances(&mut self, m: &mut Module, module: &str, line: usize) -> Result<(), Error> {
+        if self.is("#") {
+            return Err(Error::new(line, "module parameters are not supported"));
+        }
+
+        loop {
+            let line = self.line();
+            let name = self.ident()?;
+            if self.is("[") {
+                return Err(Error::new(line, "instance arrays are not supported"));
+            }
+
+            self.expect("(")?;
+            let connections = self.connections()?;
+            self.expect(")")?;
+
+            m.items.push(Item::Instance {
+                module: module.to_string(),
+                name,
+                connections,
+                line,
+            });
+
+            if !self.eat(",") {
+                break self.expect(";");
+            }
+        }
+    }
+
+    fn connections(&mut self) -> Result<Connections, Error> {
+        if self.is(")") {
+            return Ok(Connections::Positional(Vec::new()));
+        }
+
+        if self.is(".") {
+            let mut named = Vec::new();
+            loop {
+                self.expect(".")?;
+                let port = self.ident()?;
+                self.expect("(")?;
+                let expr = if self.is(")") {
+                    None
+                } else {
+                    Some(self.expr()?)
+                };
+                self.expect(")")?;
+                named.push((port, expr));
+
+                if !self.eat(",") {
+                    break Ok(Connections::Named(named));
+                }
+            }
+        } else {
+            let mut positional = Vec::new();
+            loop {
+                positional.push(if self.is(",") || self.is(")") {
+                    None
+                } else {
+                    Some(self.expr()?)
+                });
+
+                if !self.eat(",") {
+                    break Ok(Connections::Positional(positional));
+                }
+            }
+        }
+    }
+
+    // precedence: ~ > & > ^ ~^ > |
+    fn expr(&mut self) -> Result<Expr, Error> {
+        let mut lhs = self.xor()?;
+        while self.eat("|") {
+            lhs = Expr::Binary(Op::Or, Box::new(lhs), Box::new(self.xor()?));
+        }
+        Ok(lhs)
+    }
+
+    fn xor(&mut self) -> Result<Expr, Error> {
+        let mut lhs = self.and()?;
+        loop {
+            let op = if self.eat("^") {
+                Op::Xor
+            } else if self.eat("~^") || self.eat("^~") {
+                Op::Xnor
+            } else {
+                break Ok(lhs);
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.and()?));
+        }
+    }
+
+    fn and(&mut self) -> Result<Expr, Error> {
+        let mut lhs = self.unary()?;
+        while self.eat("&") {
+            lhs = Expr::Binary(Op::And, Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, Error> {
+        let line = self.line();
+
+        if self.eat("~") {
+            Ok(Expr::Not(Box::new(self.unary()?)))
+        } else if ["&", "|", "^", "~&", "~|", "~^", "^~"]
+            .iter()
+            .any(|s| self.is(s))
+        {
+            Err(Error::new(line, "reduction operators are not supported"))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, Error> {
+        let line = self.line();
+
+        match self.next()? {
+            Token::Ident(name) => {
+                if self.eat("[") {
+                    let msb = self.int()?;
+                    let e = if self.eat(":") {
+                        Expr::Slice(name, msb, self.int()?)
+                    } else {
+                        Expr::Index(name, msb)
+                    };
+                    self.expect("]")?;
+                    Ok(e)
+                } else {
+                    Ok(Expr::Ident(name))
+                }
+            }
+            Token::Number(n) => Ok(Expr::Constant(parse_number(&n, line)?)),
+            Token::Symbol("(") => {
+                let e = self.expr()?;
+                self.expect(")")?;
+                Ok(e)
+            }
+            Token::Symbol("{") => {
+                let first = self.expr()?;
+
+                if let (Expr::Constant(_), true) = (&first, self.is("{")) {
+                    let count = match self.tokens[self.pos - 1].0 {
+                        Token::Number(ref n) => usize::try_from(parse_int(n, line)?)
+                            .map_err(|_| Error::new(line, "invalid replication count"))?,
+                        _ => return Err(Error::new(line, "invalid replication count")),
+                    };
+                    if count as u64 > MAX_WIDTH {
+                        return Err(Error::new(
+                            line,
+                            format!("replication is wider than {MAX_WIDTH} bits"),
+                        ));
+                    }
+                    self.expect("{")?;
+                    let e = self.expr()?;
+                    self.expect("}")?;
+                    self.expect("}")?;
+                    return Ok(Expr::Replicate(count, Box::new(e)));
+                }
+
+                let mut parts = vec![first];
+                while self.eat(",") {
+                    parts.push(self.expr()?);
+                }
+                self.expect("}")?;
+                Ok(Expr::Concat(parts))
+            }
+            t @ Token::Symbol(_) => Err(Error::new(
+                line,
+                format!("unsupported expression starting with {}", describe(&t)),
+            )),
+        }
+    }
+}
+
+fn describe(t: &Token) -> String {
+    match t {
+        Token::Ident(s) | Token::Number(s) => format!("`{s}`"),
+        Token::Symbol(s) => format!("`{s}`"),
+    }
+}
+
+struct Signal {
+    msb: i64,
+    lsb: i64,
+    // bit n => bits[n]
+    bits: Vec<NetId>,
+}
+
+impl Signal {
+    fn index(&self, i: i64) -> Option<usize> {
+        let offset = if self.msb >= self.lsb {
+            i - self.lsb
+        } else {
+            self.lsb - i
+        };
+
+        usize::try_from(offset)
+            .ok()
+            .filter(|&o| o < self.bits.len())
+    }
+}
+
+type Signals = FnvHashMap<String, Signal>;
+
+struct Elab<'a> {
+    sim: &'a mut Sim,
+    modules: &'a FnvHashMap<String, Module>,
+    nets: Nets,
+    stack: Vec<String>,
+}
+
+impl Elab<'_> {
+    fn instantiate(&mut self, module: &Module) -> Result<Signals, Error> {
+        let mut signals = Signals::default();
+
+        for d in &module.decls {
+            let (msb, lsb) = d.range.unwrap_or((0, 0));
+            let width = usize::try_from(msb.abs_diff(lsb) + 1)
+                .map_err(|_| Error::new(module.line, format!("`{}` is too wide", d.name)))?;
+
+            signals.insert(
+                d.name.clone(),
+                Signal {
+                    msb,
+                    lsb,
+                    bits: (0..width).map(|_| self.nets.add()).collect(),
+                },
+            );
+        }
+
+        for p in &module.ports {
+            if module.decl(p).and_then(|d| d.dir).is_none() {
+                return Err(Error::new(
+                    module.line,
+                    format!("port `{}` of `{}` has no direction", p, module.name),
+                ));
+            }
+        }
+
+        for item in &module.items {
+            match item {
+                Item::Gate {
+                    kind,
+                    name,
+                    terminals,
+                    line,
+                } => {
+                    let mut nets = Vec::new();
+                    for t in terminals {
+                        let bits = self.eval(&signals, t, *line)?;
+                        if bits.len() != 1 {
+                            return Err(Error::new(*line, "gate terminals must be 1 bit wide"));
+                        }
+                        nets.push(bits[0]);
+                    }
+
+                    self.gate(kind, name.as_deref(), &nets);
+                }
+                Item::Instance {
+                    module: child,
+                    name,
+                    connections,
+                    line,
+                } => self.instance(&signals, child, name, connections, *line)?,
+                Item::Assign { lhs, rhs, line } => {
+                    let lhs = self.lvalue(&signals, lhs, *line)?;
+                    let rhs = self.eval(&signals, rhs, *line)?;
+                    self.drive(&lhs, &rhs);
+                }
+            }
+        }
+
+        Ok(signals)
+    }
+
+    fn instance(
+        &mut self,
+        signals: &Signals,
+        module: &str,
+        name: &str,
+        connections: &Connections,
+        line: usize,
+    ) -> Result<(), Error> {
+        let child = self
+            .modules
+            .get(module)
+            .ok_or_else(|| Error::new(line, format!("unknown module `{module}`")))?;
+
+        if self.stack.iter().any(|m| m == module) {
+            return Err(Error::new(
+                line,
+                format!("module `{module}` instantiates itself"),
+            ));
+        }
+
+        self.stack.push(module.to_string());
+        self.sim.enter_scope(name, module);
+        let ports = self.instantiate(child);
+        self.sim.exit_scope();
+        self.stack.pop();
+        let ports = ports?;
+
+        let connections = match connections {
+            Connections::Positional(exprs) => {
+                if exprs.len() > child.ports.len() {
+                    return Err(Error::new(
+                        line,
+                        format!("too many connections to `{module}`"),
+                    ));
+                }
+
+                child.ports.iter().zip(exprs).collect::<Vec<_>>()
+            }
+            Connections::Named(named) => {
+                for (port, _) in named {
+                    if !child.ports.contains(port) {
+                        return Err(Error::new(
+                            line,
+                            format!("module `{module}` has no port `{port}`"),
+                        ));
+                    }
+                }
+
+                named.iter().map(|(p, e)| (p, e)).collect()
+            }
+        };
+
+        for (port, expr) in connections {
+            if let Some(expr) = expr {
+                let bits = &ports[port].bits;
+
+                if child.decl(port).unwrap().dir == Some(Dir::Output) {
+                    let lhs = self.lvalue(signals, expr, line)?;
+                    self.drive(&lhs, bits);
+                } else {
+                    let rhs = self.eval(signals, expr, line)?;
+                    self.drive(bits, &rhs);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // extra bits of `rhs` are dropped and missing ones are tied to 0
+    fn drive(&mut self, lhs: &[NetId], rhs: &[NetId]) {
+        for (i, &l) in lhs.iter().enumerate() {
+            let r = match rhs.get(i) {
+                Some(&r) => r,
+                None => self.nets.constant(self.sim, false),
+            };
+            self.nets.alias(l, r);
+        }
+    }
+
+    fn signal<'s>(signals: &'s Signals, name: &str, line: usize) -> Result<&'s Signal, Error> {
+        signals
+            .get(name)
+            .ok_or_else(|| Error::new(line, format!("undeclared identifier `{name}`")))
+    }
+
+    fn lvalue(&mut self, signals: &Signals, expr: &Expr, line: usize) -> Result<Vec<NetId>, Error> {
+        match expr {
+            Expr::Ident(_) | Expr::Index(..) | Expr::Slice(..) => self.eval(signals, expr, line),
+            Expr::Concat(parts) => {
+                let mut bits = Vec::new();
+                for p in parts.iter().rev() {
+                    bits.extend(self.lvalue(signals, p, line)?);
+                }
+                Ok(bits)
+            }
+            _ => Err(Error::new(line, "expression cannot be assigned to")),
+        }
+    }
+
+    fn eval(&mut self, signals: &Signals, expr: &Expr, line: usize) -> Result<Vec<NetId>, Error> {
+        Ok(match expr {
+            Expr::Ident(name) => Self::signal(signals, name, line)?.bits.clone(),
+            Expr::Index(name, i) => {
+                let s = Self::signal(signals, name, line)?;
+                let i = s.index(*i).ok_or_else(|| {
+                    Error::new(line, format!("index {i} out of range for `{name}`"))
+                })?;
+                vec![s.bits[i]]
+            }
+            Expr::Slice(name, msb, lsb) => {
+                let s = Self::signal(signals, name, line)?;
+                let out_of_range = || {
+                    Error::new(
+                        line,
+                        format!("range [{msb}:{lsb}] out of range for `{name}`"),
+                    )
+                };
+                let (hi, lo) = (
+                    s.index(*msb).ok_or_else(out_of_range)?,
+                    s.index(*lsb).ok_or_else(out_of_range)?,
+                );
+                if hi < lo {
+                    return Err(Error::new(
+                        line,
+                        format!("reversed part select on `{name}`"),
+                    ));
+                }
+                s.bits[lo..=hi].to_vec()
+            }
+            Expr::Constant(bits) => bits
+                .iter()
+                .map(|&b| self.nets.constant(self.sim, b))
+                .collect(),
+            Expr::Concat(parts) => {
+                let mut bits = Vec::new();
+                for p in parts.iter().rev() {
+                    bits.extend(self.eval(signals, p, line)?);
+                }
+                bits
+            }
+            Expr::Replicate(count, e) => {
+                let bits = self.eval(signals, e, line)?;
+                if (bits.len() as u64).saturating_mul(*count as u64) > MAX_WIDTH {
+                    return Err(Error::new(
+                        line,
+                        format!("replication is wider than {MAX_WIDTH} bits"),
+                    ));
+                }
+                bits.repeat(*count)
+            }
+            Expr::Not(e) => self
+                .eval(signals, e, line)?
+                .into_iter()
+                .map(|a| {
+                    let y = self.nets.add();
+                    self.gate("not", None, &[y, a]);
+                    y
+                })
+                .collect(),
+            Expr::Binary(op, a, b) => {
+                let mut a = self.eval(signals, a, line)?;
+                let mut b = self.eval(signals, b, line)?;
+
+                let width = a.len().max(b.len());
+                let zero = self.nets.constant(self.sim, false);
+                a.resize(width, zero);
+                b.resize(width, zero);
+
+                let kind = match op {
+                    Op::And => "and",
+                    Op::Or => "or",
+                    Op::Xor => "xor",
+                    Op::Xnor => "xnor",
+                };
+
+                a.into_iter()
+                    .zip(b)
+                    .map(|(a, b)| {
+                        let y = self.nets.add();
+                        self.gate(kind, None, &[y, a, b]);
+                        y
+                    })
+                    .collect()
+            }
+        })
+    }
+
+    // terminals follow the primitive order: outputs first for and-like gates,
+    // input last for not/buf
+    fn gate(&mut self, kind: &str, name: Option<&str>, terminals: &[NetId]) {
+        let gate = match kind {
+            "and" => Gate::And,
+            "or" => Gate::Or,
+            "nand" => Gate::Nand,
+            "nor" => Gate::Nor,
+            "xor" => Gate::Xor,
+            "xnor" => Gate::Xnor,
+            "not" => Gate::Not,
+            _ => Gate::Buffer,
+        };
+
+        let gates = if gate.inputs() == 1 {
+            let (input, outputs) = terminals.split_last().unwrap();
+            outputs
+                .iter()
+                .map(|&y| self.nets.gate(self.sim, gate, y, &[*input]))
+                .collect::<Vec<_>>()
+        } else {
+            let (y, inputs) = terminals.split_first().unwrap();
+            vec![self.nets.gate(self.sim, gate, *y, inputs)]
+        };
+
+        if let Some(n) = name {
+            for c in gates {
+                self.sim.set_label(c, n);
+            }
+        }
+    }
+}
@@ -3,13 +3,28 @@
 
 type PinId = usize;
 type ComponentKey = usize;
+type ScopeId = usize;
 
 pub mod components;
+mod compiled;
 mod component;
+pub mod elf;
+mod event;
+mod export;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod gdb;
+pub mod import;
+mod partition;
+mod scope;
 mod sim;
+mod watch;
 
+pub use compiled::Parallel;
 pub use component::{Component, MetaComponent, PinIO, IO};
-pub use sim::Sim;
+pub use graph::storage;
+pub use scope::Scope;
+pub use sim::{Sim, StopReason, StorageKind};
+pub use watch::{Condition, Hit};
 
 #[cfg(target_arch = "wasm32")]
 pub use component::JsIO;
@@ -0,0 +1,276 @@
+use crate::{
+    component::{Boxed, Component, Wrapper},
+    event::{Netlist, Pass, Scratch, State},
+    ComponentKey, Sim,
+};
+use slab::Slab;
+
+const NONE: usize = usize::MAX;
+
+// Worker threads kept for the life of the Sim. Wasm has none to start, so
+// there everything runs on the main thread.
+pub(crate) struct Pool {
+    #[cfg(not(target_arch = "wasm32"))]
+    threads: rayon::ThreadPool,
+}
+
+impl Pool {
+    // None when the threads cannot be started
+    pub(crate) fn new(threads: usize) -> Option<Self> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .ok()
+            .map(|threads| Self { threads });
+        #[cfg(target_arch = "wasm32")]
+        let pool = {
+            let _ = threads;
+            None
+        };
+        pool
+    }
+
+    // runs each worker's jobs on a thread of the pool
+    #[cfg(not(target_arch = "wasm32"))]
+    fn run(&self, netlist: Netlist<'_>, workers: Vec<Vec<Job<'_>>>) -> Scratch {
+        let mut results = workers
+            .iter()
+            .map(|_| Scratch::default())
+            .collect::<Vec<_>>();
+        self.threads.scope(|s| {
+            for (jobs, result) in workers.into_iter().zip(&mut results) {
+                s.spawn(move |_| *result = work(netlist, jobs));
+            }
+        });
+
+        results
+            .into_iter()
+            .fold(Scratch::default(), |mut scratch, r| {
+                scratch.merge(r);
+                scratch
+            })
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn run(&self, netlist: Netlist<'_>, workers: Vec<Vec<Job<'_>>>) -> Scratch {
+        work(netlist, workers.into_iter().flatten().collect())
+    }
+}
+
+// Weakly connected regions of the netlist, components sharing no net besides
+// the clock. Regions never observe each other, so the updates a sync point
+// starts in one of them can run on any thread and in any order relative to
+// the rest while giving the same result as the single threaded engine.
+pub(crate) struct Partitions {
+    clock: usize,
+    regions: usize,
+    // regions with a component that must stay on the Sim's thread
+    local: Vec<bool>,
+    // region and index within it, by net root and by component key
+    region_of_net: Vec<usize>,
+    net_index: Vec<usize>,
+    region_of_component: Vec<usize>,
+    component_index: Vec<usize>,
+}
+
+impl Partitions {
+    pub(crate) fn new(sim: &Sim, nets: usize) -> Self {
+        let clock = sim.net(0);
+        let size = sim.keys().max().map_or(0, |k| k + 1);
+
+        // union-find over component keys, joined through the nets they share
+        let mut parent = (0..size).collect::<Vec<_>>();
+        let find = |parent: &mut Vec<usize>, mut k: usize| {
+            while parent[k] != k {
+                parent[k] = parent[parent[k]];
+                k = parent[k];
+            }
+            k
+        };
+
+        let mut owner = vec![NONE; nets];
+        for k in sim.keys() {
+            for &p in sim.pins(k) {
+                let net = sim.net(p);
+                if net == clock {
+                    continue;
+                }
+
+                if owner[net] == NONE {
+                    owner[net] = k;
+                } else {
+                    let (a, b) = (find(&mut parent, owner[net]), find(&mut parent, k));
+                    parent[b] = a;
+                }
+            }
+        }
+
+        let mut region_of_component = vec![NONE; size];
+        let mut component_index = vec![NONE; size];
+        let mut regions = vec![NONE; size];
+        let mut counts = Vec::new();
+        let mut local = Vec::new();
+        for k in sim.keys() {
+            let root = find(&mut parent, k);
+            if regions[root] == NONE {
+                regions[root] = counts.len();
+                counts.push(0);
+                local.push(false);
+            }
+
+            let r = regions[root];
+            region_of_component[k] = r;
+            component_index[k] = counts[r];
+            counts[r] += 1;
+            local[r] |= !sim.is_send(k);
+        }
+
+        let mut region_of_net = vec![NONE; nets];
+        let mut net_index = vec![NONE; nets];
+        let mut counts = vec![0; counts.len()];
+        for (net, &k) in owner.iter().enumerate() {
+            if k != NONE {
+                let r = region_of_component[k];
+                region_of_net[net] = r;
+                net_index[net] = counts[r];
+                counts[r] += 1;
+            }
+        }
+
+        Self {
+            clock,
+            regions: counts.len(),
+            local,
+            region_of_net,
+            net_index,
+            region_of_component,
+            component_index,
+        }
+    }
+
+    // Updates `affected`, in order, with each region's components updated on
+    // one of `threads` workers of the pool, and returns what the passes wrote.
+    // None when all of them belong to the same region and there is nothing to
+    // run in parallel, or when one of the regions must stay on this thread.
+    pub(crate) fn run(
+        &self,
+        pool: &Pool,
+        threads: usize,
+        netlist: Netlist<'_>,
+        values: &mut [Option<bool>],
+        components: &mut Slab<Boxed>,
+        affected: &[ComponentKey],
+    ) -> Option<Scratch> {
+        let mut slots = vec![NONE; self.regions];
+        let mut jobs = Vec::<Job<'_>>::new();
+        let clock = (self.clock, values[self.clock]);
+
+        for &k in affected {
+            let r = self.region_of_component[k];
+            if self.local[r] {
+                return None;
+            }
+            if slots[r] == NONE {
+                slots[r] = jobs.len();
+                jobs.push((
+                    Region {
+                        partitions: self,
+                        index: r,
+                        clock,
+                        values: Vec::new(),
+                        components: Vec::new(),
+                    },
+                    Vec::new(),
+                ));
+            }
+            jobs[slots[r]].1.push(k);
+        }
+
+        if jobs.len() < 2 {
+            return None;
+        }
+
+        for (net, v) in values.iter_mut().enumerate() {
+            let r = self.region_of_net[net];
+            if r != NONE && slots[r] != NONE {
+                jobs[slots[r]].0.values.push(v);
+            }
+        }
+        for (k, c) in components.iter_mut() {
+            let slot = slots[self.region_of_component[k]];
+            if slot != NONE {
+                // every component of a region taking part is Send
+                if let Some(c) = c.as_send_mut() {
+                    jobs[slot].0.components.push(c);
+                }
+            }
+        }
+
+        let mut workers = (0..threads.min(jobs.len()))
+            .map(|_| Vec::new())
+            .collect::<Vec<_>>();
+        let count = workers.len();
+        for (i, job) in jobs.into_iter().enumerate() {
+            workers[i % count].push(job);
+        }
+
+        Some(pool.run(netlist, workers))
+    }
+}
+
+// a region and the components to update in it
+type Job<'a> = (Region<'a>, Vec<ComponentKey>);
+
+fn work(netlist: Netlist<'_>, jobs: Vec<Job<'_>>) -> Scratch {
+    jobs.into_iter()
+        .fold(Scratch::default(), |scratch, (region, affected)| {
+            let mut pass = Pass::new(netlist, None, region, scratch);
+            for k in affected {
+                pass.update_component(k);
+            }
+            pass.finish()
+        })
+}
+
+// the nets and components of one region, by their index within it
+struct Region<'a> {
+    partitions: &'a Partitions,
+    index: usize,
+    // the clock as this region sees it, a component writing it only reaching
+    // the others once the Sim applies the write
+    clock: (usize, Option<bool>),
+    values: Vec<&'a mut Option<bool>>,
+    components: Vec<&'a mut Wrapper<dyn Component + Send>>,
+}
+
+impl State for Region<'_> {
+    fn get(&self, net: usize) -> Option<bool> {
+        if net == self.clock.0 {
+            self.clock.1
+        } else {
+            *self.values[self.partitions.net_index[net]]
+        }
+    }
+
+    fn set(&mut self, net: usize, value: bool) {
+        if net == self.clock.0 {
+            self.clock.1 = Some(value);
+        } else {
+            *self.values[self.partitions.net_index[net]] = Some(value);
+        }
+    }
+
+    fn component(&self, k: ComponentKey) -> &Wrapper {
+        &*self.components[self.partitions.component_index[k]]
+    }
+
+    fn component_mut(&mut self, k: ComponentKey) -> &mut Wrapper {
+        &mut *self.components[self.partitions.component_index[k]]
+    }
+
+    // the clock's other readers are left to the Sim
+    fn owns(&self, k: ComponentKey) -> bool {
+        self.partitions.region_of_component[k] == self.index
+    }
+}
@@ -0,0 +1,34 @@
+use crate::ScopeId;
+
+pub struct Scope {
+    name: String,
+    module: String,
+    parent: Option<ScopeId>,
+}
+
+impl Scope {
+    pub(crate) fn new(name: &str, module: &str, parent: Option<ScopeId>) -> Self {
+        Self {
+            name: name.to_string(),
+            module: module.to_string(),
+            parent,
+        }
+    }
+
+    // instance name inside the parent scope
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // name of the module this scope is an instance of
+    #[must_use]
+    pub fn module(&self) -> &str {
+        &self.module
+    }
+
+    #[must_use]
+    pub fn parent(&self) -> Option<ScopeId> {
+        self.parent
+    }
+}
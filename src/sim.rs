@@ -1,21 +1,82 @@
 use crate::{
-    component::{Component, Wrapper},
-    ComponentKey, PinId,
+    compiled::{Parallel, Program},
+    component::{Boxed, Component, Wrapper},
+    event::{Netlist, Pass, Scratch, Whole},
+    export,
+    partition::{Partitions, Pool},
+    scope::Scope,
+    watch::{Condition, Hit, Watchpoint},
+    ComponentKey, PinId, ScopeId,
+};
+use fnv::FnvHashMap;
+use graph::{
+    storage::{self, Storage},
+    Graph,
 };
-use fnv::{FnvHashMap, FnvHashSet};
-use graph::Graph;
 use slab::Slab;
 
 #[cfg(target_arch = "wasm32")]
 use {crate::component::JsComponent, wasm_bindgen::prelude::*};
 
+// why a run returned
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StopReason {
+    // every requested tick ran
+    Done,
+    Condition,
+    // a whole clock cycle went by without any net changing
+    Stable,
+    // max_ticks ran without the condition being met
+    Limit,
+    // a watchpoint fired, see Sim::last_hit
+    Breakpoint,
+}
+
+// connection storages to build a Sim on, for callers that cannot name a
+// Storage type such as JavaScript
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StorageKind {
+    K2Tree,
+    AdjacencyList,
+}
+
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub struct Sim {
     graph: Graph,
-    components: Slab<Wrapper>,
+    components: Slab<Boxed>,
+    // indexed by the root pin of each net
     values: Vec<Option<bool>>,
-    pin_to_component: FnvHashMap<PinId, ComponentKey>,
-    pin_to_value: FnvHashMap<PinId, usize>,
+    // indexed by pin, pin 0 (global clk) has no component
+    pin_to_component: Vec<Option<ComponentKey>>,
+    // connections and new components waiting for finish_build
+    building: bool,
+    pending: Vec<(PinId, PinId)>,
+    added: Vec<ComponentKey>,
+    // compiled mode, the program being rebuilt whenever the netlist changes
+    compiled: bool,
+    program: Option<Program>,
+    // worker threads for independent partitions, none with 1 thread, and
+    // whether a component was seen driving the clock, keeping every pass on
+    // this thread
+    threads: usize,
+    pool: Option<Pool>,
+    partitions: Option<Partitions>,
+    clock_driven: bool,
+    // clock edges since creation, and the limit on runs waiting for a condition
+    ticks: usize,
+    max_ticks: usize,
+    // checked after every tick, pausing runs when one fires
+    watchpoints: Slab<Watchpoint>,
+    hit: Option<Hit>,
+    // buffers reused by every pass
+    scratch: Scratch,
+    affected: Vec<ComponentKey>,
+    scopes: Vec<Scope>,
+    scope: Option<ScopeId>,
+    component_scope: FnvHashMap<ComponentKey, ScopeId>,
+    labels: FnvHashMap<ComponentKey, String>,
 }
 
 impl Default for Sim {
@@ -29,31 +90,23 @@ impl Sim {
     #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
     #[must_use]
     pub fn new() -> Self {
-        #[cfg(target_arch = "wasm32")]
-        console_error_panic_hook::set_once();
-
-        let mut s = Self {
-            graph: Graph::new(),
-            components: Slab::new(),
-            values: Vec::new(),
-            pin_to_component: FnvHashMap::with_hasher(Default::default()),
-            pin_to_value: FnvHashMap::with_hasher(Default::default()),
-        };
-
-        // create pin 0 (global clk)
-        s.create_pin();
+        Self::with_storage(storage::K2Tree::new())
+    }
 
-        s
+    #[must_use]
+    pub fn with_storage_kind(kind: StorageKind) -> Self {
+        match kind {
+            StorageKind::K2Tree => Self::new(),
+            StorageKind::AdjacencyList => Self::with_storage(storage::AdjacencyList::new()),
+        }
     }
 
     pub fn connect_to_clk(&mut self, c: ComponentKey, pin: PinId) {
-        self.graph.add_edge(self.pin(c, pin), 0);
-        self.update_connections();
+        self.join(self.pin(c, pin), 0);
     }
 
     pub fn connect(&mut self, c1: ComponentKey, pin1: PinId, c2: ComponentKey, pin2: PinId) {
         self._connect(c1, pin1, c2, pin2);
-        self.update_connections();
     }
 
     /// # Panics
@@ -75,13 +128,148 @@ impl Sim {
             .iter()
             .zip(pins2)
             .for_each(|(&pin1, &pin2)| self._connect(c1, pin1, c2, pin2));
-        self.update_connections();
+    }
+
+    // until finish_build, connections are only recorded and new components are
+    // not updated, so wiring large circuits does not touch the nets each time
+    pub fn begin_build(&mut self) {
+        self.building = true;
+    }
+
+    #[must_use]
+    pub fn is_building(&self) -> bool {
+        self.building
+    }
+
+    pub fn finish_build(&mut self) {
+        self.building = false;
+
+        for (a, b) in std::mem::take(&mut self.pending) {
+            self.join(a, b);
+        }
+        for k in std::mem::take(&mut self.added) {
+            self.update_component(k);
+        }
+        self.settle();
+    }
+
+    // In compiled mode the acyclic gate logic is levelized and evaluated as a
+    // flat program instead of gate by gate, run whenever a component writes
+    // one of its inputs so the gates settle where the event driven engine
+    // would have updated them. Components only ever see the gates settled,
+    // which is not always what the event driven engine shows them: updating
+    // depth first, it can reach a component down one of several reconverging
+    // paths before the others, so a register clocked on the same edge as one
+    // feeding it through such logic, or clocked by the logic itself, may
+    // sample a glitch there and the settled value here. With threads, the
+    // passes run in parallel update gates one by one.
+    pub fn set_compiled(&mut self, compiled: bool) {
+        self.compiled = compiled;
+        self.program = None;
+        self.settle();
     }
 
     pub fn tick(&mut self) {
-        let mut changes = FnvHashMap::default();
-        changes.insert(0, !self._read(0).unwrap_or(false));
-        self.propagate_changes(&changes, false);
+        self.propagate_changes(&[(0, !self._read(0).unwrap_or(false))], false);
+        self.settle();
+        self.ticks += 1;
+
+        if !self.watchpoints.is_empty() {
+            self.check_watchpoints();
+        }
+    }
+
+    pub fn run(&mut self, ticks: usize) -> StopReason {
+        self.hit = None;
+
+        for _ in 0..ticks {
+            self.tick();
+            if self.hit.is_some() {
+                return StopReason::Breakpoint;
+            }
+        }
+        StopReason::Done
+    }
+
+    // ticks until `pin` reads `value`, checked before every tick
+    pub fn run_until_pin(&mut self, c: ComponentKey, pin: PinId, value: bool) -> StopReason {
+        self.run_until(|s| s.read(c, pin) == value)
+    }
+
+    // ticks until a full clock cycle changes nothing but the clock itself
+    pub fn run_until_stable(&mut self) -> StopReason {
+        let mut quiet = 0;
+        self.hit = None;
+
+        for _ in 0..self.max_ticks {
+            let writes = self.scratch.writes;
+            self.tick();
+            if self.hit.is_some() {
+                return StopReason::Breakpoint;
+            }
+
+            // the clock is the only net written
+            if self.scratch.writes.wrapping_sub(writes) == 1 {
+                quiet += 1;
+                if quiet == 2 {
+                    return StopReason::Stable;
+                }
+            } else {
+                quiet = 0;
+            }
+        }
+
+        StopReason::Limit
+    }
+
+    // clock edges so far, two per cycle
+    #[must_use]
+    pub fn ticks(&self) -> usize {
+        self.ticks
+    }
+
+    #[must_use]
+    pub fn cycles(&self) -> usize {
+        self.ticks / 2
+    }
+
+    // how many ticks runs waiting for a condition may take before giving up
+    pub fn set_max_ticks(&mut self, max_ticks: usize) {
+        self.max_ticks = max_ticks;
+    }
+
+    // Watchpoints on `pins` of `c`, read as a bus (bit n => pins[n]). They are
+    // checked after every tick against what they read after the one before,
+    // so a change made by write() fires them on the next tick instead.
+    pub fn watch_equals(&mut self, c: ComponentKey, pins: &[PinId], value: u32) -> usize {
+        self.add_watchpoint(c, pins, Condition::Equals(value))
+    }
+
+    pub fn watch_change(&mut self, c: ComponentKey, pins: &[PinId]) -> usize {
+        self.add_watchpoint(c, pins, Condition::Change)
+    }
+
+    pub fn watch_rising(&mut self, c: ComponentKey, pins: &[PinId]) -> usize {
+        self.add_watchpoint(c, pins, Condition::Rising)
+    }
+
+    pub fn watch_falling(&mut self, c: ComponentKey, pins: &[PinId]) -> usize {
+        self.add_watchpoint(c, pins, Condition::Falling)
+    }
+
+    pub fn watch_range(&mut self, c: ComponentKey, pins: &[PinId], lo: u32, hi: u32) -> usize {
+        self.add_watchpoint(c, pins, Condition::InRange(lo, hi))
+    }
+
+    // whether `id` was a watchpoint, ids of removed ones being reused
+    pub fn remove_watchpoint(&mut self, id: usize) -> bool {
+        self.watchpoints.try_remove(id).is_some()
+    }
+
+    // the watchpoint that stopped the last run, or fired since it started
+    #[must_use]
+    pub fn last_hit(&self) -> Option<Hit> {
+        self.hit
     }
 
     #[must_use]
@@ -90,9 +278,8 @@ impl Sim {
     }
 
     pub fn write(&mut self, c: ComponentKey, pin: PinId, value: bool) {
-        let mut changes = FnvHashMap::default();
-        changes.insert(self.pin(c, pin), value);
-        self.propagate_changes(&changes, true);
+        self.propagate_changes(&[(self.pin(c, pin), value)], true);
+        self.settle();
     }
 
     #[must_use]
@@ -100,15 +287,216 @@ impl Sim {
         self._read(0).unwrap_or(false)
     }
 
+    // components added until the matching exit_scope belong to a new instance of `module`
+    pub fn enter_scope(&mut self, name: &str, module: &str) -> ScopeId {
+        let id = self.scopes.len();
+        self.scopes.push(Scope::new(name, module, self.scope));
+        self.scope = Some(id);
+        id
+    }
+
+    pub fn exit_scope(&mut self) {
+        self.scope = self.scope.and_then(|s| self.scopes[s].parent());
+    }
+
+    pub fn set_label(&mut self, c: ComponentKey, label: &str) {
+        self.labels.insert(c, label.to_string());
+    }
+
+    // structural Verilog with one module per scope and stubs for behavioural components
+    #[must_use]
+    pub fn to_verilog(&self, top: &str) -> String {
+        export::verilog(self, top)
+    }
+
+    // Graphviz diagram of the components and the nets joining their pins,
+    // optionally grouped by scope and coloured by the current net values
+    #[must_use]
+    pub fn to_dot(&self, clusters: bool, values: bool) -> String {
+        export::dot(self, clusters, values)
+    }
+
+    // `predicate` is called without arguments and cannot use the Sim itself,
+    // a thrown exception stopping the run as if it had returned true
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen(js_name = "run_until")]
+    pub fn run_until_js(&mut self, predicate: &js_sys::Function) -> StopReason {
+        self.run_until(|_| {
+            predicate
+                .call0(&JsValue::NULL)
+                .map_or(true, |v| v.is_truthy())
+        })
+    }
+
     #[cfg(target_arch = "wasm32")]
     #[wasm_bindgen(js_name = "add_component")]
     pub fn add_ext_component(&mut self, component: JsComponent) -> ComponentKey {
-        self.add_component(component)
+        self.add_local_component(component)
     }
 }
 
 impl Sim {
-    fn pins(&self, c: ComponentKey) -> &[PinId] {
+    // Sim keeping its connections in `storage` instead of the default K2Tree
+    #[must_use]
+    pub fn with_storage<S: 'static + Storage>(storage: S) -> Self {
+        #[cfg(target_arch = "wasm32")]
+        console_error_panic_hook::set_once();
+
+        let mut s = Self {
+            graph: Graph::with_storage(storage),
+            components: Slab::new(),
+            values: Vec::new(),
+            pin_to_component: Vec::new(),
+            building: false,
+            pending: Vec::new(),
+            added: Vec::new(),
+            compiled: false,
+            program: None,
+            threads: 1,
+            pool: None,
+            partitions: None,
+            clock_driven: false,
+            ticks: 0,
+            max_ticks: 1 << 20,
+            watchpoints: Slab::new(),
+            hit: None,
+            scratch: Scratch::default(),
+            affected: Vec::new(),
+            scopes: Vec::new(),
+            scope: None,
+            component_scope: FnvHashMap::with_hasher(Default::default()),
+            labels: FnvHashMap::with_hasher(Default::default()),
+        };
+
+        // create pin 0 (global clk)
+        s.create_pin();
+
+        s
+    }
+
+    // Regions of the netlist sharing no nets besides the clock are updated on
+    // up to `threads` threads after each tick and write, with the same results
+    // as on one thread. Passes reaching a component added with
+    // add_local_component stay on this thread. So does everything once a
+    // component is seen driving the clock, which all regions would have to see
+    // in order; if that is first seen on threads, the regions that already ran
+    // missed the write until the Sim applies it after them, and a warning is
+    // printed. Drive the clock before threads are set to avoid it.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+        self.pool = if self.threads > 1 {
+            Pool::new(self.threads)
+        } else {
+            None
+        };
+    }
+
+    // ticks until `predicate` holds, checked before every tick
+    pub fn run_until(&mut self, mut predicate: impl FnMut(&Sim) -> bool) -> StopReason {
+        self.hit = None;
+
+        for _ in 0..self.max_ticks {
+            if predicate(self) {
+                return StopReason::Condition;
+            }
+            self.tick();
+            if self.hit.is_some() {
+                return StopReason::Breakpoint;
+            }
+        }
+
+        if predicate(self) {
+            StopReason::Condition
+        } else {
+            StopReason::Limit
+        }
+    }
+
+    // the component added as `c`, if it is a `T`
+    #[must_use]
+    pub fn component<T: 'static + Component>(&self, c: ComponentKey) -> Option<&T> {
+        self.components.get(c)?.component().as_any().downcast_ref()
+    }
+
+    // Changes made through it reach the pins at the component's next update.
+    pub fn component_mut<T: 'static + Component>(&mut self, c: ComponentKey) -> Option<&mut T> {
+        self.components
+            .get_mut(c)?
+            .component_mut()
+            .as_any_mut()
+            .downcast_mut()
+    }
+
+    /// # Panics
+    ///
+    /// Will panic if more than 32 pins are watched
+    pub fn add_watchpoint(
+        &mut self,
+        c: ComponentKey,
+        pins: &[PinId],
+        condition: Condition,
+    ) -> usize {
+        assert!(pins.len() <= 32, "Cannot watch more than 32 pins");
+
+        let value = self.read_bus(c, pins);
+        self.watchpoints
+            .insert(Watchpoint::new(c, pins, condition, value))
+    }
+
+    // approximate bytes used on the heap by the connection storage
+    #[must_use]
+    pub fn storage_size(&self) -> usize {
+        self.graph.storage_size()
+    }
+
+    #[must_use]
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    #[must_use]
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id]
+    }
+
+    #[must_use]
+    pub fn scope_of(&self, c: ComponentKey) -> Option<ScopeId> {
+        self.component_scope.get(&c).copied()
+    }
+
+    #[must_use]
+    pub fn label(&self, c: ComponentKey) -> Option<&str> {
+        self.labels.get(&c).map(String::as_str)
+    }
+
+    // bit parallel evaluation of the gate logic, see Parallel
+    #[must_use]
+    pub fn parallel(&self) -> Parallel<'_> {
+        Parallel::new(self, &self.values)
+    }
+
+    pub(crate) fn keys(&self) -> impl Iterator<Item = ComponentKey> + '_ {
+        self.components.iter().map(|(k, _)| k)
+    }
+
+    pub(crate) fn get(&self, c: ComponentKey) -> &dyn Component {
+        self.components[c].component()
+    }
+
+    // whether the component may be updated on other threads
+    pub(crate) fn is_send(&self, c: ComponentKey) -> bool {
+        matches!(self.components[c], Boxed::Send(_))
+    }
+
+    pub(crate) fn net(&self, pin: PinId) -> usize {
+        self.graph.find(pin)
+    }
+
+    pub(crate) fn net_value(&self, net: usize) -> Option<bool> {
+        self.values[net]
+    }
+
+    pub(crate) fn pins(&self, c: ComponentKey) -> &[PinId] {
         &self.components[c].pins()
     }
 
@@ -118,8 +506,8 @@ impl Sim {
 
     fn create_pin(&mut self) -> PinId {
         let pin = self.graph.add_node();
-        self.pin_to_value.insert(pin, self.values.len());
         self.values.push(None);
+        self.pin_to_component.push(None);
         pin
     }
 
@@ -130,93 +518,212 @@ impl Sim {
     }
 
     fn _connect(&mut self, c1: ComponentKey, pin1: PinId, c2: ComponentKey, pin2: PinId) {
-        self.graph.add_edge(self.pin(c1, pin1), self.pin(c2, pin2));
+        self.join(self.pin(c1, pin1), self.pin(c2, pin2));
+    }
+
+    // merges the nets of both pins, keeping the value of either one
+    fn join(&mut self, a: PinId, b: PinId) {
+        if self.building {
+            self.pending.push((a, b));
+        } else if let Some((root, absorbed)) = self.graph.add_edge(a, b) {
+            self.values[root] = self.values[root].or(self.values[absorbed]);
+            self.program = None;
+            self.partitions = None;
+        }
     }
 
     fn _read(&self, pin: PinId) -> Option<bool> {
-        self.values[self.pin_to_value[&pin]]
+        self.values[self.graph.find(pin)]
     }
 
-    fn _write(&mut self, pin: PinId, value: bool) {
-        self.values[self.pin_to_value[&pin]] = Some(value);
+    // runs `f` over an event driven pass on the whole Sim
+    fn pass<R>(&mut self, f: impl FnOnce(&mut Pass<'_, Whole<'_>>) -> R) -> R {
+        let netlist = Netlist {
+            graph: &self.graph,
+            pin_to_component: &self.pin_to_component,
+            clock: self.graph.find(0),
+            compiled: self.program.is_some(),
+        };
+        let mut pass = Pass::new(
+            netlist,
+            self.program.as_mut(),
+            Whole {
+                values: &mut self.values,
+                components: &mut self.components,
+            },
+            std::mem::take(&mut self.scratch),
+        );
+
+        let r = f(&mut pass);
+        self.scratch = pass.finish();
+        self.clock_driven |= self.scratch.clock.take().is_some();
+        self.touch();
+        r
+    }
+
+    // tells the program about the nets written by the last pass
+    fn touch(&mut self) {
+        if let Some(program) = &mut self.program {
+            for net in self.scratch.touched.drain(..) {
+                program.touch(net);
+            }
+        }
+    }
+
+    fn update_component(&mut self, key: ComponentKey) {
+        self.pass(|p| p.update_component(key));
     }
 
-    fn read_pins(&self, pins: &[PinId]) -> Vec<bool> {
+    fn read_bus(&self, c: ComponentKey, pins: &[PinId]) -> u32 {
         pins.iter()
-            .map(|&p| self._read(p).unwrap_or(false))
-            .collect()
+            .rev()
+            .fold(0, |acc, &p| (acc << 1) | u32::from(self.read(c, p)))
     }
 
-    fn update_connections(&mut self) {
-        let pin_values = self
-            .graph
-            .nodes()
-            .iter()
-            .filter_map(|n| self._read(*n).map(|v| (*n, v)))
-            .collect::<Vec<_>>();
+    // the first watchpoint to fire becomes the hit, all of them taking the
+    // current value of their pins
+    fn check_watchpoints(&mut self) {
+        let mut watchpoints = std::mem::take(&mut self.watchpoints);
 
-        let pins = self.graph.components();
-        let components = pins.values().max().unwrap_or(&0) + 1;
-        self.values = vec![None; components];
-        self.pin_to_value = pins;
+        for (id, w) in &mut watchpoints {
+            let value = self.read_bus(w.component, &w.pins);
+            if w.update(value) && self.hit.is_none() {
+                self.hit = Some(Hit::new(id, self.ticks));
+            }
+        }
 
-        pin_values.iter().for_each(|(n, v)| self._write(*n, *v));
+        self.watchpoints = watchpoints;
     }
 
-    fn update_component(&mut self, key: ComponentKey) {
-        let input = self.read_pins(self.pins(key));
-        let c = &mut self.components[key];
-        c.set_input(input);
+    // runs the compiled program until the behavioural components it drives
+    // stop changing its inputs
+    fn settle(&mut self) {
+        if !self.compiled || self.building {
+            return;
+        }
 
-        let changes = c.update();
-        self.propagate_changes(&changes, false);
+        let mut program = match self.program.take() {
+            Some(p) => p,
+            None => Program::new(self),
+        };
+
+        let mut changed = Vec::new();
+        loop {
+            changed.clear();
+            program.run(
+                &mut Whole {
+                    values: &mut self.values,
+                    components: &mut self.components,
+                },
+                &mut changed,
+            );
+            if changed.is_empty() {
+                break;
+            }
+            self.scratch.writes += changed.len();
+
+            let mut affected = changed
+                .iter()
+                .flat_map(|&net| self.graph.members(net))
+                .filter_map(|&p| self.pin_to_component[p])
+                .filter(|&k| !program.is_compiled(k))
+                .collect::<Vec<_>>();
+            affected.sort_unstable();
+            affected.dedup();
+
+            self.program = Some(program);
+            for k in affected {
+                self.update_component(k);
+            }
+            program = self.program.take().unwrap();
+        }
+
+        self.program = Some(program);
     }
 
-    fn connected_to(&self, pin: PinId) -> impl Iterator<Item = PinId> + '_ {
-        let value = self.pin_to_value[&pin];
+    // Top level propagation. With threads, the components affected by the
+    // changes are updated region by region in parallel, each region seeing them
+    // in the same order as the single threaded engine would.
+    fn propagate_changes(&mut self, changes: &[(PinId, bool)], update_self: bool) {
+        let mut affected = std::mem::take(&mut self.affected);
+        self.pass(|p| p.apply(changes, update_self, &mut affected));
+
+        if self.pool.is_some() && !self.clock_driven && affected.len() > 1 {
+            if self.partitions.is_none() {
+                self.partitions = Some(Partitions::new(self, self.values.len()));
+            }
+
+            let netlist = Netlist {
+                graph: &self.graph,
+                pin_to_component: &self.pin_to_component,
+                clock: self.graph.find(0),
+                compiled: self.program.is_some(),
+            };
+            let scratch = self.partitions.as_ref().unwrap().run(
+                self.pool.as_ref().unwrap(),
+                self.threads,
+                netlist,
+                &mut self.values,
+                &mut self.components,
+                &affected,
+            );
+
+            if let Some(mut scratch) = scratch {
+                let clock = scratch.clock.take();
+                self.scratch.merge(scratch);
+                self.touch();
+                self.affected = affected;
+
+                if let Some(value) = clock {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    eprintln!(
+                        "warning: a component drove the clock while regions ran on threads; \
+                         they all run on one thread from now on"
+                    );
+                    self.clock_driven = true;
+                    self.propagate_changes(&[(0, value)], false);
+                }
+                return;
+            }
+        }
 
-        self.pin_to_value
-            .iter()
-            .filter_map(move |(p, &v)| if v == value { Some(*p) } else { None })
+        self.pass(|p| affected.iter().for_each(|&k| p.update_component(k)));
+        self.affected = affected;
     }
 
-    fn propagate_changes(&mut self, changes: &FnvHashMap<PinId, bool>, update_self: bool) {
-        changes
-            .iter()
-            .filter_map(|(p, v)| {
-                if self._read(*p) == Some(*v) {
-                    None
-                } else {
-                    self._write(*p, *v);
-                    Some(*p)
-                }
-            })
-            .collect::<Vec<_>>() // changed pins
-            .iter()
-            .flat_map(|pin| {
-                self.connected_to(*pin)
-                    .filter(move |p| update_self || *p != *pin) // the updated pin should not trigger an update on itself
-                    .map(|p| self.pin_to_component[&p])
-                    .filter(|k| *pin == 0 || update_self || *k != self.pin_to_component[pin]) // prevent self updates
-                    .collect::<Vec<_>>()
-            })
-            .collect::<FnvHashSet<_>>() // affected components
-            .iter()
-            .for_each(|k| self.update_component(*k))
+    pub fn add_component<T: 'static + Component + Send>(&mut self, component: T) -> ComponentKey {
+        let pins = self.create_pins(component.pin_count());
+        self.insert(Boxed::Send(Box::new(Wrapper::new(pins, component))))
     }
 
-    pub fn add_component<T: 'static + Component>(&mut self, component: T) -> ComponentKey {
+    // add_component for components that cannot leave this thread, such as
+    // ones shared through an Rc<RefCell<T>>, the passes reaching them never
+    // running on threads
+    pub fn add_local_component<T: 'static + Component>(&mut self, component: T) -> ComponentKey {
         let pins = self.create_pins(component.pin_count());
+        self.insert(Boxed::Local(Box::new(Wrapper::new(pins, component))))
+    }
 
-        let k = self
-            .components
-            .insert(Wrapper::new(pins.clone(), component));
+    fn insert(&mut self, component: Boxed) -> ComponentKey {
+        let pins = component.pins().to_vec();
+        let k = self.components.insert(component);
 
-        pins.iter().for_each(|p| {
-            self.pin_to_component.insert(*p, k);
+        pins.iter().for_each(|&p| {
+            self.pin_to_component[p] = Some(k);
         });
 
-        self.update_component(k);
+        if let Some(s) = self.scope {
+            self.component_scope.insert(k, s);
+        }
+
+        self.program = None;
+        self.partitions = None;
+        if self.building {
+            self.added.push(k);
+        } else {
+            self.update_component(k);
+            self.settle();
+        }
 
         k
     }
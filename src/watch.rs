@@ -0,0 +1,87 @@
+use crate::{ComponentKey, PinId};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+// What makes a watchpoint fire, over the value of its pins read as a bus
+// (bit n => pin n). Each one fires when it becomes true, not on every tick
+// it holds.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Condition {
+    Equals(u32),
+    Change,
+    // from zero to anything else, a rising edge for a single pin
+    Rising,
+    Falling,
+    // inclusive
+    InRange(u32, u32),
+}
+
+impl Condition {
+    fn holds(self, old: u32, new: u32) -> bool {
+        match self {
+            Condition::Equals(v) => new == v && old != v,
+            Condition::Change => new != old,
+            Condition::Rising => old == 0 && new != 0,
+            Condition::Falling => old != 0 && new == 0,
+            Condition::InRange(lo, hi) => (lo..=hi).contains(&new) && !(lo..=hi).contains(&old),
+        }
+    }
+}
+
+pub(crate) struct Watchpoint {
+    pub(crate) component: ComponentKey,
+    pub(crate) pins: Vec<PinId>,
+    condition: Condition,
+    value: u32,
+}
+
+impl Watchpoint {
+    // `value` being what the pins read now
+    pub(crate) fn new(
+        component: ComponentKey,
+        pins: &[PinId],
+        condition: Condition,
+        value: u32,
+    ) -> Self {
+        Self {
+            component,
+            pins: pins.to_vec(),
+            condition,
+            value,
+        }
+    }
+
+    // takes the current value of the pins, returning whether that fires it
+    pub(crate) fn update(&mut self, value: u32) -> bool {
+        let old = std::mem::replace(&mut self.value, value);
+        self.condition.holds(old, value)
+    }
+}
+
+// the first watchpoint that fired during a run, and the tick it fired on
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Hit {
+    watchpoint: usize,
+    tick: usize,
+}
+
+impl Hit {
+    pub(crate) fn new(watchpoint: usize, tick: usize) -> Self {
+        Self { watchpoint, tick }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Hit {
+    #[must_use]
+    pub fn watchpoint(&self) -> usize {
+        self.watchpoint
+    }
+
+    #[must_use]
+    pub fn tick(&self) -> usize {
+        self.tick
+    }
+}
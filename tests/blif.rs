@@ -0,0 +1,221 @@
+// BLIF imported and checked against the same logic in Rust: covers, latches
+// and models instantiated with `.subckt`
+
+use sim_rs::{
+    import::{blif, Ports},
+    Sim,
+};
+
+const GATES: &str = "
+.model gates
+.inputs a b
+.outputs and or xor one zero
+# the on-set of each
+.names a b and
+11 1
+.names a b or
+1- 1
+-1 1
+# or the off-set
+.names a b xor
+00 0
+11 0
+.names one
+1
+.names zero
+.end
+";
+
+const LATCHES: &str = "
+.model latches
+.inputs d clk
+.outputs q p
+.latch d q re clk 1
+.latch d p fe clk 0
+.end
+";
+
+const ADDER: &str = "
+.model add2
+.inputs a0 a1 b0 b1
+.outputs s0 s1 cout
+.subckt full a=a0 b=b0 cin=zero sum=s0 \\
+    cout=c0
+.subckt full a=a1 b=b1 cin=c0 sum=s1 cout=cout
+.names zero
+.end
+
+.model full
+.inputs a b cin
+.outputs sum cout
+.subckt half x=a y=b s=s1 c=c1
+.subckt half x=s1 y=cin s=sum c=c2
+.names c1 c2 cout
+1- 1
+-1 1
+.end
+
+.model half
+.inputs x y
+.outputs s c
+.names x y s
+01 1
+10 1
+.names x y c
+11 1
+.end
+";
+
+fn load(source: &str, top: Option<&str>) -> (Sim, Ports) {
+    let mut s = Sim::new();
+    let ports = blif::load(&mut s, source, top).unwrap();
+    (s, ports)
+}
+
+fn set(s: &mut Sim, ports: &Ports, name: &str, value: u32) {
+    s.write(ports[name], 1, value != 0);
+}
+
+fn get(s: &Sim, ports: &Ports, name: &str) -> u32 {
+    u32::from(s.read(ports[name], 1))
+}
+
+#[test]
+fn covers() {
+    let (mut s, ports) = load(GATES, None);
+
+    for a in 0..2 {
+        for b in 0..2 {
+            set(&mut s, &ports, "a", a);
+            set(&mut s, &ports, "b", b);
+
+            let outputs = ["and", "or", "xor", "one", "zero"].map(|o| get(&s, &ports, o));
+            assert_eq!(outputs, [a & b, a | b, a ^ b, 1, 0], "a {}, b {}", a, b);
+        }
+    }
+}
+
+#[test]
+fn latches() {
+    let (mut s, ports) = load(LATCHES, None);
+    // driven by the clock of the Sim
+    assert!(!ports.contains_key("clk"));
+
+    assert_eq!((get(&s, &ports, "q"), get(&s, &ports, "p")), (1, 0));
+    set(&mut s, &ports, "d", 0);
+    assert_eq!(get(&s, &ports, "q"), 1);
+
+    // each on its own edge
+    s.tick();
+    assert_eq!((get(&s, &ports, "q"), get(&s, &ports, "p")), (0, 0));
+    set(&mut s, &ports, "d", 1);
+    s.tick();
+    assert_eq!((get(&s, &ports, "q"), get(&s, &ports, "p")), (0, 1));
+    s.tick();
+    assert_eq!((get(&s, &ports, "q"), get(&s, &ports, "p")), (1, 1));
+    set(&mut s, &ports, "d", 0);
+    s.tick();
+    assert_eq!((get(&s, &ports, "q"), get(&s, &ports, "p")), (1, 0));
+}
+
+#[test]
+fn subckt() {
+    let (mut s, ports) = load(ADDER, None);
+
+    for a in 0..4 {
+        for b in 0..4 {
+            set(&mut s, &ports, "a0", a & 1);
+            set(&mut s, &ports, "a1", a >> 1);
+            set(&mut s, &ports, "b0", b & 1);
+            set(&mut s, &ports, "b1", b >> 1);
+
+            let sum =
+                get(&s, &ports, "s0") | get(&s, &ports, "s1") << 1 | get(&s, &ports, "cout") << 2;
+            assert_eq!(sum, a + b, "{} + {}", a, b);
+        }
+    }
+
+    // a model of the hierarchy can be the top one too
+    let (mut s, ports) = load(ADDER, Some("half"));
+    set(&mut s, &ports, "x", 1);
+    set(&mut s, &ports, "y", 1);
+    assert_eq!((get(&s, &ports, "s"), get(&s, &ports, "c")), (0, 1));
+}
+
+#[test]
+fn errors() {
+    let wide = (0..17).map(|i| format!("i{i} ")).collect::<String>();
+    let wide = format!(
+        ".model m\n.inputs {}\n.outputs y\n.names {}y\n{} 1\n.end\n",
+        wide,
+        wide,
+        "1".repeat(17)
+    );
+
+    let cases: &[(&str, usize, &str)] = &[
+        (
+            ".model m\n.gate and2 a=x\n.end\n",
+            2,
+            "unsupported command `.gate`",
+        ),
+        (".model m\n.names a y\n11 1\n", 3, "malformed cover"),
+        (".model m\n.names a y\n2 1\n", 3, "invalid cube `2`"),
+        (".model m\n.names a y\n1 x\n", 3, "invalid output `x`"),
+        (
+            ".model m\n.names a y\n1 1\n0 0\n",
+            4,
+            "cover mixes on-set and off-set cubes",
+        ),
+        (".model m\n.end\n.model m\n.end\n", 3, "duplicate model `m`"),
+        (".model m\n.latch a\n", 2, "malformed `.latch`"),
+        (
+            ".model m\n.latch a b xx c\n",
+            2,
+            "unsupported latch type `xx`",
+        ),
+        (".model m\n.latch a b 4\n", 2, "invalid initial value `4`"),
+        (".model m\n.subckt n a=b\n.end\n", 2, "unknown model `n`"),
+        (
+            ".model m\n.subckt m\n.end\n",
+            2,
+            "model `m` instantiates itself",
+        ),
+        (
+            ".model m\n.subckt n x=y\n.end\n.model n\n.inputs a\n.end\n",
+            2,
+            "model `n` has no port `x`",
+        ),
+        (
+            ".model m\n.subckt n a\n.end\n",
+            2,
+            "malformed connection `a`",
+        ),
+        (
+            &wide,
+            4,
+            "covers over more than 16 inputs are not supported",
+        ),
+    ];
+
+    for &(source, line, message) in cases {
+        let error = blif::load(&mut Sim::new(), source, None).unwrap_err();
+        assert_eq!(
+            (error.line(), error.message()),
+            (line, message),
+            "{}",
+            source
+        );
+    }
+
+    // at most 16 is fine
+    let narrow = wide
+        .replace("i16 ", "")
+        .replace(&"1".repeat(17), &"1".repeat(16));
+    assert!(blif::load(&mut Sim::new(), &narrow, None).is_ok());
+    assert_eq!(
+        blif::load(&mut Sim::new(), ".model m\n", Some("n"))
+            .unwrap_err()
+            .message(),
+        "unknown model `n`"
+    );
+}
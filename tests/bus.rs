@@ -0,0 +1,143 @@
+// Rams mapped into the address space of an Input standing in for the bus
+// master, each word a byte apart as a Ram is addressed by the offset it gets
+
+use sim_rs::{
+    components::{bus::SystemBus, mem::Ram, port::Input},
+    Sim,
+};
+
+const MISS: usize = 66;
+const SELECT: usize = 66;
+
+fn set(s: &mut Sim, c: usize, first: usize, width: usize, value: u32) {
+    for bit in 0..width {
+        s.write(c, first + bit, value >> bit & 1 != 0);
+    }
+}
+
+fn get(s: &Sim, c: usize, first: usize, width: usize) -> u32 {
+    (0..width).fold(0, |acc, bit| acc | u32::from(s.read(c, first + bit)) << bit)
+}
+
+// the address set while reading, as the bus drives the data lines of the
+// master until write goes high
+fn write(s: &mut Sim, master: usize, addr: u32, value: u32) {
+    set(s, master, 1, 32, addr);
+    s.write(master, 65, true);
+    set(s, master, 33, 32, value);
+    s.write(master, 65, false);
+}
+
+fn read(s: &mut Sim, master: usize, addr: u32) -> u32 {
+    set(s, master, 1, 32, addr);
+    get(s, master, 33, 32)
+}
+
+// the Sim, the bus, its master and a Ram for each of `map`, as (base, size),
+// filled with its number times 0x100 plus the offset
+fn system(map: &[(u32, u32)]) -> (Sim, usize, usize, Vec<usize>) {
+    let mut s = Sim::new();
+    s.begin_build();
+
+    let mut bus = SystemBus::new();
+    for &(base, size) in map {
+        bus.map(base, size);
+    }
+    let b = s.add_component(bus);
+    let m = s.add_component(Input::new(65));
+    for pin in 1..=65 {
+        s.connect(m, pin, b, pin);
+    }
+
+    let mut rams = Vec::new();
+    for (slave, &(_, size)) in map.iter().enumerate() {
+        let data: Vec<_> = (0..size).map(|i| 0x100 * slave as u32 + i).collect();
+        let r = s.add_component(Ram::new(&data));
+        for pin in 1..=65 {
+            s.connect(r, pin, b, SystemBus::slave_pin(slave, pin));
+        }
+        rams.push(r);
+    }
+
+    s.finish_build();
+    (s, b, m, rams)
+}
+
+fn selected(s: &Sim, b: usize, slaves: usize) -> Vec<bool> {
+    (0..slaves)
+        .map(|slave| s.read(b, SystemBus::slave_pin(slave, SELECT)))
+        .collect()
+}
+
+#[test]
+fn decode() {
+    let mut bus = SystemBus::new();
+    assert_eq!(bus.map(0x100, 0x10), 0);
+    assert_eq!(bus.map(0, 0x200), 1);
+    assert_eq!(bus.map(0xffff_fff0, 0x10), 2);
+
+    let cases = [
+        (0, Some(1)),
+        (0xff, Some(1)),
+        (0x100, Some(0)),
+        (0x10f, Some(0)),
+        (0x110, Some(1)),
+        (0x1ff, Some(1)),
+        (0x200, None),
+        (0xffff_ffef, None),
+        (0xffff_fff0, Some(2)),
+        (0xffff_ffff, Some(2)),
+    ];
+    for &(addr, slave) in &cases {
+        assert_eq!(bus.decode(addr), slave, "{:#x}", addr);
+    }
+}
+
+#[test]
+fn precedence() {
+    let (mut s, b, m, rams) = system(&[(0x40, 0x10), (0, 0x80)]);
+
+    // the first slave shadows the middle of the second
+    assert_eq!(read(&mut s, m, 0x3f), 0x13f);
+    assert_eq!(selected(&s, b, 2), [false, true]);
+    assert_eq!(read(&mut s, m, 0x44), 0x004);
+    assert_eq!(selected(&s, b, 2), [true, false]);
+    assert_eq!(read(&mut s, m, 0x50), 0x150);
+    assert!(!s.read(b, MISS));
+
+    write(&mut s, m, 0x44, 0xaaaa);
+    write(&mut s, m, 0x54, 0xbbbb);
+    let ram = |s: &Sim, r: usize, word| s.component::<Ram>(r).unwrap().read(word);
+    assert_eq!(
+        (ram(&s, rams[0], 4), ram(&s, rams[1], 0x44)),
+        (0xaaaa, 0x144)
+    );
+    assert_eq!((ram(&s, rams[0], 0), ram(&s, rams[1], 0x54)), (0, 0xbbbb));
+    assert_eq!(read(&mut s, m, 0x44), 0xaaaa);
+    assert_eq!(read(&mut s, m, 0x54), 0xbbbb);
+}
+
+#[test]
+fn miss() {
+    let (mut s, b, m, rams) = system(&[(0x100, 0x10), (0x200, 0x10)]);
+
+    assert_eq!(read(&mut s, m, 0x204), 0x104);
+    assert!(!s.read(b, MISS));
+    // reading 0 rather than what was last on the data lines
+    assert_eq!(read(&mut s, m, 0x110), 0);
+    assert!(s.read(b, MISS));
+    assert_eq!(selected(&s, b, 2), [false, false]);
+
+    // and writing nothing
+    write(&mut s, m, 0x1f0, 0xcccc);
+    assert!(s.read(b, MISS));
+    for (slave, &r) in rams.iter().enumerate() {
+        for word in 0..0x10 {
+            let value = s.component::<Ram>(r).unwrap().read(word);
+            assert_eq!(value, 0x100 * slave as u32 + word as u32);
+        }
+    }
+
+    assert_eq!(read(&mut s, m, 0x10f), 0xf);
+    assert!(!s.read(b, MISS));
+}
@@ -0,0 +1,262 @@
+// Random registers fed back through gate logic, run event driven and compiled
+// side by side and compared after every tick
+
+use sim_rs::{
+    components::{
+        logic::{And, Buffer, Nand, Nor, Not, Or, Xnor, Xor},
+        mem::{Register, Trigger},
+        port::Input,
+    },
+    Sim,
+};
+
+const REGISTERS: usize = 12;
+const GATES: usize = 60;
+const TICKS: usize = 200;
+const INPUTS: usize = 8;
+
+// xorshift, so both Sims get the same netlist
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+// The Sim and every (component, pin) to compare. Every gate has a single
+// input that can change, the other tied high or low, so no gate glitches
+// while the event driven engine updates them one by one: a register clocked
+// then could sample the glitch, where the program settles all gates at once.
+fn netlist(seed: u64) -> (Sim, Vec<(usize, usize)>) {
+    let mut rng = Rng(seed);
+    let mut s = Sim::new();
+    s.begin_build();
+
+    let high = s.add_component(Not);
+    let low = s.add_component(Not);
+    s.connect(low, 1, high, 2);
+    let constants = [(high, 2), (low, 2)];
+
+    let registers = (0..REGISTERS)
+        .map(|i| {
+            let trigger = if i % 3 == 0 {
+                Trigger::Falling
+            } else {
+                Trigger::Rising
+            };
+            let r = s.add_component(Register::new(1, trigger, (i % 2) as u32));
+            s.connect_to_clk(r, 3);
+            s.connect(r, 4, high, 2);
+            r
+        })
+        .collect::<Vec<_>>();
+
+    let mut outputs = registers.iter().map(|&r| (r, 2)).collect::<Vec<_>>();
+    for i in 0..GATES {
+        let g = match i % 4 {
+            0 => s.add_component(And),
+            1 => s.add_component(Or),
+            2 => s.add_component(Xor),
+            _ => s.add_component(Not),
+        };
+        let (src, out) = outputs[rng.below(outputs.len())];
+        s.connect(g, 1, src, out);
+
+        if i % 4 == 3 {
+            outputs.push((g, 2));
+        } else {
+            let (src, out) = constants[rng.below(2)];
+            s.connect(g, 2, src, out);
+            outputs.push((g, 3));
+        }
+    }
+
+    // registers read gates and each other, so a register clocked after
+    // another sees its new value wherever the event driven engine would
+    for &r in &registers {
+        let (src, out) = outputs[rng.below(outputs.len())];
+        s.connect(r, 1, src, out);
+    }
+
+    s.finish_build();
+    (s, outputs)
+}
+
+#[test]
+fn compiled_matches_event_driven() {
+    for seed in 1..=20 {
+        let (mut event, outputs) = netlist(seed);
+        let (mut compiled, _) = netlist(seed);
+        compiled.set_compiled(true);
+
+        for tick in 0..TICKS {
+            for &(c, pin) in &outputs {
+                assert_eq!(
+                    compiled.read(c, pin),
+                    event.read(c, pin),
+                    "seed {seed}, tick {tick}: pin {pin} of component {c}"
+                );
+            }
+
+            event.tick();
+            compiled.tick();
+        }
+    }
+}
+
+// Gates of every kind reading any earlier net on both inputs, so a change
+// reaches some of them down paths of different lengths, fed from an Input
+// written at random.
+// The registers sample that logic on the clock but feed nothing, so none is
+// updated while the event driven engine is still propagating a change, and
+// both engines agree. Returns the Input and every (component, pin) to compare.
+fn reconvergent(seed: u64) -> (Sim, usize, Vec<(usize, usize)>) {
+    let mut rng = Rng(seed);
+    let mut s = Sim::new();
+    s.begin_build();
+
+    let input = s.add_component(Input::new(INPUTS));
+    let mut nets = (1..=INPUTS).map(|pin| (input, pin)).collect::<Vec<_>>();
+    for i in 0..GATES {
+        let g = match i % 8 {
+            0 => s.add_component(And),
+            1 => s.add_component(Or),
+            2 => s.add_component(Xor),
+            3 => s.add_component(Nand),
+            4 => s.add_component(Nor),
+            5 => s.add_component(Xnor),
+            6 => s.add_component(Not),
+            _ => s.add_component(Buffer),
+        };
+        let (src, out) = nets[rng.below(nets.len())];
+        s.connect(g, 1, src, out);
+
+        if i % 8 >= 6 {
+            nets.push((g, 2));
+        } else {
+            let (src, out) = nets[rng.below(nets.len())];
+            s.connect(g, 2, src, out);
+            nets.push((g, 3));
+        }
+    }
+
+    let high = s.add_component(Not);
+    for i in 0..REGISTERS {
+        let trigger = if i % 3 == 0 {
+            Trigger::Falling
+        } else {
+            Trigger::Rising
+        };
+        let r = s.add_component(Register::new(1, trigger, 0));
+        let (src, out) = nets[rng.below(nets.len())];
+        s.connect(r, 1, src, out);
+        s.connect_to_clk(r, 3);
+        s.connect(r, 4, high, 2);
+        nets.push((r, 2));
+    }
+
+    s.finish_build();
+    (s, input, nets)
+}
+
+#[test]
+fn reconvergent_fanout_matches_event_driven() {
+    for seed in 1..=20 {
+        let (mut event, input, nets) = reconvergent(seed);
+        let (mut compiled, _, _) = reconvergent(seed);
+        compiled.set_compiled(true);
+        let mut rng = Rng(seed);
+
+        for tick in 0..TICKS {
+            let value = rng.below(1 << INPUTS);
+            for pin in 1..=INPUTS {
+                event.write(input, pin, value >> (pin - 1) & 1 != 0);
+                compiled.write(input, pin, value >> (pin - 1) & 1 != 0);
+            }
+
+            for &(c, pin) in &nets {
+                assert_eq!(
+                    compiled.read(c, pin),
+                    event.read(c, pin),
+                    "seed {seed}, tick {tick}: pin {pin} of component {c}"
+                );
+            }
+
+            event.tick();
+            compiled.tick();
+        }
+    }
+}
+
+// 64 random patterns of the Input evaluated at once, against writing each to
+// the Sim in turn. Without a tick the registers keep their values in both.
+#[test]
+fn parallel_matches_scalar() {
+    for seed in 1..=20 {
+        let (mut s, input, nets) = reconvergent(seed);
+        let mut rng = Rng(seed);
+        let patterns = (0..64).map(|_| rng.below(1 << INPUTS)).collect::<Vec<_>>();
+
+        let mut parallel = s.parallel();
+        for pin in 1..=INPUTS {
+            let bits = patterns.iter().enumerate().fold(0, |acc, (i, &p)| {
+                acc | u64::from(p >> (pin - 1) & 1 != 0) << i
+            });
+            parallel.write(input, pin, bits);
+        }
+        parallel.run();
+        let values = nets
+            .iter()
+            .map(|&(c, pin)| parallel.read(c, pin))
+            .collect::<Vec<_>>();
+
+        for (i, &value) in patterns.iter().enumerate() {
+            for pin in 1..=INPUTS {
+                s.write(input, pin, value >> (pin - 1) & 1 != 0);
+            }
+
+            for (&(c, pin), &bits) in nets.iter().zip(&values) {
+                assert_eq!(
+                    s.read(c, pin),
+                    bits >> i & 1 != 0,
+                    "seed {seed}, pattern {i}: pin {pin} of component {c}"
+                );
+            }
+        }
+    }
+}
+
+// x & !x pulses high for an instant when x rises and the event driven engine
+// updates the And before the Not, clocking a register the compiled program,
+// settling the gates first, never clocks
+#[test]
+fn compiled_settles_glitches_away() {
+    for &compiled in &[false, true] {
+        let mut s = Sim::new();
+        s.begin_build();
+
+        let x = s.add_component(Input::new(1));
+        let and = s.add_component(And);
+        let not = s.add_component(Not);
+        let high = s.add_component(Not);
+        let r = s.add_component(Register::new(1, Trigger::Rising, 0));
+
+        s.connect(and, 1, x, 1);
+        s.connect(not, 1, x, 1);
+        s.connect(and, 2, not, 2);
+        s.connect(r, 1, high, 2);
+        s.connect(r, 3, and, 3);
+        s.connect(r, 4, high, 2);
+
+        s.finish_build();
+        s.set_compiled(compiled);
+
+        s.write(x, 1, true);
+        assert!(!s.read(and, 3));
+        assert_eq!(s.read(r, 2), !compiled, "compiled: {}", compiled);
+    }
+}
@@ -0,0 +1,95 @@
+// Graphviz output of a small netlist: an And and a Not in a scope between an
+// Input and an Output, the Not also feeding a clocked Register
+
+use sim_rs::{
+    components::{
+        logic::{And, Not},
+        mem::{Register, Trigger},
+        port::{Input, Output},
+    },
+    Sim,
+};
+
+fn netlist() -> Sim {
+    let mut s = Sim::new();
+    s.begin_build();
+
+    let a = s.add_component(Input::new(2));
+    s.set_label(a, "a|b");
+    s.enter_scope("h0", "gate");
+    let and = s.add_component(And);
+    let not = s.add_component(Not);
+    s.exit_scope();
+    let y = s.add_component(Output::new(2));
+    let r = s.add_component(Register::new(1, Trigger::Rising, 0));
+
+    s.connect(a, 1, and, 1);
+    s.connect(a, 2, and, 2);
+    s.connect(and, 3, not, 1);
+    s.connect(and, 3, y, 1);
+    s.connect(not, 2, y, 2);
+    s.connect(not, 2, r, 1);
+    s.connect_to_clk(r, 3);
+
+    s.finish_build();
+    s.write(a, 1, true);
+    s
+}
+
+#[test]
+fn flat() {
+    let expected = r#"graph sim {
+  rankdir=LR;
+  node [shape=record];
+  c0 [label="{a\|b\nInput|{<p1> 0|<p2> 1}}"];
+  c1 [label="{And|{<p1> a|<p2> b|<p3> y}}"];
+  c2 [label="{Not|{<p1> a|<p2> y}}"];
+  c3 [label="{Output|{<p1> 0|<p2> 1}}"];
+  c4 [label="{Register|{<p1> d[0]|<p2> q[0]|<p3> clk|<p4> enable|<p5> clear}}"];
+  clk [shape=circle];
+  clk -- c4:p3;
+  c0:p1 -- c1:p1;
+  c0:p2 -- c1:p2;
+  n5 [shape=point];
+  c1:p3 -- n5;
+  c2:p1 -- n5;
+  c3:p1 -- n5;
+  n7 [shape=point];
+  c2:p2 -- n7;
+  c3:p2 -- n7;
+  c4:p1 -- n7;
+}
+"#;
+    assert_eq!(netlist().to_dot(false, false), expected);
+}
+
+#[test]
+fn clusters_and_values() {
+    let expected = r#"graph sim {
+  rankdir=LR;
+  node [shape=record];
+  subgraph cluster_0 {
+    label="h0 : gate";
+    c1 [label="{And|{<p1> a|<p2> b|<p3> y}}"];
+    c2 [label="{Not|{<p1> a|<p2> y}}"];
+  }
+  c0 [label="{a\|b\nInput|{<p1> 0|<p2> 1}}"];
+  c3 [label="{Output|{<p1> 0|<p2> 1}}"];
+  c4 [label="{Register|{<p1> d[0]|<p2> q[0]|<p3> clk|<p4> enable|<p5> clear}}"];
+  clk [shape=circle];
+  clk -- c4:p3 [color=gray, style=dashed];
+  c0:p1 -- c1:p1 [color=green3, penwidth=2];
+  c0:p2 -- c1:p2 [color=gray, style=dashed];
+  n5 [shape=point];
+  c1:p3 -- n5 [color=black];
+  c2:p1 -- n5 [color=black];
+  c3:p1 -- n5 [color=black];
+  n7 [shape=point];
+  c2:p2 -- n7 [color=green3, penwidth=2];
+  c3:p2 -- n7 [color=green3, penwidth=2];
+  c4:p1 -- n7 [color=green3, penwidth=2];
+}
+"#;
+    // high, low and never driven nets, the clock not having ticked yet
+    assert_eq!(netlist().to_dot(true, true), expected);
+}
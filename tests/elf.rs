@@ -0,0 +1,362 @@
+// Executables put together byte by byte: a header, its program headers and
+// segments, and a symbol table with its strings when there are symbols
+
+use sim_rs::{
+    components::{
+        cpu::{Mips, Rv32},
+        mem::Ram,
+    },
+    elf::Elf,
+    Component, Sim,
+};
+
+const ET_REL: u16 = 1;
+const ET_EXEC: u16 = 2;
+const EM_MIPS: u16 = 8;
+const EM_RISCV: u16 = 243;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_SECTION: u8 = 3;
+
+struct Builder {
+    big_endian: bool,
+    kind: u16,
+    machine: u16,
+    entry: u32,
+    // address, contents and size in memory
+    segments: Vec<(u32, Vec<u8>, u32)>,
+    // name, address, size and type
+    symbols: Vec<(&'static str, u32, u32, u8)>,
+}
+
+impl Builder {
+    fn new(machine: u16) -> Self {
+        Self {
+            big_endian: machine == EM_MIPS,
+            kind: ET_EXEC,
+            machine,
+            entry: 0x1000,
+            segments: Vec::new(),
+            symbols: Vec::new(),
+        }
+    }
+
+    fn segment(mut self, addr: u32, data: &[u8], size: u32) -> Self {
+        self.segments.push((addr, data.to_vec(), size));
+        self
+    }
+
+    fn symbol(mut self, name: &'static str, addr: u32, size: u32, kind: u8) -> Self {
+        self.symbols.push((name, addr, size, kind));
+        self
+    }
+
+    fn u16(&self, out: &mut Vec<u8>, v: u16) {
+        out.extend(if self.big_endian {
+            v.to_be_bytes()
+        } else {
+            v.to_le_bytes()
+        });
+    }
+
+    fn u32(&self, out: &mut Vec<u8>, v: u32) {
+        out.extend(if self.big_endian {
+            v.to_be_bytes()
+        } else {
+            v.to_le_bytes()
+        });
+    }
+
+    fn build(&self) -> Vec<u8> {
+        let phoff = 52;
+        let mut data_offset = phoff + 32 * self.segments.len();
+        let mut contents: Vec<u8> = Vec::new();
+        let mut out = b"\x7fELF\x01".to_vec();
+        out.extend([if self.big_endian { 2 } else { 1 }, 1]);
+        out.resize(16, 0);
+
+        self.u16(&mut out, self.kind);
+        self.u16(&mut out, self.machine);
+        self.u32(&mut out, 1);
+        self.u32(&mut out, self.entry);
+        self.u32(&mut out, phoff as u32);
+        // the section headers, filled in below
+        let shoff = out.len();
+        self.u32(&mut out, 0);
+        self.u32(&mut out, 0);
+        for v in [52, 32, self.segments.len() as u16, 40, 0, 0] {
+            self.u16(&mut out, v);
+        }
+
+        for (addr, data, size) in &self.segments {
+            for v in [
+                1,
+                data_offset as u32,
+                *addr,
+                *addr,
+                data.len() as u32,
+                *size,
+            ] {
+                self.u32(&mut out, v);
+            }
+            self.u32(&mut out, 7);
+            self.u32(&mut out, 4);
+            contents.extend(data);
+            data_offset += data.len();
+        }
+        out.extend(contents);
+        if self.symbols.is_empty() {
+            return out;
+        }
+
+        let strings_offset = out.len();
+        out.push(0);
+        let mut names = Vec::new();
+        for (name, ..) in &self.symbols {
+            names.push((out.len() - strings_offset) as u32);
+            out.extend(name.bytes());
+            out.push(0);
+        }
+        let strings_size = out.len() - strings_offset;
+
+        let table_offset = out.len();
+        out.resize(out.len() + 16, 0);
+        for ((_, addr, size, kind), name) in self.symbols.iter().zip(names) {
+            for v in [name, *addr, *size] {
+                self.u32(&mut out, v);
+            }
+            out.extend([*kind, 0, 1, 0]);
+        }
+        let table_size = out.len() - table_offset;
+
+        // null, symbol table, strings
+        let sections = out.len() as u32;
+        out.resize(out.len() + 40, 0);
+        for (kind, offset, size, link) in [
+            (2, table_offset, table_size, 2),
+            (3, strings_offset, strings_size, 0),
+        ] {
+            for v in [0, kind, 0, 0, offset as u32, size as u32, link, 0, 4, 16] {
+                self.u32(&mut out, v);
+            }
+        }
+
+        let mut header = Vec::new();
+        self.u32(&mut header, sections);
+        out[shoff..shoff + 4].copy_from_slice(&header);
+        header.clear();
+        self.u16(&mut header, 3);
+        out[48..50].copy_from_slice(&header);
+        out
+    }
+}
+
+fn error(bytes: &[u8]) -> String {
+    Elf::parse(bytes).unwrap_err().message().to_string()
+}
+
+#[test]
+fn parse() {
+    let elf = Builder::new(EM_RISCV)
+        .segment(0x1000, &[1, 2, 3, 4], 4)
+        .segment(0x2000, &[5, 6], 8)
+        .symbol("data", 0x2000, 8, STT_OBJECT)
+        .symbol("main", 0x1000, 4, STT_FUNC)
+        .symbol(".text", 0x1000, 0, STT_SECTION)
+        .build();
+    let elf = Elf::parse(&elf).unwrap();
+
+    assert_eq!(elf.entry(), 0x1000);
+    assert!(!elf.is_big_endian());
+    let segments: Vec<_> = elf
+        .segments()
+        .iter()
+        .map(|s| (s.addr(), s.data(), s.size()))
+        .collect();
+    assert_eq!(
+        segments,
+        [(0x1000, &[1, 2, 3, 4][..], 4), (0x2000, &[5, 6][..], 8)]
+    );
+
+    // functions and objects only, by address
+    let symbols: Vec<_> = elf
+        .symbols()
+        .iter()
+        .map(|s| (s.name(), s.addr(), s.size(), s.is_function()))
+        .collect();
+    assert_eq!(
+        symbols,
+        [("main", 0x1000, 4, true), ("data", 0x2000, 8, false)]
+    );
+    assert_eq!(elf.symbol("data").unwrap().addr(), 0x2000);
+    assert!(elf.symbol(".text").is_none());
+
+    // stripped
+    let elf = Builder::new(EM_MIPS).segment(0, &[0; 4], 4).build();
+    let elf = Elf::parse(&elf).unwrap();
+    assert!(elf.is_big_endian());
+    assert!(elf.symbols().is_empty());
+}
+
+#[test]
+fn malformed() {
+    let valid = Builder::new(EM_RISCV).segment(0x1000, &[0; 8], 8).build();
+    assert!(Elf::parse(&valid).is_ok());
+
+    let patched = |offset: usize, bytes: &[u8]| {
+        let mut elf = valid.clone();
+        elf[offset..offset + bytes.len()].copy_from_slice(bytes);
+        elf
+    };
+    let cases = [
+        (Vec::new(), "not an ELF file"),
+        (valid[..51].to_vec(), "not an ELF file"),
+        (patched(0, b"\x7fELG"), "not an ELF file"),
+        (patched(4, &[2]), "not a 32-bit ELF file"),
+        (patched(5, &[3]), "unknown byte order"),
+        (patched(16, &[1, 0]), "not an executable"),
+        (patched(16, &[3, 0]), "not an executable"),
+        (patched(18, &[3, 0]), "not a MIPS or RISC-V executable"),
+        // the program headers, then the segment, past the end
+        (valid[..60].to_vec(), "truncated file"),
+        (valid[..90].to_vec(), "truncated file"),
+        (patched(28, &[0xff, 0xff, 0xff, 0xff]), "truncated file"),
+        // 8 bytes in the file but 4 in memory
+        (
+            patched(52 + 20, &[4]),
+            "segment larger in the file than in memory",
+        ),
+    ];
+    for (bytes, message) in &cases {
+        assert_eq!(
+            error(bytes),
+            *message,
+            "{:02x?}",
+            &bytes[..bytes.len().min(20)]
+        );
+    }
+
+    let relocatable = Builder {
+        kind: ET_REL,
+        ..Builder::new(EM_MIPS)
+    };
+    assert_eq!(error(&relocatable.build()), "not an executable");
+}
+
+// a Ram of 16 words at 0x1000, and another at 0x2000, both filled with ones
+fn system<C: Component + Send + 'static>(cpu: C) -> (Sim, usize, [(usize, u32); 2]) {
+    let mut s = Sim::new();
+    let cpu = s.add_component(cpu);
+    let text = s.add_component(Ram::new(&[u32::MAX; 16]));
+    let data = s.add_component(Ram::new(&[u32::MAX; 16]));
+    (s, cpu, [(text, 0x1000), (data, 0x2000)])
+}
+
+fn words(s: &Sim, ram: usize, range: std::ops::Range<usize>) -> Vec<u32> {
+    let ram = s.component::<Ram>(ram).unwrap();
+    range.map(|word| ram.read(word)).collect()
+}
+
+#[test]
+fn load() {
+    let elf = Builder {
+        entry: 0x1004,
+        ..Builder::new(EM_RISCV)
+    }
+    .segment(0x1000, &[1, 2, 3, 4, 5, 6, 7, 8], 8)
+    // the rest of its 10 bytes zeroed
+    .segment(0x2002, &[0xaa, 0xbb, 0xcc], 10);
+    let elf = Elf::parse(&elf.build()).unwrap();
+
+    let (mut s, cpu, memory) = system(Rv32::new(false));
+    elf.load(&mut s, cpu, &memory).unwrap();
+    assert_eq!(
+        words(&s, memory[0].0, 0..3),
+        [0x0403_0201, 0x0807_0605, u32::MAX]
+    );
+    assert_eq!(
+        words(&s, memory[1].0, 0..4),
+        [0xbbaa_ffff, 0x0000_00cc, 0, u32::MAX]
+    );
+    assert_eq!(s.component::<Rv32>(cpu).unwrap().pc(), 0x1004);
+
+    // big endian
+    let elf = Builder::new(EM_MIPS).segment(0x1000, &[1, 2, 3], 6).build();
+    let elf = Elf::parse(&elf).unwrap();
+    let (mut s, cpu, memory) = system(Mips::new());
+    elf.load(&mut s, cpu, &memory).unwrap();
+    assert_eq!(
+        words(&s, memory[0].0, 0..3),
+        [0x0102_0300, 0x0000_ffff, u32::MAX]
+    );
+    assert_eq!(s.component::<Mips>(cpu).unwrap().pc(), 0x1000);
+}
+
+#[test]
+fn outside_the_map() {
+    let cases = [
+        (
+            0x3000,
+            4,
+            "segment at 0x00003000 is not within the memory map",
+        ),
+        // straddling the end of the first Ram
+        (
+            0x103c,
+            8,
+            "segment at 0x0000103c is not within the memory map",
+        ),
+        (
+            0xffff_fffc,
+            8,
+            "segment at 0xfffffffc is not within the memory map",
+        ),
+    ];
+
+    for &(addr, size, message) in &cases {
+        let elf = Builder::new(EM_RISCV).segment(addr, &[], size).build();
+        let elf = Elf::parse(&elf).unwrap();
+        let (mut s, cpu, memory) = system(Rv32::new(false));
+        let error = elf.load(&mut s, cpu, &memory).unwrap_err();
+        assert_eq!(error.message(), message);
+    }
+
+    // and neither a Ram nor a Rom
+    let elf = Builder::new(EM_RISCV).segment(0x1000, &[], 4).build();
+    let elf = Elf::parse(&elf).unwrap();
+    let (mut s, cpu, _) = system(Rv32::new(false));
+    let error = elf.load(&mut s, cpu, &[(cpu, 0x1000)]).unwrap_err();
+    assert_eq!(
+        error.message(),
+        format!("component {} is not a Ram or Rom", cpu)
+    );
+}
+
+#[test]
+fn describe() {
+    let elf = Builder::new(EM_RISCV)
+        .symbol("main", 0x1000, 0x10, STT_FUNC)
+        // of unknown size
+        .symbol("start", 0x1010, 0, STT_FUNC)
+        .symbol("table", 0x2000, 8, STT_OBJECT)
+        .symbol("flag", 0x3000, 0, STT_OBJECT)
+        .build();
+    let elf = Elf::parse(&elf).unwrap();
+
+    let cases = [
+        (0x0ffc, "0x00000ffc"),
+        (0x1000, "main"),
+        (0x100c, "main+0xc"),
+        (0x1010, "start"),
+        (0x1ffc, "start+0xfec"),
+        (0x2004, "table+0x4"),
+        // past the object, and not the function before it either
+        (0x2008, "0x00002008"),
+        // objects of unknown size covering nothing
+        (0x3000, "0x00003000"),
+    ];
+    for &(addr, description) in &cases {
+        assert_eq!(elf.describe(addr), description, "{:#x}", addr);
+    }
+    assert_eq!(elf.lookup(0x1ffc).unwrap().name(), "start");
+}
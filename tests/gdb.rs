@@ -0,0 +1,176 @@
+// Sessions with the gdb stub over a Pipe, a packet at a time, against a Mips
+// running nops from a mapped Ram
+
+use sim_rs::{
+    components::{
+        cpu::Mips,
+        logic::{And, Not},
+        mem::Ram,
+    },
+    gdb::{GdbStub, Pipe, TcpLink},
+    Sim,
+};
+use std::{
+    io::{Cursor, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+// a word of the Ram past where the nops are run to
+const DATA: (usize, u32) = (0xf0, 0x0123_4567);
+
+const PC: usize = 37;
+
+fn range(start: usize, end: usize) -> Vec<usize> {
+    (start..=end).collect()
+}
+
+// the Sim and a stub with the instruction Ram mapped at 0
+fn system() -> (Sim, GdbStub) {
+    let mut instr = [0; 1 << 8];
+    instr[DATA.0] = DATA.1;
+
+    let mut s = Sim::new();
+    s.begin_build();
+
+    let cpu = s.add_component(Mips::new());
+    let instr_ram = s.add_component(Ram::new(&instr));
+    let data_ram = s.add_component(Ram::new(&[0; 32]));
+    let not = s.add_component(Not);
+    let and = s.add_component(And);
+
+    s.connect(not, 1, cpu, 96);
+    s.connect_to_clk(and, 1);
+    s.connect(and, 2, not, 2);
+    s.connect(and, 3, cpu, 130);
+
+    s.connect_bulk(cpu, &range(3, 10), instr_ram, &range(1, 8));
+    s.connect_bulk(cpu, &range(33, 64), instr_ram, &range(33, 64));
+    s.connect_bulk(cpu, &range(67, 71), data_ram, &range(1, 5));
+    s.connect_bulk(cpu, &range(97, 128), data_ram, &range(33, 64));
+    s.connect(cpu, 129, data_ram, 65);
+
+    s.finish_build();
+
+    let mut gdb = GdbStub::new(cpu);
+    gdb.map_ram(instr_ram, 0);
+    (s, gdb)
+}
+
+fn packet(data: &str) -> String {
+    let sum = data.bytes().fold(0u8, |acc, b| acc.wrapping_add(b));
+    format!("${data}#{sum:02x}")
+}
+
+// the replies to `packets`, checking each was acknowledged and framed
+fn session(packets: &[&str]) -> Vec<String> {
+    let (mut s, mut gdb) = system();
+    let input = packets.iter().map(|p| packet(p)).collect::<String>();
+
+    let mut link = Pipe(Cursor::new(input.into_bytes()), Vec::new());
+    gdb.serve(&mut s, &mut link).unwrap();
+
+    let output = String::from_utf8(link.1).unwrap();
+    let mut replies = Vec::new();
+    let mut rest = output.as_str();
+    while let Some(r) = rest.strip_prefix('+') {
+        let end = r.find('#').expect("unterminated reply") + 3;
+        let data = &r[1..end - 3];
+        assert_eq!(&r[..end], packet(data), "malformed reply");
+
+        replies.push(data.to_string());
+        rest = &r[end..];
+    }
+    assert_eq!(rest, "", "unacknowledged packet");
+
+    replies
+}
+
+// register `r` of a `g` reply
+fn register(reply: &str, r: usize) -> u32 {
+    u32::from_str_radix(&reply[8 * r..8 * r + 8], 16).unwrap()
+}
+
+#[test]
+fn registers() {
+    let replies = session(&["g", "P4=0000002a", "P25=00000100", "p4", "g", "p40"]);
+
+    assert_eq!(replies[0].len(), 38 * 8);
+    assert_eq!(register(&replies[0], PC), 0);
+    assert_eq!(replies[1..4], ["OK", "OK", "0000002a"]);
+    assert_eq!(register(&replies[4], 4), 42);
+    assert_eq!(register(&replies[4], PC), 0x100);
+    assert_eq!(replies[5], "xxxxxxxx");
+}
+
+#[test]
+fn memory() {
+    let replies = session(&[
+        "m3c0,4",
+        "M3c4,4:deadbeef",
+        "m3c0,8",
+        "m3c1,2",
+        "m1000,4",
+        "M3c4,4:dead",
+    ]);
+    assert_eq!(
+        replies,
+        ["01234567", "OK", "01234567deadbeef", "2345", "E01", "E01"]
+    );
+}
+
+#[test]
+fn breakpoints() {
+    // the one at 0x10 is removed before it is reached, and watchpoint kinds
+    // are not supported
+    let replies = session(&[
+        "Z0,10,4", "Z1,18,4", "z0,10,4", "Z2,8,4", "c", "g", "Z0,20,4", "c", "g",
+    ]);
+
+    assert_eq!(replies[..5], ["OK", "OK", "OK", "", "S05"]);
+    assert_eq!(register(&replies[5], PC), 0x18);
+    assert_eq!(replies[6..8], ["OK", "S05"]);
+    assert_eq!(register(&replies[8], PC), 0x20);
+}
+
+#[test]
+fn step() {
+    let replies = session(&["s", "g", "s", "g", "P25=00000020", "s", "g"]);
+
+    assert_eq!(replies[0], "S05");
+    assert_eq!(register(&replies[1], PC), 4);
+    assert_eq!(replies[2], "S05");
+    assert_eq!(register(&replies[3], PC), 8);
+    assert_eq!(replies[4..6], ["OK", "S05"]);
+    assert_eq!(register(&replies[6], PC), 0x24);
+}
+
+#[test]
+fn empty_and_unknown_packets() {
+    // unsupported, so empty replies, even for a first character that is not ASCII
+    assert_eq!(session(&["", "\u{e9}", "X"]), ["", "", ""]);
+}
+
+#[test]
+fn detach() {
+    // nothing after a detach is served
+    assert_eq!(session(&["D", "g"]), ["OK"]);
+}
+
+#[test]
+fn tcp_link_keeps_what_is_not_an_interrupt() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+
+    client.write_all(b"$\x03").unwrap();
+    // until both bytes have arrived
+    let mut peeked = [0; 2];
+    while stream.peek(&mut peeked).unwrap() < 2 {}
+
+    let mut link = TcpLink::new(stream);
+    assert!(!sim_rs::gdb::Link::interrupted(&mut link));
+    let mut byte = [0];
+    link.read_exact(&mut byte).unwrap();
+    assert_eq!(byte[0], b'$');
+    assert!(sim_rs::gdb::Link::interrupted(&mut link));
+}
@@ -0,0 +1,134 @@
+// An InterruptController driven through its registers by an Input standing in
+// for the bus master, its lines by another, a clock cycle at a time
+
+use sim_rs::{
+    components::{intc::InterruptController, port::Input},
+    Sim,
+};
+
+const PENDING: u32 = 0;
+const ENABLE: u32 = 1;
+const EDGE: u32 = 2;
+const CLAIM: u32 = 3;
+const PRIORITY: u32 = 4;
+
+const IRQ: usize = 67;
+
+fn set(s: &mut Sim, c: usize, first: usize, width: usize, value: u32) {
+    for bit in 0..width {
+        s.write(c, first + bit, value >> bit & 1 != 0);
+    }
+}
+
+fn get(s: &Sim, c: usize, first: usize, width: usize) -> u32 {
+    (0..width).fold(0, |acc, bit| acc | u32::from(s.read(c, first + bit)) << bit)
+}
+
+// a register write, taking effect as the strobe falls
+fn write(s: &mut Sim, master: usize, reg: u32, value: u32) {
+    s.write(master, 65, true);
+    set(s, master, 1, 32, reg);
+    set(s, master, 33, 32, value);
+    s.write(master, 65, false);
+}
+
+fn read(s: &mut Sim, master: usize, reg: u32) -> u32 {
+    set(s, master, 1, 32, reg);
+    get(s, master, 33, 32)
+}
+
+fn cycles(s: &mut Sim, n: usize) {
+    for _ in 0..2 * n {
+        s.tick();
+    }
+}
+
+// the Sim, an InterruptController of 4 lines, its master and the Input
+// driving its lines
+fn system() -> (Sim, usize, usize, usize) {
+    let mut s = Sim::new();
+    s.begin_build();
+
+    let c = s.add_component(InterruptController::new(4));
+    let m = s.add_component(Input::new(65));
+    for pin in 1..=65 {
+        s.connect(m, pin, c, pin);
+    }
+    s.connect_to_clk(c, 66);
+    let lines = s.add_component(Input::new(4));
+    for line in 0..4 {
+        s.connect(lines, line + 1, c, InterruptController::line_pin(line));
+    }
+
+    s.finish_build();
+    (s, c, m, lines)
+}
+
+#[test]
+fn level_and_edge() {
+    let (mut s, c, m, lines) = system();
+    write(&mut s, m, EDGE, 0b10);
+
+    // sampled on the clock
+    set(&mut s, lines, 1, 4, 0b11);
+    assert_eq!(read(&mut s, m, PENDING), 0);
+    cycles(&mut s, 1);
+    assert_eq!(read(&mut s, m, PENDING), 0b11);
+
+    // a level-triggered line pending while high, an edge-triggered one until cleared
+    set(&mut s, lines, 1, 4, 0);
+    cycles(&mut s, 1);
+    assert_eq!(read(&mut s, m, PENDING), 0b10);
+    write(&mut s, m, PENDING, 0b11);
+    assert_eq!(read(&mut s, m, PENDING), 0);
+
+    // held high, it does not latch again without another rising edge
+    set(&mut s, lines, 1, 4, 0b10);
+    cycles(&mut s, 1);
+    write(&mut s, m, PENDING, 0b10);
+    cycles(&mut s, 2);
+    assert_eq!(read(&mut s, m, PENDING), 0);
+    assert!(!s.read(c, IRQ));
+}
+
+#[test]
+fn enable_mask() {
+    let (mut s, c, m, lines) = system();
+
+    set(&mut s, lines, 1, 4, 0b0100);
+    cycles(&mut s, 1);
+    assert_eq!(read(&mut s, m, PENDING), 0b0100);
+    assert!(!s.read(c, IRQ));
+    assert_eq!(read(&mut s, m, CLAIM), 0);
+
+    write(&mut s, m, ENABLE, 0b1011);
+    assert!(!s.read(c, IRQ));
+    write(&mut s, m, ENABLE, 0xffff_ffff);
+    assert_eq!(read(&mut s, m, ENABLE), 0b1111);
+    assert!(s.read(c, IRQ));
+    assert_eq!(read(&mut s, m, CLAIM), 3);
+}
+
+#[test]
+fn priority() {
+    let (mut s, _, m, lines) = system();
+    write(&mut s, m, ENABLE, 0b1111);
+    set(&mut s, lines, 1, 4, 0b1111);
+    cycles(&mut s, 1);
+
+    // all of priority 0, so the lowest line
+    assert_eq!(read(&mut s, m, CLAIM), 1);
+    write(&mut s, m, PRIORITY + 2, 5);
+    assert_eq!(read(&mut s, m, PRIORITY + 2), 5);
+    assert_eq!(read(&mut s, m, CLAIM), 3);
+    // a tie between lines 1 and 2
+    write(&mut s, m, PRIORITY + 1, 5);
+    assert_eq!(read(&mut s, m, CLAIM), 2);
+    write(&mut s, m, PRIORITY + 3, 6);
+    assert_eq!(read(&mut s, m, CLAIM), 4);
+
+    // only pending lines are claimed
+    set(&mut s, lines, 1, 4, 0b0001);
+    cycles(&mut s, 1);
+    assert_eq!(read(&mut s, m, CLAIM), 1);
+}
@@ -0,0 +1,240 @@
+// Logisim projects laid out as the editor saves them, each pin placed on a port
+// of the component under test, and driven through the labelled pins
+
+use sim_rs::{
+    import::{logisim, Ports},
+    Sim,
+};
+
+// a Register at (200,100): d (170,100), q (220,100), clk (180,120), clear
+// (200,120), and its enable input left unconnected
+const REGISTER: &str = r##"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<project source="3.8.0" version="1.0">
+  <lib desc="#Wiring" name="0"/>
+  <lib desc="#Memory" name="4"/>
+  <main name="main"/>
+  <circuit name="main">
+    <wire from="(100,100)" to="(170,100)"/>
+    <comp lib="0" loc="(100,100)" name="Pin">
+      <a name="width" val="4"/>
+      <a name="label" val="d"/>
+    </comp>
+    <comp lib="0" loc="(220,100)" name="Pin">
+      <a name="facing" val="west"/>
+      <a name="output" val="true"/>
+      <a name="width" val="4"/>
+      <a name="label" val="q"/>
+    </comp>
+    <comp lib="0" loc="(180,120)" name="Clock"/>
+    <comp lib="0" loc="(200,120)" name="Pin">
+      <a name="facing" val="north"/>
+      <a name="label" val="clear"/>
+    </comp>
+    <comp lib="4" loc="(200,100)" name="Register">
+      <a name="width" val="4"/>
+    </comp>
+  </circuit>
+</project>
+"##;
+
+// a RAM at (300,200): addr (160,200), data (300,200), sel (210,240), clk
+// (230,240), ld (250,240), clr (270,240)
+const RAM: &str = r##"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<project source="3.8.0" version="1.0">
+  <lib desc="#Wiring" name="0"/>
+  <lib desc="#Memory" name="4"/>
+  <main name="main"/>
+  <circuit name="main">
+    <comp lib="0" loc="(160,200)" name="Pin">
+      <a name="width" val="2"/>
+      <a name="label" val="addr"/>
+    </comp>
+    <comp lib="0" loc="(300,200)" name="Pin">
+      <a name="facing" val="west"/>
+      <a name="width" val="4"/>
+      <a name="label" val="data"/>
+    </comp>
+    <comp lib="0" loc="(210,240)" name="Pin">
+      <a name="facing" val="north"/>
+      <a name="label" val="sel"/>
+    </comp>
+    <comp lib="0" loc="(230,240)" name="Pin">
+      <a name="facing" val="north"/>
+      <a name="label" val="clk"/>
+    </comp>
+    <comp lib="0" loc="(250,240)" name="Pin">
+      <a name="facing" val="north"/>
+      <a name="label" val="ld"/>
+    </comp>
+    <comp lib="0" loc="(270,240)" name="Pin">
+      <a name="facing" val="north"/>
+      <a name="label" val="clr"/>
+    </comp>
+    <comp lib="4" loc="(300,200)" name="RAM">
+      <a name="addrWidth" val="2"/>
+      <a name="dataWidth" val="4"/>
+    </comp>
+  </circuit>
+</project>
+"##;
+
+// a ROM at (300,200) with its contents: addr (160,200), data (300,200), and
+// sel (210,240) unless `{sel}` leaves it unconnected
+const ROM: &str = r##"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<project source="3.8.0" version="1.0">
+  <lib desc="#Wiring" name="0"/>
+  <lib desc="#Memory" name="4"/>
+  <main name="main"/>
+  <circuit name="main">
+    <comp lib="0" loc="(160,200)" name="Pin">
+      <a name="width" val="2"/>
+      <a name="label" val="addr"/>
+    </comp>
+    <comp lib="0" loc="(300,200)" name="Pin">
+      <a name="facing" val="west"/>
+      <a name="output" val="true"/>
+      <a name="width" val="8"/>
+      <a name="label" val="data"/>
+    </comp>
+    {sel}
+    <comp lib="4" loc="(300,200)" name="ROM">
+      <a name="addrWidth" val="2"/>
+      <a name="dataWidth" val="8"/>
+      <a name="contents">addr/data: 2 8&#10;12 2*34 56</a>
+    </comp>
+  </circuit>
+</project>
+"##;
+
+const ROM_SEL: &str = r##"<comp lib="0" loc="(210,240)" name="Pin">
+      <a name="facing" val="north"/>
+      <a name="label" val="sel"/>
+    </comp>"##;
+
+fn load(source: &str) -> (Sim, Ports) {
+    let mut s = Sim::new();
+    let ports = logisim::load(&mut s, source, None).unwrap();
+    (s, ports)
+}
+
+fn set(s: &mut Sim, ports: &Ports, name: &str, width: usize, value: u32) {
+    for bit in 0..width {
+        s.write(ports[name], bit + 1, value >> bit & 1 != 0);
+    }
+}
+
+fn get(s: &Sim, ports: &Ports, name: &str, width: usize) -> u32 {
+    (0..width).fold(0, |acc, bit| {
+        acc | u32::from(s.read(ports[name], bit + 1)) << bit
+    })
+}
+
+#[test]
+fn register() {
+    let (mut s, ports) = load(REGISTER);
+
+    set(&mut s, &ports, "d", 4, 5);
+    assert_eq!(get(&s, &ports, "q", 4), 0);
+    // latched on the rising edge only
+    s.tick();
+    assert_eq!(get(&s, &ports, "q", 4), 5);
+    set(&mut s, &ports, "d", 4, 9);
+    s.tick();
+    assert_eq!(get(&s, &ports, "q", 4), 5);
+    s.tick();
+    assert_eq!(get(&s, &ports, "q", 4), 9);
+
+    set(&mut s, &ports, "clear", 1, 1);
+    assert_eq!(get(&s, &ports, "q", 4), 0);
+    s.tick();
+    s.tick();
+    assert_eq!(get(&s, &ports, "q", 4), 0);
+    set(&mut s, &ports, "clear", 1, 0);
+    s.tick();
+    s.tick();
+    assert_eq!(get(&s, &ports, "q", 4), 9);
+}
+
+// the word at `addr` of a RAM, loaded onto the data bus
+fn load_word(s: &mut Sim, ports: &Ports, addr: u32) -> u32 {
+    set(s, ports, "addr", 2, addr);
+    set(s, ports, "sel", 1, 1);
+    set(s, ports, "ld", 1, 1);
+    let word = get(s, ports, "data", 4);
+    set(s, ports, "ld", 1, 0);
+    word
+}
+
+// stores `data` at `addr` of a RAM with a clock pulse, selected or not
+fn store(s: &mut Sim, ports: &Ports, addr: u32, data: u32, sel: bool) {
+    set(s, ports, "ld", 1, 0);
+    set(s, ports, "sel", 1, sel.into());
+    set(s, ports, "addr", 2, addr);
+    set(s, ports, "data", 4, data);
+    set(s, ports, "clk", 1, 1);
+    set(s, ports, "clk", 1, 0);
+}
+
+#[test]
+fn ram() {
+    let (mut s, ports) = load(RAM);
+
+    // only on the clock edge
+    set(&mut s, &ports, "sel", 1, 1);
+    set(&mut s, &ports, "addr", 2, 1);
+    set(&mut s, &ports, "data", 4, 0xa);
+    assert_eq!(load_word(&mut s, &ports, 1), 0);
+
+    store(&mut s, &ports, 1, 0xa, true);
+    store(&mut s, &ports, 2, 0x5, true);
+    assert_eq!(load_word(&mut s, &ports, 1), 0xa);
+    assert_eq!(load_word(&mut s, &ports, 2), 0x5);
+
+    // only while selected
+    store(&mut s, &ports, 3, 0x7, false);
+    assert_eq!(load_word(&mut s, &ports, 3), 0);
+
+    // not driving the data bus unless selected and loading
+    set(&mut s, &ports, "addr", 2, 1);
+    set(&mut s, &ports, "data", 4, 0x3);
+    set(&mut s, &ports, "ld", 1, 1);
+    set(&mut s, &ports, "sel", 1, 0);
+    set(&mut s, &ports, "data", 4, 0x6);
+    assert_eq!(get(&s, &ports, "data", 4), 0x6);
+    set(&mut s, &ports, "sel", 1, 1);
+    assert_eq!(get(&s, &ports, "data", 4), 0xa);
+
+    // clear empties it, and stores nothing while held
+    set(&mut s, &ports, "clr", 1, 1);
+    store(&mut s, &ports, 2, 0xf, true);
+    set(&mut s, &ports, "clr", 1, 0);
+    for addr in 0..4 {
+        assert_eq!(load_word(&mut s, &ports, addr), 0, "addr {addr}");
+    }
+}
+
+#[test]
+fn rom() {
+    let words = [0x12, 0x34, 0x34, 0x56];
+
+    let (mut s, ports) = load(&ROM.replace("{sel}", ROM_SEL));
+    set(&mut s, &ports, "sel", 1, 1);
+    for (addr, &word) in (0..).zip(&words) {
+        set(&mut s, &ports, "addr", 2, addr);
+        assert_eq!(get(&s, &ports, "data", 8), word, "addr {addr}");
+    }
+
+    // deselected, the data bus is left as it was
+    set(&mut s, &ports, "sel", 1, 0);
+    set(&mut s, &ports, "addr", 2, 0);
+    assert_eq!(get(&s, &ports, "data", 8), 0x56);
+    set(&mut s, &ports, "sel", 1, 1);
+    assert_eq!(get(&s, &ports, "data", 8), 0x12);
+
+    // an unconnected select leaves it selected
+    let (mut s, ports) = load(&ROM.replace("{sel}", ""));
+    for (addr, &word) in (0..).zip(&words) {
+        set(&mut s, &ports, "addr", 2, addr);
+        assert_eq!(get(&s, &ports, "data", 8), word, "addr {addr}");
+    }
+}
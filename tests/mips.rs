@@ -0,0 +1,352 @@
+// A Mips running from a Ram mapped at 0, every word past the program a nop,
+// its trace gathered as it goes. The programs are coprocessor 0 instructions,
+// which the Mips carries out itself.
+
+use sim_rs::{
+    components::{
+        cpu::{Event, Mips},
+        mem::Ram,
+        port::Input,
+    },
+    Sim,
+};
+use std::sync::{Arc, Mutex};
+
+fn mfc0(rt: u32, rd: u32) -> u32 {
+    0x4000_0000 | rt << 16 | rd << 11
+}
+
+fn mtc0(rt: u32, rd: u32) -> u32 {
+    0x4080_0000 | rt << 16 | rd << 11
+}
+
+fn ei(rt: u32) -> u32 {
+    0x4160_6020 | rt << 16
+}
+
+const ERET: u32 = 0x4200_0018;
+
+const STATUS: u32 = 12;
+const CAUSE: u32 = 13;
+const EPC: u32 = 14;
+const PRID: u32 = 15;
+
+fn range(start: usize, end: usize) -> Vec<usize> {
+    (start..=end).collect()
+}
+
+struct System {
+    sim: Sim,
+    cpu: usize,
+    // reset and the interrupt lines
+    pins: usize,
+    events: Arc<Mutex<Vec<(usize, Event)>>>,
+}
+
+impl System {
+    // `program` at 0 and `vector` at the exception vector, through the 256
+    // words of Ram the instruction addresses wrap around
+    fn new(program: &[u32], vector: &[u32]) -> Self {
+        let mut instr = [0; 1 << 8];
+        instr[..program.len()].copy_from_slice(program);
+        instr[0x60..0x60 + vector.len()].copy_from_slice(vector);
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut mips = Mips::new();
+        let sink = Arc::clone(&events);
+        mips.set_trace(move |cycle, event| sink.lock().unwrap().push((cycle, event)));
+
+        let mut s = Sim::new();
+        s.begin_build();
+
+        let cpu = s.add_component(mips);
+        let instr_ram = s.add_component(Ram::new(&instr));
+        let data_ram = s.add_component(Ram::new(&[0; 32]));
+        let pins = s.add_component(Input::new(7));
+
+        s.connect_to_clk(cpu, 130);
+        s.connect_bulk(cpu, &range(3, 10), instr_ram, &range(1, 8));
+        s.connect_bulk(cpu, &range(33, 64), instr_ram, &range(33, 64));
+        s.connect_bulk(cpu, &range(67, 71), data_ram, &range(1, 5));
+        s.connect_bulk(cpu, &range(97, 128), data_ram, &range(33, 64));
+        s.connect(cpu, 129, data_ram, 65);
+        s.connect_bulk(pins, &range(1, 7), cpu, &range(131, 137));
+
+        s.finish_build();
+        Self {
+            sim: s,
+            cpu,
+            pins,
+            events,
+        }
+    }
+
+    fn mips(&self) -> &Mips {
+        self.sim.component::<Mips>(self.cpu).unwrap()
+    }
+
+    fn mips_mut(&mut self) -> &mut Mips {
+        self.sim.component_mut::<Mips>(self.cpu).unwrap()
+    }
+
+    // runs until `n` more instructions have retired
+    fn step(&mut self, n: usize) {
+        let cpu = self.cpu;
+        let target = self.mips().instructions() + n;
+        self.sim.set_max_ticks(100 * n);
+        self.sim
+            .run_until(|s| s.component::<Mips>(cpu).unwrap().instructions() >= target);
+        assert_eq!(self.mips().instructions(), target);
+    }
+
+    fn set_reset(&mut self, high: bool) {
+        self.sim.write(self.pins, 1, high);
+    }
+
+    fn set_line(&mut self, line: usize, high: bool) {
+        self.sim.write(self.pins, line + 2, high);
+    }
+
+    fn exceptions(&self) -> Vec<Event> {
+        let events = self.events.lock().unwrap();
+        events
+            .iter()
+            .map(|&(_, e)| e)
+            .filter(|e| matches!(e, Event::Exception { .. }))
+            .collect()
+    }
+
+    // the address of the instruction retired last
+    fn retired(&self) -> u32 {
+        let events = self.events.lock().unwrap();
+        events
+            .iter()
+            .rev()
+            .find_map(|&(_, e)| match e {
+                Event::Retire { pc, .. } => Some(pc),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    // the events gathered so far, fetches left out
+    fn events(&self) -> Vec<(usize, Event)> {
+        let events = self.events.lock().unwrap();
+        events
+            .iter()
+            .copied()
+            .filter(|(_, e)| !matches!(e, Event::Fetch { .. }))
+            .collect()
+    }
+}
+
+#[test]
+fn trace() {
+    let program = [
+        mfc0(8, STATUS),
+        ei(9),
+        mfc0(10, PRID),
+        mtc0(10, EPC),
+        mfc0(8, STATUS),
+    ];
+    let mut s = System::new(&program, &[]);
+    s.mips_mut().set_status(0xff00);
+    s.step(6);
+
+    let events = s.events();
+    let kinds = events.iter().map(|&(_, e)| e).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            Event::Retire { pc: 0, count: 1 },
+            Event::RegWrite {
+                reg: 8,
+                value: 0xff00
+            },
+            Event::Retire { pc: 4, count: 2 },
+            Event::RegWrite {
+                reg: 9,
+                value: 0xff00
+            },
+            Event::Retire { pc: 8, count: 3 },
+            Event::RegWrite {
+                reg: 10,
+                value: 0x0001_8000
+            },
+            Event::Retire { pc: 12, count: 4 },
+            Event::Retire { pc: 16, count: 5 },
+            Event::RegWrite {
+                reg: 8,
+                value: 0xff01
+            },
+            Event::Retire { pc: 20, count: 6 },
+        ]
+    );
+    assert_eq!((s.mips().status(), s.mips().epc()), (0xff01, 0x0001_8000));
+
+    // writes tagged with the cycle of the instruction retiring
+    for pair in events.windows(2) {
+        if let [(retired, Event::Retire { .. }), (written, Event::RegWrite { .. })] = pair {
+            assert_eq!(retired, written);
+        }
+        assert!(pair[0].0 <= pair[1].0);
+    }
+
+    // each instruction fetched before it retired
+    let all = s.events.lock().unwrap().clone();
+    for (i, &(_, event)) in all.iter().enumerate() {
+        if let Event::Retire { pc, .. } = event {
+            let instr = program.get(pc as usize / 4).copied().unwrap_or(0);
+            let fetch = Event::Fetch { addr: pc, instr };
+            assert!(all[..i].iter().any(|&(_, e)| e == fetch), "{}", event);
+        }
+    }
+
+    // and nothing more once it is cleared
+    let before = s.events.lock().unwrap().len();
+    s.mips_mut().clear_trace();
+    s.step(2);
+    assert_eq!(s.events.lock().unwrap().len(), before);
+}
+
+const IE: u32 = 1;
+const EXL: u32 = 1 << 1;
+const ERL: u32 = 1 << 2;
+const BEV: u32 = 1 << 22;
+// IM0-IM7
+const IM: u32 = 0xff00;
+
+#[test]
+fn interrupts() {
+    for line in 0..6 {
+        let mut s = System::new(&[], &[ERET]);
+        s.mips_mut().set_status(IM | IE);
+        s.step(2);
+
+        // taken after the next instruction
+        s.set_line(line, true);
+        s.step(1);
+        let ip = 1 << (10 + line);
+        let epc = s.retired() + 4;
+        assert_eq!(s.exceptions(), [Event::Exception { epc, cause: ip }]);
+        let mips = s.mips();
+        assert_eq!(mips.pc(), 0x8000_0180, "line {}", line);
+        assert_eq!((mips.epc(), mips.cause()), (epc, ip), "line {}", line);
+        assert_eq!(mips.status(), IM | EXL | IE, "line {}", line);
+
+        // and returned from, the line having gone low
+        s.set_line(line, false);
+        s.step(1);
+        assert_eq!(s.retired(), 0x8000_0180);
+        assert_eq!((s.mips().pc(), s.mips().status()), (epc, IM | IE));
+        s.step(4);
+        assert_eq!(s.exceptions().len(), 1, "line {}", line);
+    }
+}
+
+#[test]
+fn masked() {
+    for &status in &[IM, IM & !0x400 | IE, IM | EXL | IE, IM | ERL | IE] {
+        let mut s = System::new(&[], &[]);
+        s.mips_mut().set_status(status);
+        s.set_line(0, true);
+        s.step(8);
+
+        // pending all the same
+        assert_eq!(s.exceptions(), [], "status {:#x}", status);
+        assert_eq!(s.mips().cause(), 0x400, "status {:#x}", status);
+    }
+
+    // until unmasked
+    let mut s = System::new(&[], &[]);
+    s.mips_mut().set_status(IM);
+    s.set_line(5, true);
+    s.step(2);
+    s.mips_mut().set_status(IM | IE);
+    s.step(1);
+    assert_eq!(s.exceptions().len(), 1);
+}
+
+#[test]
+fn vectors() {
+    // the bootstrap vector
+    let mut s = System::new(&[], &[]);
+    s.mips_mut().set_status(BEV | IM | IE);
+    s.set_line(2, true);
+    s.step(1);
+    assert_eq!(s.mips().pc(), 0xbfc0_0380);
+
+    // a software interrupt, raised by writing Cause
+    let program = [mtc0(8, CAUSE), ERET];
+    let mut s = System::new(&program, &[]);
+    s.mips_mut().set_status(IM | IE);
+    s.mips_mut().set_reg(8, 0x0000_fd00);
+    s.step(1);
+    assert_eq!(
+        s.exceptions(),
+        [Event::Exception {
+            epc: 4,
+            cause: 0x100
+        }]
+    );
+    assert_eq!(s.mips().pc(), 0x8000_0180);
+}
+
+#[test]
+fn reset() {
+    let mut s = System::new(&[], &[]);
+    s.mips_mut().set_reset_vector(0x20);
+    s.mips_mut().set_status(IM | IE);
+    s.step(3);
+
+    // held at the reset vector with coprocessor 0 and the counters cleared
+    s.set_reset(true);
+    s.set_line(0, true);
+    for _ in 0..6 {
+        s.sim.tick();
+        assert_eq!(s.mips().pc(), 0x20);
+    }
+    let mips = s.mips();
+    assert_eq!((mips.status(), mips.instructions()), (0, 0));
+
+    s.set_reset(false);
+    s.step(2);
+    assert_eq!(s.retired(), 0x24);
+    assert_eq!(s.exceptions(), []);
+}
+
+#[test]
+fn registers() {
+    let mut s = System::new(&[], &[]);
+    assert!(s.sim.component::<Ram>(s.cpu).is_none());
+    assert!(s.sim.mips_state(s.pins).is_none());
+    s.step(3);
+
+    let mips = s.mips_mut();
+    mips.set_reg(0, 5);
+    mips.set_reg(31, 7);
+    mips.set_hi(1);
+    mips.set_lo(2);
+    assert_eq!(
+        (mips.reg(0), mips.reg(31), mips.hi(), mips.lo()),
+        (0, 7, 1, 2)
+    );
+
+    // edited as a copy, the counters staying with the Mips
+    let mut state = s.sim.mips_state(s.cpu).unwrap();
+    assert_eq!((state.pc(), state.instructions()), (12, 3));
+    assert_eq!(state.regs()[31], 7);
+    state.set_pc(0x40);
+    state.set_reg(0, 9);
+    state.set_reg(4, 3);
+    state.set_epc(0x10);
+    assert_eq!(s.mips().reg(4), 0);
+    assert!(s.sim.set_mips_state(s.cpu, &state));
+    assert!(!s.sim.set_mips_state(s.pins, &state));
+
+    let mips = s.mips();
+    assert_eq!((mips.pc(), mips.reg(0), mips.reg(4)), (0x40, 0, 3));
+    assert_eq!((mips.epc(), mips.instructions()), (0x10, 3));
+    s.step(1);
+    assert_eq!(s.retired(), 0x40);
+}
@@ -0,0 +1,316 @@
+// The cycles single 6502 instructions take and what short programs compute,
+// and when the 6502 takes an interrupt, for the line asserted on each cycle of
+// a short program: the NMOS part acts on what it sampled as an instruction's
+// penultimate cycle ended, CLI only taking effect after the next one, and a
+// taken branch staying on its page not sampling on its last two cycles.
+
+use sim_rs::{components::cpu::Mos6502, Component, PinIO, Sim, IO};
+
+// 0400: cli
+// 0401: ldx #0
+// 0403: inx
+// 0404: bne $0408
+// 0406: nop
+// 0407: nop
+// 0408: inx
+// 0409: jmp $0409
+const PROGRAM: [u8; 12] = [
+    0x58, 0xa2, 0x00, 0xe8, 0xd0, 0x02, 0xea, 0xea, 0xe8, 0x4c, 0x09, 0x04,
+];
+
+// Memory asserting one interrupt line from cycle `assert` on, counted from
+// the first fetch of the program, and noting the cycle of the first write,
+// which is the interrupt pushing the pc.
+//
+// pins:
+//  1-16: addr
+//  17-24: data
+//  25: r/w
+//  26: phi2
+//  27: sync
+//  28: irq
+//  29: nmi
+//  30: high, for rdy, res and so
+struct Memory {
+    memory: Vec<u8>,
+    line: usize,
+    assert: usize,
+    cycle: usize,
+    start: Option<usize>,
+    push: Option<usize>,
+}
+
+impl Component for Memory {
+    fn pin_count(&self) -> usize {
+        30
+    }
+
+    fn update(&mut self, io: &mut IO) {
+        let addr = io.read_range(1..=16) as usize;
+        let read = io.read(25);
+        if read {
+            io.write_range(17..=24, self.memory[addr].into());
+        }
+
+        if io.is_rising_edge(26) {
+            if io.read(27) && addr == 0x400 && self.start.is_none() {
+                self.start = Some(self.cycle);
+            }
+            if !read && self.push.is_none() {
+                self.push = self.start.map(|s| self.cycle - s);
+            }
+            self.cycle += 1;
+        }
+
+        let asserted = self
+            .start
+            .is_some_and(|s| self.cycle - 1 - s >= self.assert);
+        io.write(28, !(asserted && self.line == 28));
+        io.write(29, !(asserted && self.line == 29));
+        io.write(30, true);
+    }
+}
+
+// the cycle the pc is pushed on
+fn push(line: usize, assert: usize) -> usize {
+    let mut memory = vec![0xea; 1 << 16];
+    memory[0x400..0x400 + PROGRAM.len()].copy_from_slice(&PROGRAM);
+    memory[0xfffa..].copy_from_slice(&[0x00, 0x05, 0x00, 0x04, 0x00, 0x05]);
+
+    let mut s = Sim::new();
+    s.begin_build();
+
+    let cpu = s.add_component(Mos6502::new());
+    let m = s.add_component(Memory {
+        memory,
+        line,
+        assert,
+        cycle: 0,
+        start: None,
+        push: None,
+    });
+
+    let addr = [
+        9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 22, 23, 24, 25,
+    ];
+    let data = [33, 32, 31, 30, 29, 28, 27, 26];
+    s.connect_bulk(cpu, &addr, m, &(1..=16).collect::<Vec<_>>());
+    s.connect_bulk(cpu, &data, m, &(17..=24).collect::<Vec<_>>());
+    for &(a, b) in &[(34, 25), (39, 26), (7, 27), (4, 28), (6, 29)] {
+        s.connect(cpu, a, m, b);
+    }
+    for &pin in &[2, 38, 40] {
+        s.connect(cpu, pin, m, 30);
+    }
+    s.connect_to_clk(cpu, 37);
+
+    s.finish_build();
+    s.run(2 * 40);
+    s.component::<Memory>(m).unwrap().push.unwrap()
+}
+
+#[test]
+fn irq() {
+    // the pc goes on the stack two cycles into the sequence: after LDX ends
+    // on cycle 3, INX on 5, BNE on 8, INX on 10 and JMP on 13
+    let expected = [6, 6, 6, 8, 8, 11, 11, 13, 13, 13, 16, 16, 16];
+    for (assert, &cycle) in expected.iter().enumerate() {
+        assert_eq!(push(28, assert), cycle, "irq from cycle {assert}");
+    }
+}
+
+#[test]
+fn nmi() {
+    // as for IRQ, but CLI has no say
+    let expected = [4, 6, 6, 8, 8, 11, 11, 13, 13, 13, 16, 16, 16];
+    for (assert, &cycle) in expected.iter().enumerate() {
+        assert_eq!(push(29, assert), cycle, "nmi from cycle {assert}");
+    }
+}
+
+// Memory noting the cycle and address of every opcode fetch, storing what is
+// written during phi2.
+//
+// pins:
+//  1-16: addr
+//  17-24: data
+//  25: r/w
+//  26: phi2
+//  27: sync
+//  28: high, for rdy, irq, nmi, res and so
+struct Bus {
+    memory: Vec<u8>,
+    cycle: usize,
+    fetches: Vec<(usize, u16)>,
+}
+
+impl Component for Bus {
+    fn pin_count(&self) -> usize {
+        28
+    }
+
+    fn update(&mut self, io: &mut IO) {
+        let addr = io.read_range(1..=16) as usize;
+        if io.read(25) {
+            io.write_range(17..=24, self.memory[addr].into());
+        } else if io.read(26) {
+            self.memory[addr] = io.read_range(17..=24) as u8;
+        }
+
+        if io.is_rising_edge(26) {
+            if io.read(27) {
+                self.fetches.push((self.cycle, addr as u16));
+            }
+            self.cycle += 1;
+        }
+        io.write(28, true);
+    }
+}
+
+// Runs `cycles` cycles from a reset into 0400 with the memory full of NOPs
+// besides the `patches`, returning the Sim, the cpu and the Bus.
+fn system(patches: &[(u16, &[u8])], cycles: usize) -> (Sim, usize, usize) {
+    let mut memory = vec![0xea; 1 << 16];
+    memory[0xfffc..0xfffe].copy_from_slice(&[0x00, 0x04]);
+    for &(at, bytes) in patches {
+        let at = usize::from(at);
+        memory[at..at + bytes.len()].copy_from_slice(bytes);
+    }
+
+    let mut s = Sim::new();
+    s.begin_build();
+
+    let cpu = s.add_component(Mos6502::new());
+    let bus = s.add_component(Bus {
+        memory,
+        cycle: 0,
+        fetches: Vec::new(),
+    });
+
+    let addr = [
+        9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 22, 23, 24, 25,
+    ];
+    let data = [33, 32, 31, 30, 29, 28, 27, 26];
+    s.connect_bulk(cpu, &addr, bus, &(1..=16).collect::<Vec<_>>());
+    s.connect_bulk(cpu, &data, bus, &(17..=24).collect::<Vec<_>>());
+    for &(a, b) in &[(34, 25), (39, 26), (7, 27)] {
+        s.connect(cpu, a, bus, b);
+    }
+    for &pin in &[2, 4, 6, 38, 40] {
+        s.connect(cpu, pin, bus, 28);
+    }
+    s.connect_to_clk(cpu, 37);
+
+    s.finish_build();
+    s.run(2 * cycles);
+    (s, cpu, bus)
+}
+
+// (what is in memory, where the instruction timed is, its cycles)
+type Timing = (&'static [(u16, &'static [u8])], u16, usize);
+
+#[rustfmt::skip]
+const TIMINGS: &[Timing] = &[
+    // implied and immediate
+    (&[(0x400, &[0xea])], 0x400, 2),
+    (&[(0x400, &[0xa9, 0x10])], 0x400, 2),
+    (&[(0x400, &[0x0a])], 0x400, 2),
+    // zero page, indexed and not
+    (&[(0x400, &[0xa5, 0x10])], 0x400, 3),
+    (&[(0x400, &[0x85, 0x10])], 0x400, 3),
+    (&[(0x400, &[0xb5, 0x10])], 0x400, 4),
+    (&[(0x400, &[0xb6, 0x10])], 0x400, 4),
+    (&[(0x400, &[0xe6, 0x10])], 0x400, 5),
+    (&[(0x400, &[0xf6, 0x10])], 0x400, 6),
+    // absolute, indexed reads taking a cycle more to cross a page
+    (&[(0x400, &[0xad, 0x00, 0x20])], 0x400, 4),
+    (&[(0x400, &[0xa2, 0x01, 0xbd, 0x00, 0x20])], 0x402, 4),
+    (&[(0x400, &[0xa2, 0xff, 0xbd, 0x01, 0x20])], 0x402, 5),
+    (&[(0x400, &[0xa0, 0xff, 0xb9, 0x01, 0x20])], 0x402, 5),
+    (&[(0x400, &[0xa2, 0x01, 0x9d, 0x00, 0x20])], 0x402, 5),
+    (&[(0x400, &[0xee, 0x00, 0x20])], 0x400, 6),
+    (&[(0x400, &[0xa2, 0x01, 0xfe, 0x00, 0x20])], 0x402, 7),
+    // indirect, the pointer at 0010 being eaea
+    (&[(0x400, &[0xa1, 0x10])], 0x400, 6),
+    (&[(0x400, &[0xa0, 0x00, 0xb1, 0x10])], 0x402, 5),
+    (&[(0x400, &[0xa0, 0xff, 0xb1, 0x10])], 0x402, 6),
+    (&[(0x400, &[0xa0, 0x00, 0x91, 0x10])], 0x402, 6),
+    // stack
+    (&[(0x400, &[0x48])], 0x400, 3),
+    (&[(0x400, &[0x08])], 0x400, 3),
+    (&[(0x400, &[0x68])], 0x400, 4),
+    (&[(0x400, &[0x28])], 0x400, 4),
+    // jumps, subroutines and interrupts
+    (&[(0x400, &[0x4c, 0x00, 0x05])], 0x400, 3),
+    (&[(0x400, &[0x6c, 0x00, 0x20])], 0x400, 5),
+    (&[(0x400, &[0x20, 0x00, 0x05])], 0x400, 6),
+    (&[(0x400, &[0x20, 0x00, 0x05]), (0x500, &[0x60])], 0x500, 6),
+    (&[(0x400, &[0x00])], 0x400, 7),
+    (&[(0x400, &[0x40])], 0x400, 6),
+    // branches not taken, taken, and taken to another page
+    (&[(0x400, &[0xa9, 0x00, 0xd0, 0x02])], 0x402, 2),
+    (&[(0x400, &[0xa9, 0x01, 0xd0, 0x02])], 0x402, 3),
+    (&[(0x400, &[0x4c, 0xfb, 0x04]), (0x4fb, &[0xa9, 0x01, 0xd0, 0x10])], 0x4fd, 4),
+    (&[(0x400, &[0xa9, 0x01, 0xd0, 0x80])], 0x402, 4),
+];
+
+#[test]
+fn cycles() {
+    for (i, &(patches, at, cycles)) in TIMINGS.iter().enumerate() {
+        let (s, _, bus) = system(patches, 40);
+        let fetches = &s.component::<Bus>(bus).unwrap().fetches;
+
+        let n = fetches
+            .iter()
+            .position(|&(_, addr)| addr == at)
+            .unwrap_or_else(|| panic!("case {}: {:04x} not reached", i, at));
+        let opcode = s.component::<Bus>(bus).unwrap().memory[usize::from(at)];
+        assert_eq!(
+            fetches[n + 1].0 - fetches[n].0,
+            cycles,
+            "case {}: opcode {:02x}",
+            i,
+            opcode
+        );
+    }
+}
+
+// 0400: lda #$19
+// 0402: clc
+// 0403: adc #$28
+// 0405: sta $10
+// 0407: sed
+// 0408: clc
+// 0409: lda #$19
+// 040b: adc #$28
+// 040d: sta $11
+// 040f: cld
+// 0410: sec
+// 0411: lda #$10
+// 0413: sbc #$20
+// 0415: tax
+// 0416: ldy #3
+// 0418: dey
+// 0419: bne $0418
+// 041b: jmp $041b
+const ARITHMETIC: [u8; 30] = [
+    0xa9, 0x19, 0x18, 0x69, 0x28, 0x85, 0x10, 0xf8, 0x18, 0xa9, 0x19, 0x69, 0x28, 0x85, 0x11, 0xd8,
+    0x38, 0xa9, 0x10, 0xe9, 0x20, 0xaa, 0xa0, 0x03, 0x88, 0xd0, 0xfd, 0x4c, 0x1b, 0x04,
+];
+
+#[test]
+fn arithmetic() {
+    let (s, cpu, bus) = system(&[(0x400, &ARITHMETIC)], 100);
+    let bus = s.component::<Bus>(bus).unwrap();
+    let cpu = s.component::<Mos6502>(cpu).unwrap();
+
+    // binary, then decimal
+    assert_eq!(bus.memory[0x10..0x12], [0x41, 0x47]);
+    // borrowing, so the carry is clear
+    assert_eq!((cpu.a(), cpu.x(), cpu.y()), (0xf0, 0xf0, 0));
+    assert_eq!(cpu.p() & 0x0b, 0x02, "p {:02x}", cpu.p());
+    assert_eq!(cpu.pc(), 0x41b);
+    // the loop ran three times
+    let loops = bus.fetches.iter().filter(|f| f.1 == 0x418).count();
+    assert_eq!(loops, 3);
+}
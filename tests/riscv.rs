@@ -0,0 +1,1179 @@
+// The rv32ui and rv32um programs of riscv-tests rebuilt instruction by
+// instruction, following their conventions: gp holds the number of the case
+// running, and the program writes 1 to `tohost` once every case passed, or
+// (gp << 1) | 1 on the first that failed. The official p environment binaries
+// of a riscv-tests build run with --ignored, RISCV_TESTS set to its isa
+// directory.
+
+use sim_rs::{
+    components::{cpu::Rv32, mem::Ram},
+    elf::Elf,
+    Sim,
+};
+
+// byte addresses
+const TOHOST: u32 = 0x1000;
+const DATA: u32 = 0x2000;
+
+const GP: u32 = 3;
+
+// opcodes
+const OP: u32 = 0x33;
+const OP_IMM: u32 = 0x13;
+const LOAD: u32 = 0x03;
+const STORE: u32 = 0x23;
+const BRANCH: u32 = 0x63;
+const JAL: u32 = 0x6f;
+const JALR: u32 = 0x67;
+const LUI: u32 = 0x37;
+const AUIPC: u32 = 0x17;
+const SYSTEM: u32 = 0x73;
+
+// funct3 and funct7, the latter going into the top of the immediate of
+// shifts by an immediate
+type Op = (u32, u32);
+
+const ADD: Op = (0, 0x00);
+const SUB: Op = (0, 0x20);
+const SLL: Op = (1, 0x00);
+const SLT: Op = (2, 0x00);
+const SLTU: Op = (3, 0x00);
+const XOR: Op = (4, 0x00);
+const SRL: Op = (5, 0x00);
+const SRA: Op = (5, 0x20);
+const OR: Op = (6, 0x00);
+const AND: Op = (7, 0x00);
+
+const MUL: Op = (0, 0x01);
+const MULH: Op = (1, 0x01);
+const MULHSU: Op = (2, 0x01);
+const MULHU: Op = (3, 0x01);
+const DIV: Op = (4, 0x01);
+const DIVU: Op = (5, 0x01);
+const REM: Op = (6, 0x01);
+const REMU: Op = (7, 0x01);
+
+const ADDI: Op = ADD;
+const SLTI: Op = SLT;
+const SLTIU: Op = SLTU;
+const XORI: Op = XOR;
+const ORI: Op = OR;
+const ANDI: Op = AND;
+const SLLI: Op = SLL;
+const SRLI: Op = SRL;
+const SRAI: Op = SRA;
+
+// funct3 of loads, stores and branches
+const LB: u32 = 0;
+const LH: u32 = 1;
+const LW: u32 = 2;
+const LBU: u32 = 4;
+const LHU: u32 = 5;
+const SB: u32 = 0;
+const SH: u32 = 1;
+const SW: u32 = 2;
+const BEQ: u32 = 0;
+const BNE: u32 = 1;
+const BLT: u32 = 4;
+const BGE: u32 = 5;
+const BLTU: u32 = 6;
+const BGEU: u32 = 7;
+
+// machine mode CSRs
+const MTVEC: u32 = 0x305;
+const MEPC: u32 = 0x341;
+const MCAUSE: u32 = 0x342;
+const MTVAL: u32 = 0x343;
+
+// code from address 0 and data from DATA, assembled an instruction at a time
+#[derive(Default)]
+struct Program {
+    code: Vec<u32>,
+    data: Vec<u32>,
+    // branches to the failure handler, their offset filled in by `finish`
+    fails: Vec<usize>,
+}
+
+#[allow(clippy::cast_sign_loss)]
+impl Program {
+    fn pc(&self) -> u32 {
+        4 * self.code.len() as u32
+    }
+
+    fn r(&mut self, (funct3, funct7): Op, rd: u32, rs1: u32, rs2: u32) {
+        self.code
+            .push(funct7 << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | OP);
+    }
+
+    fn i(&mut self, opcode: u32, funct3: u32, rd: u32, rs1: u32, imm: i32) {
+        let imm = imm as u32 & 0xfff;
+        self.code
+            .push(imm << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode);
+    }
+
+    fn imm(&mut self, (funct3, funct7): Op, rd: u32, rs1: u32, imm: i32) {
+        self.i(OP_IMM, funct3, rd, rs1, (funct7 << 5) as i32 | imm);
+    }
+
+    fn s(&mut self, funct3: u32, rs2: u32, rs1: u32, imm: i32) {
+        let imm = imm as u32 & 0xfff;
+        self.code.push(
+            (imm >> 5) << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | (imm & 0x1f) << 7 | STORE,
+        );
+    }
+
+    fn b(&mut self, funct3: u32, rs1: u32, rs2: u32, offset: i32) {
+        self.code
+            .push(branch_offset(offset) | rs2 << 20 | rs1 << 15 | funct3 << 12 | BRANCH);
+    }
+
+    fn u(&mut self, opcode: u32, rd: u32, imm: u32) {
+        self.code.push(imm << 12 | rd << 7 | opcode);
+    }
+
+    fn jal(&mut self, rd: u32, offset: i32) {
+        let o = offset as u32;
+        let imm =
+            (o >> 20 & 1) << 19 | (o >> 1 & 0x3ff) << 9 | (o >> 11 & 1) << 8 | (o >> 12 & 0xff);
+        self.code.push(imm << 12 | rd << 7 | JAL);
+    }
+
+    fn csrr(&mut self, rd: u32, csr: u32) {
+        self.code.push(csr << 20 | 2 << 12 | rd << 7 | SYSTEM);
+    }
+
+    fn csrw(&mut self, csr: u32, rs1: u32) {
+        self.code.push(csr << 20 | rs1 << 15 | 1 << 12 | SYSTEM);
+    }
+
+    // lui and addi, like the li pseudo-instruction
+    fn li(&mut self, rd: u32, value: u32) {
+        let lo = ((value & 0xfff) ^ 0x800).wrapping_sub(0x800);
+        self.u(LUI, rd, value.wrapping_sub(lo) >> 12);
+        self.i(OP_IMM, ADDI.0, rd, rd, lo as i32);
+    }
+
+    fn case(&mut self, n: u32) {
+        self.li(GP, n);
+    }
+
+    fn fail(&mut self) {
+        self.fails.push(self.code.len());
+        self.b(BNE, 0, GP, 0);
+    }
+
+    // fails unless `reg` holds `value`
+    fn expect(&mut self, reg: u32, value: u32) {
+        self.li(7, value);
+        self.fails.push(self.code.len());
+        self.b(BNE, reg, 7, 0);
+    }
+
+    // reports to tohost, passing when run into
+    fn finish(mut self) -> Self {
+        self.li(10, 1);
+        self.li(11, TOHOST);
+        self.s(SW, 10, 11, 0);
+        self.jal(0, 0);
+
+        let fail = self.pc();
+        for &i in &self.fails {
+            let offset = fail - 4 * i as u32;
+            assert!(offset < 1 << 12, "case too far from the failure handler");
+            self.code[i] |= branch_offset(offset as i32);
+        }
+
+        self.imm(SLLI, 10, GP, 1);
+        self.imm(ORI, 10, 10, 1);
+        self.li(11, TOHOST);
+        self.s(SW, 10, 11, 0);
+        self.jal(0, 0);
+        self
+    }
+}
+
+#[allow(clippy::cast_sign_loss)]
+fn branch_offset(offset: i32) -> u32 {
+    let o = offset as u32;
+    (o >> 12 & 1) << 31 | (o >> 5 & 0x3f) << 25 | (o >> 1 & 0xf) << 8 | (o >> 11 & 1) << 7
+}
+
+// the core running from a memory of its own, a second one holding the same
+// words on the data bus, both 256KiB from address 0 and mirrored above
+fn sim(m: bool, words: &[u32]) -> (Sim, usize, usize, usize) {
+    let mut memory = vec![0; 1 << 16];
+    memory[..words.len()].copy_from_slice(words);
+
+    let mut s = Sim::new();
+    s.begin_build();
+
+    let cpu = s.add_component(Rv32::new(m));
+    let text = s.add_component(Ram::new(&memory));
+    let data = s.add_component(Ram::new(&memory));
+
+    fn range(start: usize, end: usize) -> Vec<usize> {
+        (start..=end).collect()
+    }
+
+    s.connect_bulk(cpu, &range(3, 18), text, &range(1, 16));
+    s.connect_bulk(cpu, &range(33, 64), text, &range(33, 64));
+    s.connect_bulk(cpu, &range(67, 82), data, &range(1, 16));
+    s.connect_bulk(cpu, &range(97, 129), data, &range(33, 65));
+    s.connect_to_clk(cpu, 130);
+
+    s.finish_build();
+    (s, cpu, text, data)
+}
+
+// what the program ends up writing to the word at byte address `tohost`
+fn tohost(s: &mut Sim, data: usize, tohost: u32) -> u32 {
+    let word = (tohost as usize % (1 << 18)) / 4;
+    s.run_until(|s| s.component::<Ram>(data).unwrap().read(word) != 0);
+    s.component::<Ram>(data).unwrap().read(word)
+}
+
+fn run(program: &Program, m: bool) -> u32 {
+    let mut words = program.code.clone();
+    assert!(words.len() <= TOHOST as usize / 4, "program too long");
+    words.resize(DATA as usize / 4, 0);
+    words.extend(&program.data);
+
+    let (mut s, _, _, data) = sim(m, &words);
+    tohost(&mut s, data, TOHOST)
+}
+
+fn check(name: &str, program: &Program, m: bool) {
+    match run(program, m) {
+        1 => {}
+        0 => panic!("{}: no result", name),
+        v => panic!("{}: case {} failed", name, v >> 1),
+    }
+}
+
+// the cases of each instruction by name, (result, rs1, rs2 or the immediate)
+type Table<T> = &'static [(&'static str, Op, &'static [(u32, u32, T)])];
+
+// (result, rs1, rs2), as riscv-tests' TEST_RR_OP
+#[rustfmt::skip]
+const RR: Table<u32> = &[
+    ("add", ADD, &[
+        (0x0000_0002, 0x0000_0001, 0x0000_0001),
+        (0x0000_000a, 0x0000_0003, 0x0000_0007),
+        (0xffff_8000, 0x0000_0000, 0xffff_8000),
+        (0x8000_0000, 0x8000_0000, 0x0000_0000),
+        (0x7fff_8000, 0x8000_0000, 0xffff_8000),
+        (0x0000_7fff, 0x0000_0000, 0x0000_7fff),
+        (0x7fff_ffff, 0x7fff_ffff, 0x0000_0000),
+        (0x8000_7ffe, 0x7fff_ffff, 0x0000_7fff),
+        (0x8000_7fff, 0x8000_0000, 0x0000_7fff),
+        (0x7fff_7fff, 0x7fff_ffff, 0xffff_8000),
+        (0xffff_ffff, 0x0000_0000, 0xffff_ffff),
+        (0x0000_0000, 0xffff_ffff, 0x0000_0001),
+        (0xffff_fffe, 0xffff_ffff, 0xffff_ffff),
+        (0x8000_0000, 0x0000_0001, 0x7fff_ffff),
+    ]),
+    ("sub", SUB, &[
+        (0x0000_0000, 0x0000_0001, 0x0000_0001),
+        (0xffff_fffc, 0x0000_0003, 0x0000_0007),
+        (0x0000_8000, 0x0000_0000, 0xffff_8000),
+        (0x8000_0000, 0x8000_0000, 0x0000_0000),
+        (0x8000_8000, 0x8000_0000, 0xffff_8000),
+        (0xffff_8001, 0x0000_0000, 0x0000_7fff),
+        (0x7fff_ffff, 0x7fff_ffff, 0x0000_0000),
+        (0x7fff_8000, 0x7fff_ffff, 0x0000_7fff),
+        (0x7fff_8001, 0x8000_0000, 0x0000_7fff),
+        (0x8000_7fff, 0x7fff_ffff, 0xffff_8000),
+        (0x0000_0001, 0x0000_0000, 0xffff_ffff),
+        (0xffff_fffe, 0xffff_ffff, 0x0000_0001),
+        (0x0000_0000, 0xffff_ffff, 0xffff_ffff),
+        (0x8000_0002, 0x0000_0001, 0x7fff_ffff),
+    ]),
+    ("slt", SLT, &[
+        (0x0000_0000, 0x0000_0000, 0x0000_0000),
+        (0x0000_0000, 0x0000_0001, 0x0000_0001),
+        (0x0000_0001, 0x0000_0003, 0x0000_0007),
+        (0x0000_0000, 0x0000_0007, 0x0000_0003),
+        (0x0000_0000, 0x0000_0000, 0xffff_8000),
+        (0x0000_0001, 0x8000_0000, 0x0000_0000),
+        (0x0000_0001, 0x8000_0000, 0xffff_8000),
+        (0x0000_0001, 0x0000_0000, 0x0000_7fff),
+        (0x0000_0000, 0x7fff_ffff, 0x0000_0000),
+        (0x0000_0000, 0x7fff_ffff, 0x0000_7fff),
+        (0x0000_0001, 0x8000_0000, 0x0000_7fff),
+        (0x0000_0000, 0x7fff_ffff, 0xffff_8000),
+        (0x0000_0000, 0x0000_0000, 0xffff_ffff),
+        (0x0000_0001, 0xffff_ffff, 0x0000_0001),
+        (0x0000_0000, 0xffff_ffff, 0xffff_ffff),
+        (0x0000_0001, 0x0000_0001, 0x7fff_ffff),
+    ]),
+    ("sltu", SLTU, &[
+        (0x0000_0000, 0x0000_0000, 0x0000_0000),
+        (0x0000_0000, 0x0000_0001, 0x0000_0001),
+        (0x0000_0001, 0x0000_0003, 0x0000_0007),
+        (0x0000_0000, 0x0000_0007, 0x0000_0003),
+        (0x0000_0001, 0x0000_0000, 0xffff_8000),
+        (0x0000_0000, 0x8000_0000, 0x0000_0000),
+        (0x0000_0001, 0x8000_0000, 0xffff_8000),
+        (0x0000_0001, 0x0000_0000, 0x0000_7fff),
+        (0x0000_0000, 0x7fff_ffff, 0x0000_0000),
+        (0x0000_0000, 0x7fff_ffff, 0x0000_7fff),
+        (0x0000_0000, 0x8000_0000, 0x0000_7fff),
+        (0x0000_0001, 0x7fff_ffff, 0xffff_8000),
+        (0x0000_0001, 0x0000_0000, 0xffff_ffff),
+        (0x0000_0000, 0xffff_ffff, 0x0000_0001),
+        (0x0000_0000, 0xffff_ffff, 0xffff_ffff),
+        (0x0000_0001, 0x0000_0001, 0x7fff_ffff),
+    ]),
+    ("xor", XOR, &[
+        (0xf00f_f00f, 0xff00_ff00, 0x0f0f_0f0f),
+        (0xff00_ff00, 0x0ff0_0ff0, 0xf0f0_f0f0),
+        (0x0ff0_0ff0, 0x00ff_00ff, 0x0f0f_0f0f),
+        (0x00ff_00ff, 0xf00f_f00f, 0xf0f0_f0f0),
+    ]),
+    ("or", OR, &[
+        (0xff0f_ff0f, 0xff00_ff00, 0x0f0f_0f0f),
+        (0xfff0_fff0, 0x0ff0_0ff0, 0xf0f0_f0f0),
+        (0x0fff_0fff, 0x00ff_00ff, 0x0f0f_0f0f),
+        (0xf0ff_f0ff, 0xf00f_f00f, 0xf0f0_f0f0),
+    ]),
+    ("and", AND, &[
+        (0x0f00_0f00, 0xff00_ff00, 0x0f0f_0f0f),
+        (0x00f0_00f0, 0x0ff0_0ff0, 0xf0f0_f0f0),
+        (0x000f_000f, 0x00ff_00ff, 0x0f0f_0f0f),
+        (0xf000_f000, 0xf00f_f00f, 0xf0f0_f0f0),
+    ]),
+    ("sll", SLL, &[
+        (0x0000_0001, 0x0000_0001, 0x0000_0000),
+        (0x0000_0002, 0x0000_0001, 0x0000_0001),
+        (0x0000_0080, 0x0000_0001, 0x0000_0007),
+        (0x0000_4000, 0x0000_0001, 0x0000_000e),
+        (0x8000_0000, 0x0000_0001, 0x0000_001f),
+        (0xffff_ffff, 0xffff_ffff, 0x0000_0000),
+        (0xffff_fffe, 0xffff_ffff, 0x0000_0001),
+        (0xffff_ff80, 0xffff_ffff, 0x0000_0007),
+        (0xffff_c000, 0xffff_ffff, 0x0000_000e),
+        (0x8000_0000, 0xffff_ffff, 0x0000_001f),
+        (0x2121_2121, 0x2121_2121, 0x0000_0000),
+        (0x4242_4242, 0x2121_2121, 0x0000_0001),
+        (0x9090_9080, 0x2121_2121, 0x0000_0007),
+        (0x4848_4000, 0x2121_2121, 0x0000_000e),
+        (0x8000_0000, 0x2121_2121, 0x0000_001f),
+        (0x8000_0000, 0x8000_0000, 0x0000_0000),
+        (0x0000_0000, 0x8000_0000, 0x0000_0001),
+        (0x0000_0000, 0x8000_0000, 0x0000_0007),
+        (0x0000_0000, 0x8000_0000, 0x0000_000e),
+        (0x0000_0000, 0x8000_0000, 0x0000_001f),
+        (0x2121_2121, 0x2121_2121, 0xffff_ffc0),
+        (0x4242_4242, 0x2121_2121, 0xffff_ffc1),
+        (0x9090_9080, 0x2121_2121, 0xffff_ffc7),
+        (0x4848_4000, 0x2121_2121, 0xffff_ffce),
+        (0x8000_0000, 0x2121_2121, 0xffff_ffff),
+    ]),
+    ("srl", SRL, &[
+        (0x0000_0001, 0x0000_0001, 0x0000_0000),
+        (0x0000_0000, 0x0000_0001, 0x0000_0001),
+        (0x0000_0000, 0x0000_0001, 0x0000_0007),
+        (0x0000_0000, 0x0000_0001, 0x0000_000e),
+        (0x0000_0000, 0x0000_0001, 0x0000_001f),
+        (0xffff_ffff, 0xffff_ffff, 0x0000_0000),
+        (0x7fff_ffff, 0xffff_ffff, 0x0000_0001),
+        (0x01ff_ffff, 0xffff_ffff, 0x0000_0007),
+        (0x0003_ffff, 0xffff_ffff, 0x0000_000e),
+        (0x0000_0001, 0xffff_ffff, 0x0000_001f),
+        (0x2121_2121, 0x2121_2121, 0x0000_0000),
+        (0x1090_9090, 0x2121_2121, 0x0000_0001),
+        (0x0042_4242, 0x2121_2121, 0x0000_0007),
+        (0x0000_8484, 0x2121_2121, 0x0000_000e),
+        (0x0000_0000, 0x2121_2121, 0x0000_001f),
+        (0x8000_0000, 0x8000_0000, 0x0000_0000),
+        (0x4000_0000, 0x8000_0000, 0x0000_0001),
+        (0x0100_0000, 0x8000_0000, 0x0000_0007),
+        (0x0002_0000, 0x8000_0000, 0x0000_000e),
+        (0x0000_0001, 0x8000_0000, 0x0000_001f),
+        (0x2121_2121, 0x2121_2121, 0xffff_ffc0),
+        (0x1090_9090, 0x2121_2121, 0xffff_ffc1),
+        (0x0042_4242, 0x2121_2121, 0xffff_ffc7),
+        (0x0000_8484, 0x2121_2121, 0xffff_ffce),
+        (0x0000_0000, 0x2121_2121, 0xffff_ffff),
+    ]),
+    ("sra", SRA, &[
+        (0x0000_0001, 0x0000_0001, 0x0000_0000),
+        (0x0000_0000, 0x0000_0001, 0x0000_0001),
+        (0x0000_0000, 0x0000_0001, 0x0000_0007),
+        (0x0000_0000, 0x0000_0001, 0x0000_000e),
+        (0x0000_0000, 0x0000_0001, 0x0000_001f),
+        (0xffff_ffff, 0xffff_ffff, 0x0000_0000),
+        (0xffff_ffff, 0xffff_ffff, 0x0000_0001),
+        (0xffff_ffff, 0xffff_ffff, 0x0000_0007),
+        (0xffff_ffff, 0xffff_ffff, 0x0000_000e),
+        (0xffff_ffff, 0xffff_ffff, 0x0000_001f),
+        (0x2121_2121, 0x2121_2121, 0x0000_0000),
+        (0x1090_9090, 0x2121_2121, 0x0000_0001),
+        (0x0042_4242, 0x2121_2121, 0x0000_0007),
+        (0x0000_8484, 0x2121_2121, 0x0000_000e),
+        (0x0000_0000, 0x2121_2121, 0x0000_001f),
+        (0x8000_0000, 0x8000_0000, 0x0000_0000),
+        (0xc000_0000, 0x8000_0000, 0x0000_0001),
+        (0xff00_0000, 0x8000_0000, 0x0000_0007),
+        (0xfffe_0000, 0x8000_0000, 0x0000_000e),
+        (0xffff_ffff, 0x8000_0000, 0x0000_001f),
+        (0x2121_2121, 0x2121_2121, 0xffff_ffc0),
+        (0x1090_9090, 0x2121_2121, 0xffff_ffc1),
+        (0x0042_4242, 0x2121_2121, 0xffff_ffc7),
+        (0x0000_8484, 0x2121_2121, 0xffff_ffce),
+        (0x0000_0000, 0x2121_2121, 0xffff_ffff),
+    ]),
+];
+
+// the M extension ones, as in rv32um
+#[rustfmt::skip]
+const RR_M: Table<u32> = &[
+    ("mul", MUL, &[
+        (0x0000_0000, 0x0000_0000, 0x0000_0000),
+        (0x0000_0001, 0x0000_0001, 0x0000_0001),
+        (0x0000_0015, 0x0000_0003, 0x0000_0007),
+        (0x0000_0000, 0x0000_0000, 0xffff_8000),
+        (0x0000_0000, 0x8000_0000, 0x0000_0000),
+        (0x0000_0000, 0x8000_0000, 0xffff_8000),
+        (0x0000_ff7f, 0xaaaa_aaab, 0x0002_fe7d),
+        (0x0000_ff7f, 0x0002_fe7d, 0xaaaa_aaab),
+        (0x0000_0000, 0xff00_0000, 0xff00_0000),
+        (0x0000_0001, 0xffff_ffff, 0xffff_ffff),
+        (0xffff_ffff, 0xffff_ffff, 0x0000_0001),
+        (0xffff_ffff, 0x0000_0001, 0xffff_ffff),
+    ]),
+    ("mulh", MULH, &[
+        (0x0000_0000, 0x0000_0000, 0x0000_0000),
+        (0x0000_0000, 0x0000_0001, 0x0000_0001),
+        (0x0000_0000, 0x0000_0003, 0x0000_0007),
+        (0x0000_0000, 0x0000_0000, 0xffff_8000),
+        (0x0000_0000, 0x8000_0000, 0x0000_0000),
+        (0x0000_4000, 0x8000_0000, 0xffff_8000),
+        (0xffff_0081, 0xaaaa_aaab, 0x0002_fe7d),
+        (0xffff_0081, 0x0002_fe7d, 0xaaaa_aaab),
+        (0x0001_0000, 0xff00_0000, 0xff00_0000),
+        (0x0000_0000, 0xffff_ffff, 0xffff_ffff),
+        (0xffff_ffff, 0xffff_ffff, 0x0000_0001),
+        (0xffff_ffff, 0x0000_0001, 0xffff_ffff),
+    ]),
+    ("mulhsu", MULHSU, &[
+        (0x0000_0000, 0x0000_0000, 0x0000_0000),
+        (0x0000_0000, 0x0000_0001, 0x0000_0001),
+        (0x0000_0000, 0x0000_0003, 0x0000_0007),
+        (0x0000_0000, 0x0000_0000, 0xffff_8000),
+        (0x0000_0000, 0x8000_0000, 0x0000_0000),
+        (0x8000_4000, 0x8000_0000, 0xffff_8000),
+        (0xffff_0081, 0xaaaa_aaab, 0x0002_fe7d),
+        (0x0001_fefe, 0x0002_fe7d, 0xaaaa_aaab),
+        (0xff01_0000, 0xff00_0000, 0xff00_0000),
+        (0xffff_ffff, 0xffff_ffff, 0xffff_ffff),
+        (0xffff_ffff, 0xffff_ffff, 0x0000_0001),
+        (0x0000_0000, 0x0000_0001, 0xffff_ffff),
+    ]),
+    ("mulhu", MULHU, &[
+        (0x0000_0000, 0x0000_0000, 0x0000_0000),
+        (0x0000_0000, 0x0000_0001, 0x0000_0001),
+        (0x0000_0000, 0x0000_0003, 0x0000_0007),
+        (0x0000_0000, 0x0000_0000, 0xffff_8000),
+        (0x0000_0000, 0x8000_0000, 0x0000_0000),
+        (0x7fff_c000, 0x8000_0000, 0xffff_8000),
+        (0x0001_fefe, 0xaaaa_aaab, 0x0002_fe7d),
+        (0x0001_fefe, 0x0002_fe7d, 0xaaaa_aaab),
+        (0xfe01_0000, 0xff00_0000, 0xff00_0000),
+        (0xffff_fffe, 0xffff_ffff, 0xffff_ffff),
+        (0x0000_0000, 0xffff_ffff, 0x0000_0001),
+        (0x0000_0000, 0x0000_0001, 0xffff_ffff),
+    ]),
+    ("div", DIV, &[
+        (0x0000_0003, 0x0000_0014, 0x0000_0006),
+        (0xffff_fffd, 0xffff_ffec, 0x0000_0006),
+        (0xffff_fffd, 0x0000_0014, 0xffff_fffa),
+        (0x0000_0003, 0xffff_ffec, 0xffff_fffa),
+        (0x8000_0000, 0x8000_0000, 0x0000_0001),
+        (0x8000_0000, 0x8000_0000, 0xffff_ffff),
+        (0xffff_ffff, 0x8000_0000, 0x0000_0000),
+        (0xffff_ffff, 0x0000_0001, 0x0000_0000),
+        (0xffff_ffff, 0x0000_0000, 0x0000_0000),
+    ]),
+    ("divu", DIVU, &[
+        (0x0000_0003, 0x0000_0014, 0x0000_0006),
+        (0x2aaa_aaa7, 0xffff_ffec, 0x0000_0006),
+        (0x0000_0000, 0x0000_0014, 0xffff_fffa),
+        (0x0000_0000, 0xffff_ffec, 0xffff_fffa),
+        (0x8000_0000, 0x8000_0000, 0x0000_0001),
+        (0x0000_0000, 0x8000_0000, 0xffff_ffff),
+        (0xffff_ffff, 0x8000_0000, 0x0000_0000),
+        (0xffff_ffff, 0x0000_0001, 0x0000_0000),
+        (0xffff_ffff, 0x0000_0000, 0x0000_0000),
+    ]),
+    ("rem", REM, &[
+        (0x0000_0002, 0x0000_0014, 0x0000_0006),
+        (0xffff_fffe, 0xffff_ffec, 0x0000_0006),
+        (0x0000_0002, 0x0000_0014, 0xffff_fffa),
+        (0xffff_fffe, 0xffff_ffec, 0xffff_fffa),
+        (0x0000_0000, 0x8000_0000, 0x0000_0001),
+        (0x0000_0000, 0x8000_0000, 0xffff_ffff),
+        (0x8000_0000, 0x8000_0000, 0x0000_0000),
+        (0x0000_0001, 0x0000_0001, 0x0000_0000),
+        (0x0000_0000, 0x0000_0000, 0x0000_0000),
+    ]),
+    ("remu", REMU, &[
+        (0x0000_0002, 0x0000_0014, 0x0000_0006),
+        (0x0000_0002, 0xffff_ffec, 0x0000_0006),
+        (0x0000_0014, 0x0000_0014, 0xffff_fffa),
+        (0xffff_ffec, 0xffff_ffec, 0xffff_fffa),
+        (0x0000_0000, 0x8000_0000, 0x0000_0001),
+        (0x8000_0000, 0x8000_0000, 0xffff_ffff),
+        (0x8000_0000, 0x8000_0000, 0x0000_0000),
+        (0x0000_0001, 0x0000_0001, 0x0000_0000),
+        (0x0000_0000, 0x0000_0000, 0x0000_0000),
+    ]),
+];
+
+// (result, rs1, imm), as TEST_IMM_OP
+#[rustfmt::skip]
+const IMM: Table<i32> = &[
+    ("addi", ADDI, &[
+        (0x0000_0000, 0x0000_0000, 0),
+        (0x0000_0002, 0x0000_0001, 1),
+        (0x0000_000a, 0x0000_0003, 7),
+        (0xffff_f800, 0x0000_0000, -2048),
+        (0x8000_0000, 0x8000_0000, 0),
+        (0x7fff_f800, 0x8000_0000, -2048),
+        (0x0000_07ff, 0x0000_0000, 2047),
+        (0x7fff_ffff, 0x7fff_ffff, 0),
+        (0x8000_07fe, 0x7fff_ffff, 2047),
+        (0x8000_07ff, 0x8000_0000, 2047),
+        (0x7fff_f7ff, 0x7fff_ffff, -2048),
+        (0xffff_ffff, 0x0000_0000, -1),
+        (0x0000_0000, 0xffff_ffff, 1),
+        (0xffff_fffe, 0xffff_ffff, -1),
+        (0x00ff_080e, 0x00ff_00ff, 1807),
+        (0xff00_fe0f, 0xff00_ff00, -241),
+    ]),
+    ("slti", SLTI, &[
+        (0x0000_0000, 0x0000_0000, 0),
+        (0x0000_0000, 0x0000_0001, 1),
+        (0x0000_0001, 0x0000_0003, 7),
+        (0x0000_0000, 0x0000_0000, -2048),
+        (0x0000_0001, 0x8000_0000, 0),
+        (0x0000_0001, 0x8000_0000, -2048),
+        (0x0000_0001, 0x0000_0000, 2047),
+        (0x0000_0000, 0x7fff_ffff, 0),
+        (0x0000_0000, 0x7fff_ffff, 2047),
+        (0x0000_0001, 0x8000_0000, 2047),
+        (0x0000_0000, 0x7fff_ffff, -2048),
+        (0x0000_0000, 0x0000_0000, -1),
+        (0x0000_0001, 0xffff_ffff, 1),
+        (0x0000_0000, 0xffff_ffff, -1),
+        (0x0000_0000, 0x00ff_00ff, 1807),
+        (0x0000_0001, 0xff00_ff00, -241),
+    ]),
+    ("sltiu", SLTIU, &[
+        (0x0000_0000, 0x0000_0000, 0),
+        (0x0000_0000, 0x0000_0001, 1),
+        (0x0000_0001, 0x0000_0003, 7),
+        (0x0000_0001, 0x0000_0000, -2048),
+        (0x0000_0000, 0x8000_0000, 0),
+        (0x0000_0001, 0x8000_0000, -2048),
+        (0x0000_0001, 0x0000_0000, 2047),
+        (0x0000_0000, 0x7fff_ffff, 0),
+        (0x0000_0000, 0x7fff_ffff, 2047),
+        (0x0000_0000, 0x8000_0000, 2047),
+        (0x0000_0001, 0x7fff_ffff, -2048),
+        (0x0000_0001, 0x0000_0000, -1),
+        (0x0000_0000, 0xffff_ffff, 1),
+        (0x0000_0000, 0xffff_ffff, -1),
+        (0x0000_0000, 0x00ff_00ff, 1807),
+        (0x0000_0001, 0xff00_ff00, -241),
+    ]),
+    ("xori", XORI, &[
+        (0x0000_0000, 0x0000_0000, 0),
+        (0x0000_0000, 0x0000_0001, 1),
+        (0x0000_0004, 0x0000_0003, 7),
+        (0xffff_f800, 0x0000_0000, -2048),
+        (0x8000_0000, 0x8000_0000, 0),
+        (0x7fff_f800, 0x8000_0000, -2048),
+        (0x0000_07ff, 0x0000_0000, 2047),
+        (0x7fff_ffff, 0x7fff_ffff, 0),
+        (0x7fff_f800, 0x7fff_ffff, 2047),
+        (0x8000_07ff, 0x8000_0000, 2047),
+        (0x8000_07ff, 0x7fff_ffff, -2048),
+        (0xffff_ffff, 0x0000_0000, -1),
+        (0xffff_fffe, 0xffff_ffff, 1),
+        (0x0000_0000, 0xffff_ffff, -1),
+        (0x00ff_07f0, 0x00ff_00ff, 1807),
+        (0x00ff_000f, 0xff00_ff00, -241),
+    ]),
+    ("ori", ORI, &[
+        (0x0000_0000, 0x0000_0000, 0),
+        (0x0000_0001, 0x0000_0001, 1),
+        (0x0000_0007, 0x0000_0003, 7),
+        (0xffff_f800, 0x0000_0000, -2048),
+        (0x8000_0000, 0x8000_0000, 0),
+        (0xffff_f800, 0x8000_0000, -2048),
+        (0x0000_07ff, 0x0000_0000, 2047),
+        (0x7fff_ffff, 0x7fff_ffff, 0),
+        (0x7fff_ffff, 0x7fff_ffff, 2047),
+        (0x8000_07ff, 0x8000_0000, 2047),
+        (0xffff_ffff, 0x7fff_ffff, -2048),
+        (0xffff_ffff, 0x0000_0000, -1),
+        (0xffff_ffff, 0xffff_ffff, 1),
+        (0xffff_ffff, 0xffff_ffff, -1),
+        (0x00ff_07ff, 0x00ff_00ff, 1807),
+        (0xffff_ff0f, 0xff00_ff00, -241),
+    ]),
+    ("andi", ANDI, &[
+        (0x0000_0000, 0x0000_0000, 0),
+        (0x0000_0001, 0x0000_0001, 1),
+        (0x0000_0003, 0x0000_0003, 7),
+        (0x0000_0000, 0x0000_0000, -2048),
+        (0x0000_0000, 0x8000_0000, 0),
+        (0x8000_0000, 0x8000_0000, -2048),
+        (0x0000_0000, 0x0000_0000, 2047),
+        (0x0000_0000, 0x7fff_ffff, 0),
+        (0x0000_07ff, 0x7fff_ffff, 2047),
+        (0x0000_0000, 0x8000_0000, 2047),
+        (0x7fff_f800, 0x7fff_ffff, -2048),
+        (0x0000_0000, 0x0000_0000, -1),
+        (0x0000_0001, 0xffff_ffff, 1),
+        (0xffff_ffff, 0xffff_ffff, -1),
+        (0x0000_000f, 0x00ff_00ff, 1807),
+        (0xff00_ff00, 0xff00_ff00, -241),
+    ]),
+    ("slli", SLLI, &[
+        (0x0000_0001, 0x0000_0001, 0),
+        (0x0000_0002, 0x0000_0001, 1),
+        (0x0000_0080, 0x0000_0001, 7),
+        (0x0000_4000, 0x0000_0001, 14),
+        (0x8000_0000, 0x0000_0001, 31),
+        (0xffff_ffff, 0xffff_ffff, 0),
+        (0xffff_fffe, 0xffff_ffff, 1),
+        (0xffff_ff80, 0xffff_ffff, 7),
+        (0xffff_c000, 0xffff_ffff, 14),
+        (0x8000_0000, 0xffff_ffff, 31),
+        (0x2121_2121, 0x2121_2121, 0),
+        (0x4242_4242, 0x2121_2121, 1),
+        (0x9090_9080, 0x2121_2121, 7),
+        (0x4848_4000, 0x2121_2121, 14),
+        (0x8000_0000, 0x2121_2121, 31),
+        (0x8000_0000, 0x8000_0000, 0),
+        (0x0000_0000, 0x8000_0000, 1),
+        (0x0000_0000, 0x8000_0000, 7),
+        (0x0000_0000, 0x8000_0000, 14),
+        (0x0000_0000, 0x8000_0000, 31),
+    ]),
+    ("srli", SRLI, &[
+        (0x0000_0001, 0x0000_0001, 0),
+        (0x0000_0000, 0x0000_0001, 1),
+        (0x0000_0000, 0x0000_0001, 7),
+        (0x0000_0000, 0x0000_0001, 14),
+        (0x0000_0000, 0x0000_0001, 31),
+        (0xffff_ffff, 0xffff_ffff, 0),
+        (0x7fff_ffff, 0xffff_ffff, 1),
+        (0x01ff_ffff, 0xffff_ffff, 7),
+        (0x0003_ffff, 0xffff_ffff, 14),
+        (0x0000_0001, 0xffff_ffff, 31),
+        (0x2121_2121, 0x2121_2121, 0),
+        (0x1090_9090, 0x2121_2121, 1),
+        (0x0042_4242, 0x2121_2121, 7),
+        (0x0000_8484, 0x2121_2121, 14),
+        (0x0000_0000, 0x2121_2121, 31),
+        (0x8000_0000, 0x8000_0000, 0),
+        (0x4000_0000, 0x8000_0000, 1),
+        (0x0100_0000, 0x8000_0000, 7),
+        (0x0002_0000, 0x8000_0000, 14),
+        (0x0000_0001, 0x8000_0000, 31),
+    ]),
+    ("srai", SRAI, &[
+        (0x0000_0001, 0x0000_0001, 0),
+        (0x0000_0000, 0x0000_0001, 1),
+        (0x0000_0000, 0x0000_0001, 7),
+        (0x0000_0000, 0x0000_0001, 14),
+        (0x0000_0000, 0x0000_0001, 31),
+        (0xffff_ffff, 0xffff_ffff, 0),
+        (0xffff_ffff, 0xffff_ffff, 1),
+        (0xffff_ffff, 0xffff_ffff, 7),
+        (0xffff_ffff, 0xffff_ffff, 14),
+        (0xffff_ffff, 0xffff_ffff, 31),
+        (0x2121_2121, 0x2121_2121, 0),
+        (0x1090_9090, 0x2121_2121, 1),
+        (0x0042_4242, 0x2121_2121, 7),
+        (0x0000_8484, 0x2121_2121, 14),
+        (0x0000_0000, 0x2121_2121, 31),
+        (0x8000_0000, 0x8000_0000, 0),
+        (0xc000_0000, 0x8000_0000, 1),
+        (0xff00_0000, 0x8000_0000, 7),
+        (0xfffe_0000, 0x8000_0000, 14),
+        (0xffff_ffff, 0x8000_0000, 31),
+    ]),
+];
+
+fn register_ops(table: Table<u32>, m: bool) {
+    for &(name, op, cases) in table {
+        let mut p = Program::default();
+        let mut n = 2;
+        for &(result, a, b) in cases {
+            p.case(n);
+            p.li(1, a);
+            p.li(2, b);
+            p.r(op, 14, 1, 2);
+            p.expect(14, result);
+            n += 1;
+        }
+
+        // the destination being a source, and x0
+        let (result, a, b) = cases[0];
+        p.case(n);
+        p.li(1, a);
+        p.li(2, b);
+        p.r(op, 1, 1, 2);
+        p.expect(1, result);
+        p.case(n + 1);
+        p.li(1, a);
+        p.li(2, b);
+        p.r(op, 2, 1, 2);
+        p.expect(2, result);
+        p.case(n + 2);
+        p.r(op, 0, 1, 2);
+        p.expect(0, 0);
+
+        check(name, &p.finish(), m);
+    }
+}
+
+#[test]
+fn rv32ui_register() {
+    register_ops(RR, false);
+    register_ops(RR, true);
+}
+
+#[test]
+fn rv32ui_immediate() {
+    for &(name, op, cases) in IMM {
+        let mut p = Program::default();
+        let mut n = 2;
+        for &(result, a, imm) in cases {
+            p.case(n);
+            p.li(1, a);
+            p.imm(op, 14, 1, imm);
+            p.expect(14, result);
+            n += 1;
+        }
+
+        let (result, a, imm) = cases[0];
+        p.case(n);
+        p.li(1, a);
+        p.imm(op, 1, 1, imm);
+        p.expect(1, result);
+        p.case(n + 1);
+        p.imm(op, 0, 1, imm);
+        p.expect(0, 0);
+
+        check(name, &p.finish(), false);
+    }
+}
+
+#[test]
+fn rv32ui_upper() {
+    let mut p = Program::default();
+    for (n, &(imm, result)) in [
+        (0, 0),
+        (0xfffff, 0xffff_f000),
+        (0x7ffff, 0x7fff_f000),
+        (0x80000, 0x8000_0000),
+    ]
+    .iter()
+    .enumerate()
+    {
+        p.case(n as u32 + 2);
+        p.u(LUI, 1, imm);
+        p.expect(1, result);
+    }
+    p.case(6);
+    p.u(LUI, 0, 0x80000);
+    p.expect(0, 0);
+    check("lui", &p.finish(), false);
+
+    let mut p = Program::default();
+    p.case(2);
+    let pc = p.pc();
+    p.u(AUIPC, 10, 1);
+    p.expect(10, pc + 0x1000);
+    p.case(3);
+    let pc = p.pc();
+    p.u(AUIPC, 10, 0xfffff);
+    p.expect(10, pc.wrapping_sub(0x1000));
+    check("auipc", &p.finish(), false);
+}
+
+#[test]
+fn rv32ui_jumps() {
+    let mut p = Program::default();
+    p.case(2);
+    let pc = p.pc();
+    p.jal(4, 12);
+    p.fail();
+    p.fail();
+    p.expect(4, pc + 4);
+
+    // forwards and back again
+    p.case(3);
+    p.li(6, 0);
+    p.jal(0, 12);
+    p.fail();
+    p.jal(0, 12);
+    p.i(OP_IMM, ADDI.0, 6, 0, 1);
+    p.jal(0, -8);
+    p.expect(6, 1);
+    check("jal", &p.finish(), false);
+
+    let mut p = Program::default();
+    p.case(2);
+    let pc = p.pc();
+    p.u(AUIPC, 5, 0);
+    p.i(JALR, 0, 4, 5, 12);
+    p.fail();
+    p.expect(4, pc + 8);
+
+    // the lowest bit of the target cleared
+    p.case(3);
+    let pc = p.pc();
+    p.u(AUIPC, 5, 0);
+    p.i(OP_IMM, ADDI.0, 5, 5, 17);
+    p.i(JALR, 0, 4, 5, 0);
+    p.fail();
+    p.expect(4, pc + 12);
+
+    // linking into the base register
+    p.case(4);
+    let pc = p.pc();
+    p.u(AUIPC, 5, 0);
+    p.i(JALR, 0, 5, 5, 12);
+    p.fail();
+    p.expect(5, pc + 8);
+    check("jalr", &p.finish(), false);
+}
+
+#[test]
+fn rv32ui_branches() {
+    const M: u32 = u32::MAX;
+
+    // (funct3, taken, not taken)
+    #[allow(clippy::type_complexity)]
+    let branches: &[(&str, u32, &[(u32, u32)], &[(u32, u32)])] = &[
+        (
+            "beq",
+            BEQ,
+            &[(0, 0), (1, 1), (M, M)],
+            &[(0, 1), (1, 0), (M, 1), (1, M)],
+        ),
+        (
+            "bne",
+            BNE,
+            &[(0, 1), (1, 0), (M, 1), (1, M)],
+            &[(0, 0), (1, 1), (M, M)],
+        ),
+        (
+            "blt",
+            BLT,
+            &[(0, 1), (M, 1), (M - 1, M)],
+            &[(1, 0), (1, M), (M, M - 1), (1, M - 1)],
+        ),
+        (
+            "bge",
+            BGE,
+            &[(0, 0), (1, 1), (M, M), (1, 0), (1, M), (M, M - 1)],
+            &[(0, 1), (M, 1), (M - 1, M), (M - 1, 1)],
+        ),
+        (
+            "bltu",
+            BLTU,
+            &[(0, 1), (M - 1, M), (0, M)],
+            &[(1, 0), (M, M - 1), (M, 0), (0x8000_0000, 0x7fff_ffff)],
+        ),
+        (
+            "bgeu",
+            BGEU,
+            &[(0, 0), (1, 1), (M, M), (1, 0), (M, M - 1), (M, 0)],
+            &[(0, 1), (M - 1, M), (0, M), (0x7fff_ffff, 0x8000_0000)],
+        ),
+    ];
+
+    for &(name, funct3, taken, not_taken) in branches {
+        let mut p = Program::default();
+        let mut n = 2;
+
+        // as TEST_BR2_OP_TAKEN and TEST_BR2_OP_NOTTAKEN, forwards then back
+        for &(a, b) in taken {
+            p.case(n);
+            p.li(1, a);
+            p.li(2, b);
+            p.b(funct3, 1, 2, 12);
+            p.fail();
+            p.b(BNE, 0, GP, 12);
+            p.b(funct3, 1, 2, -4);
+            p.fail();
+            n += 1;
+        }
+        for &(a, b) in not_taken {
+            p.case(n);
+            p.li(1, a);
+            p.li(2, b);
+            p.b(funct3, 1, 2, 8);
+            p.b(BNE, 0, GP, 8);
+            p.fail();
+            p.b(funct3, 1, 2, -4);
+            n += 1;
+        }
+
+        check(name, &p.finish(), false);
+    }
+}
+
+#[test]
+fn rv32ui_loads() {
+    // (funct3, data, (result, offset, base)), as TEST_LD_OP
+    #[allow(clippy::type_complexity)]
+    let loads: &[(&str, u32, &[u32], &[(u32, i32, u32)])] = &[
+        (
+            "lb",
+            LB,
+            &[0x0ff0_00ff],
+            &[
+                (0xffff_ffff, 0, 0),
+                (0x0000_0000, 1, 0),
+                (0xffff_fff0, 2, 0),
+                (0x0000_000f, 3, 0),
+                (0xffff_ffff, -3, 3),
+                (0x0000_0000, -2, 3),
+                (0xffff_fff0, -1, 3),
+                (0x0000_000f, 0, 3),
+            ],
+        ),
+        (
+            "lbu",
+            LBU,
+            &[0x0ff0_00ff],
+            &[
+                (0x0000_00ff, 0, 0),
+                (0x0000_0000, 1, 0),
+                (0x0000_00f0, 2, 0),
+                (0x0000_000f, 3, 0),
+                (0x0000_00ff, -3, 3),
+                (0x0000_0000, -2, 3),
+                (0x0000_00f0, -1, 3),
+                (0x0000_000f, 0, 3),
+            ],
+        ),
+        (
+            "lh",
+            LH,
+            &[0xff00_00ff, 0xf00f_0ff0],
+            &[
+                (0x0000_00ff, 0, 0),
+                (0xffff_ff00, 2, 0),
+                (0x0000_0ff0, 4, 0),
+                (0xffff_f00f, 6, 0),
+                (0x0000_00ff, -6, 6),
+                (0xffff_ff00, -4, 6),
+                (0x0000_0ff0, -2, 6),
+                (0xffff_f00f, 0, 6),
+            ],
+        ),
+        (
+            "lhu",
+            LHU,
+            &[0xff00_00ff, 0xf00f_0ff0],
+            &[
+                (0x0000_00ff, 0, 0),
+                (0x0000_ff00, 2, 0),
+                (0x0000_0ff0, 4, 0),
+                (0x0000_f00f, 6, 0),
+                (0x0000_00ff, -6, 6),
+                (0x0000_ff00, -4, 6),
+                (0x0000_0ff0, -2, 6),
+                (0x0000_f00f, 0, 6),
+            ],
+        ),
+        (
+            "lw",
+            LW,
+            &[0x00ff_00ff, 0xff00_ff00, 0x0ff0_0ff0, 0xf00f_f00f],
+            &[
+                (0x00ff_00ff, 0, 0),
+                (0xff00_ff00, 4, 0),
+                (0x0ff0_0ff0, 8, 0),
+                (0xf00f_f00f, 12, 0),
+                (0x00ff_00ff, -12, 12),
+                (0xff00_ff00, -8, 12),
+                (0x0ff0_0ff0, -4, 12),
+                (0xf00f_f00f, 0, 12),
+            ],
+        ),
+    ];
+
+    for &(name, funct3, data, cases) in loads {
+        let mut p = Program {
+            data: data.to_vec(),
+            ..Program::default()
+        };
+        for (n, &(result, offset, base)) in cases.iter().enumerate() {
+            p.case(n as u32 + 2);
+            p.li(15, DATA + base);
+            p.i(LOAD, funct3, 14, 15, offset);
+            p.expect(14, result);
+        }
+
+        check(name, &p.finish(), false);
+    }
+}
+
+#[test]
+fn rv32ui_stores() {
+    // (store, load, (value, loaded, offset, base), words after), as
+    // TEST_ST_OP, the stores going into words of 0xefefefef
+    #[allow(clippy::type_complexity)]
+    let stores: &[(&str, u32, u32, &[(u32, u32, i32, u32)], &[u32])] = &[
+        (
+            "sb",
+            SB,
+            LB,
+            &[
+                (0xffff_ffaa, 0xffff_ffaa, 0, 0),
+                (0x0000_0000, 0x0000_0000, 1, 0),
+                (0xffff_efa0, 0xffff_ffa0, 2, 0),
+                (0x0000_000a, 0x0000_000a, 3, 0),
+                (0x0000_0012, 0x0000_0012, -1, 8),
+                (0x0000_0034, 0x0000_0034, -4, 8),
+            ],
+            &[0x0aa0_00aa, 0x12ef_ef34, 0xefef_efef],
+        ),
+        (
+            "sh",
+            SH,
+            LH,
+            &[
+                (0x0000_00aa, 0x0000_00aa, 0, 0),
+                (0xffff_aa00, 0xffff_aa00, 2, 0),
+                (0xbeef_0aa0, 0x0000_0aa0, 4, 0),
+                (0xffff_a00a, 0xffff_a00a, 6, 0),
+                (0x0000_1234, 0x0000_1234, -2, 12),
+            ],
+            &[0xaa00_00aa, 0xa00a_0aa0, 0x1234_efef],
+        ),
+        (
+            "sw",
+            SW,
+            LW,
+            &[
+                (0x00aa_00aa, 0x00aa_00aa, 0, 0),
+                (0xaa00_aa00, 0xaa00_aa00, 4, 0),
+                (0x0aa0_0aa0, 0x0aa0_0aa0, -4, 12),
+                (0xa00a_a00a, 0xa00a_a00a, 0, 12),
+            ],
+            &[0x00aa_00aa, 0xaa00_aa00, 0x0aa0_0aa0, 0xa00a_a00a],
+        ),
+    ];
+
+    for &(name, store, load, cases, words) in stores {
+        let mut p = Program {
+            data: vec![0xefef_efef; 4],
+            ..Program::default()
+        };
+        let mut n = 2;
+        for &(value, loaded, offset, base) in cases {
+            p.case(n);
+            p.li(1, value);
+            p.li(2, DATA + base);
+            p.s(store, 1, 2, offset);
+            p.i(LOAD, load, 14, 2, offset);
+            p.expect(14, loaded);
+            n += 1;
+        }
+
+        // the rest of each word left as it was
+        p.li(2, DATA);
+        for (i, &word) in words.iter().enumerate() {
+            p.case(n);
+            p.i(LOAD, LW, 14, 2, 4 * i as i32);
+            p.expect(14, word);
+            n += 1;
+        }
+
+        check(name, &p.finish(), false);
+    }
+}
+
+#[test]
+fn rv32um() {
+    register_ops(RR_M, true);
+}
+
+#[test]
+fn rv32um_illegal_without_m() {
+    let mut p = Program::default();
+    p.case(2);
+    p.li(1, 3);
+    p.li(2, 7);
+    let pc = p.pc();
+    p.u(AUIPC, 5, 0);
+    p.i(OP_IMM, ADDI.0, 5, 5, 20);
+    p.csrw(MTVEC, 5);
+    p.r(MUL, 14, 1, 2);
+    p.fail();
+    let mul = p.code[p.code.len() - 2];
+
+    p.case(3);
+    p.csrr(10, MCAUSE);
+    p.expect(10, 2);
+    p.case(4);
+    p.csrr(10, MEPC);
+    p.expect(10, pc + 12);
+    p.case(5);
+    p.csrr(10, MTVAL);
+    p.expect(10, mul);
+    check("mul", &p.finish(), false);
+}
+
+// the official binaries, rv32ui with and without M
+#[test]
+#[ignore = "needs RISCV_TESTS set to the isa directory of a riscv-tests build"]
+fn riscv_tests() {
+    let dir = std::env::var_os("RISCV_TESTS").expect("RISCV_TESTS is not set");
+
+    let mut paths = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_none())
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut failed = Vec::new();
+    let mut ran = 0;
+    for path in paths {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let ms: &[bool] = if name.starts_with("rv32ui-p-") {
+            &[false, true]
+        } else if name.starts_with("rv32um-p-") {
+            &[true]
+        } else {
+            continue;
+        };
+
+        let elf = Elf::parse(&std::fs::read(&path).unwrap()).unwrap();
+        let address = elf.symbol("tohost").expect("no tohost symbol").addr();
+        for &m in ms {
+            let (mut s, cpu, text, data) = sim(m, &[]);
+            elf.load(&mut s, cpu, &[(text, 0x8000_0000)]).unwrap();
+            elf.load(&mut s, cpu, &[(data, 0x8000_0000)]).unwrap();
+            if tohost(&mut s, data, address) != 1 {
+                failed.push((name.clone(), m));
+            }
+            ran += 1;
+        }
+    }
+
+    assert!(ran > 0, "no rv32ui or rv32um binaries found");
+    assert!(failed.is_empty(), "failed: {:?}", failed);
+}
@@ -0,0 +1,97 @@
+// Runs of a register rotating a single set bit on every rising edge, reading
+// 1, 2, 4, 8 and 1 again after ticks 0, 1, 3, 5 and 7, and of one settling
+// on its first
+
+use sim_rs::{
+    components::{
+        logic::Not,
+        mem::{Register, Trigger},
+    },
+    Sim, StopReason,
+};
+
+const Q: [usize; 4] = [5, 6, 7, 8];
+
+// the Sim and the register, rotating or loading all ones
+fn register(rotating: bool) -> (Sim, usize) {
+    let mut s = Sim::new();
+    s.begin_build();
+
+    let high = s.add_component(Not);
+    let r = s.add_component(Register::new(4, Trigger::Rising, 1));
+    for i in 0..4 {
+        if rotating {
+            s.connect(r, (i + 1) % 4 + 1, r, Q[i]);
+        } else {
+            s.connect(r, i + 1, high, 2);
+        }
+    }
+    s.connect_to_clk(r, 9);
+    s.connect(r, 10, high, 2);
+
+    s.finish_build();
+    (s, r)
+}
+
+#[test]
+fn run() {
+    let (mut s, r) = register(true);
+
+    assert_eq!(s.run(5), StopReason::Done);
+    assert_eq!((s.ticks(), s.cycles()), (5, 2));
+    assert!(s.read(r, Q[3]));
+    assert_eq!(s.run(0), StopReason::Done);
+    assert_eq!(s.ticks(), 5);
+}
+
+#[test]
+fn run_until() {
+    let (mut s, r) = register(true);
+
+    // checked before every tick, so not ticking when it already holds
+    assert_eq!(s.run_until_pin(r, Q[0], true), StopReason::Condition);
+    assert_eq!(s.ticks(), 0);
+    assert_eq!(s.run_until_pin(r, Q[3], true), StopReason::Condition);
+    assert_eq!(s.ticks(), 5);
+    assert_eq!(s.run_until(|s| s.ticks() == 8), StopReason::Condition);
+    assert!(s.read(r, Q[0]));
+
+    // and once more after the last tick allowed
+    s.set_max_ticks(3);
+    assert_eq!(s.run_until_pin(r, Q[2], true), StopReason::Condition);
+    assert_eq!(s.ticks(), 11);
+    assert_eq!(s.run_until(|_| false), StopReason::Limit);
+    assert_eq!(s.ticks(), 14);
+}
+
+#[test]
+fn run_until_stable() {
+    // loading on tick 1, then two ticks writing nothing but the clock
+    let (mut s, r) = register(false);
+    assert_eq!(s.run_until_stable(), StopReason::Stable);
+    assert_eq!(s.ticks(), 3);
+    assert!(Q.iter().all(|&q| s.read(r, q)));
+
+    let (mut s, _) = register(true);
+    s.set_max_ticks(50);
+    assert_eq!(s.run_until_stable(), StopReason::Limit);
+    assert_eq!(s.ticks(), 50);
+}
+
+#[test]
+fn watchpoints_stop_every_run() {
+    let (mut s, r) = register(true);
+    let id = s.watch_equals(r, &Q, 4);
+
+    assert_eq!(s.run_until(|_| false), StopReason::Breakpoint);
+    assert_eq!(s.ticks(), 3);
+    assert_eq!(s.run_until_pin(r, Q[1], false), StopReason::Condition);
+    assert_eq!(s.last_hit(), None);
+
+    assert!(s.remove_watchpoint(id));
+    s.watch_change(r, &Q);
+    assert_eq!(s.run_until_stable(), StopReason::Breakpoint);
+    assert_eq!(s.last_hit().unwrap().tick(), 5);
+    assert_eq!(s.run(10), StopReason::Breakpoint);
+    assert_eq!(s.ticks(), 7);
+}
@@ -0,0 +1,133 @@
+// Independent regions of registers and gates sharing only the clock, run on
+// one thread and on several side by side and compared after every tick
+
+use sim_rs::{
+    components::{
+        logic::{And, Not, Or, Xor},
+        mem::{Register, Trigger},
+    },
+    Sim,
+};
+use std::{cell::RefCell, rc::Rc};
+
+const REGIONS: usize = 8;
+const REGISTERS: usize = 4;
+const GATES: usize = 20;
+const TICKS: usize = 200;
+
+// xorshift, so both Sims get the same netlist
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+// Adds a region of registers fed back through gates, each with constants of
+// its own so regions only meet at the clock, and returns every (component,
+// pin) to compare. With `local`, its first register is shared through an
+// Rc<RefCell<T>>, keeping the passes reaching it on the Sim's thread.
+fn region(s: &mut Sim, rng: &mut Rng, local: bool) -> Vec<(usize, usize)> {
+    let high = s.add_component(Not);
+    let low = s.add_component(Not);
+    s.connect(low, 1, high, 2);
+    let constants = [(high, 2), (low, 2)];
+
+    let registers = (0..REGISTERS)
+        .map(|i| {
+            let trigger = if i % 2 == 0 {
+                Trigger::Rising
+            } else {
+                Trigger::Falling
+            };
+            let register = Register::new(1, trigger, (i % 2) as u32);
+            let r = if local && i == 0 {
+                s.add_local_component(Rc::new(RefCell::new(register)))
+            } else {
+                s.add_component(register)
+            };
+            s.connect_to_clk(r, 3);
+            s.connect(r, 4, high, 2);
+            r
+        })
+        .collect::<Vec<_>>();
+
+    let mut outputs = registers.iter().map(|&r| (r, 2)).collect::<Vec<_>>();
+    for i in 0..GATES {
+        let g = match i % 4 {
+            0 => s.add_component(And),
+            1 => s.add_component(Or),
+            2 => s.add_component(Xor),
+            _ => s.add_component(Not),
+        };
+        let (src, out) = outputs[rng.below(outputs.len())];
+        s.connect(g, 1, src, out);
+
+        if i % 4 == 3 {
+            outputs.push((g, 2));
+        } else {
+            let (src, out) = if rng.below(2) == 0 {
+                outputs[rng.below(outputs.len())]
+            } else {
+                constants[rng.below(2)]
+            };
+            s.connect(g, 2, src, out);
+            outputs.push((g, 3));
+        }
+    }
+
+    for &r in &registers {
+        let (src, out) = outputs[rng.below(outputs.len())];
+        s.connect(r, 1, src, out);
+    }
+
+    outputs
+}
+
+fn netlist(seed: u64, local: bool) -> (Sim, Vec<(usize, usize)>) {
+    let mut rng = Rng(seed);
+    let mut s = Sim::new();
+    s.begin_build();
+
+    let outputs = (0..REGIONS)
+        .flat_map(|i| region(&mut s, &mut rng, local && i == REGIONS / 2))
+        .collect::<Vec<_>>();
+
+    s.finish_build();
+    (s, outputs)
+}
+
+fn compare(local: bool) {
+    for seed in 1..=4 {
+        let (mut single, outputs) = netlist(seed, local);
+        let (mut threaded, _) = netlist(seed, local);
+        threaded.set_threads(4);
+
+        for tick in 0..TICKS {
+            for &(c, pin) in &outputs {
+                assert_eq!(
+                    threaded.read(c, pin),
+                    single.read(c, pin),
+                    "seed {seed}, tick {tick}: pin {pin} of component {c}"
+                );
+            }
+
+            single.tick();
+            threaded.tick();
+        }
+    }
+}
+
+#[test]
+fn threaded_matches_single_threaded() {
+    compare(false);
+}
+
+#[test]
+fn local_component_matches_single_threaded() {
+    compare(true);
+}
@@ -0,0 +1,131 @@
+// A Timer driven through its registers by an Input standing in for the bus
+// master, a clock cycle at a time
+
+use sim_rs::{
+    components::{port::Input, timer::Timer},
+    Sim,
+};
+
+// registers and bits
+const COUNT: u32 = 0;
+const COMPARE: u32 = 1;
+const PRESCALER: u32 = 2;
+const CONTROL: u32 = 3;
+const STATUS: u32 = 4;
+
+const ENABLE: u32 = 1;
+const MATCH_INT: u32 = 1 << 1;
+const RESTART: u32 = 1 << 2;
+
+const IRQ: usize = 67;
+
+fn set(s: &mut Sim, c: usize, first: usize, width: usize, value: u32) {
+    for bit in 0..width {
+        s.write(c, first + bit, value >> bit & 1 != 0);
+    }
+}
+
+fn get(s: &Sim, c: usize, first: usize, width: usize) -> u32 {
+    (0..width).fold(0, |acc, bit| acc | u32::from(s.read(c, first + bit)) << bit)
+}
+
+// a register write, taking effect as the strobe falls
+fn write(s: &mut Sim, master: usize, reg: u32, value: u32) {
+    s.write(master, 65, true);
+    set(s, master, 1, 32, reg);
+    set(s, master, 33, 32, value);
+    s.write(master, 65, false);
+}
+
+fn read(s: &mut Sim, master: usize, reg: u32) -> u32 {
+    set(s, master, 1, 32, reg);
+    get(s, master, 33, 32)
+}
+
+fn cycles(s: &mut Sim, n: usize) {
+    for _ in 0..2 * n {
+        s.tick();
+    }
+}
+
+// the Sim, a Timer and its master
+fn system() -> (Sim, usize, usize) {
+    let mut s = Sim::new();
+    s.begin_build();
+
+    let c = s.add_component(Timer::new());
+    let m = s.add_component(Input::new(65));
+    for pin in 1..=65 {
+        s.connect(m, pin, c, pin);
+    }
+    s.connect_to_clk(c, 66);
+
+    s.finish_build();
+    (s, c, m)
+}
+
+#[test]
+fn prescaler() {
+    let (mut s, t, m) = system();
+    write(&mut s, m, PRESCALER, 2);
+    cycles(&mut s, 10);
+    assert_eq!(read(&mut s, m, COUNT), 0, "counting while disabled");
+
+    // once every 3 cycles
+    write(&mut s, m, CONTROL, ENABLE);
+    for n in 1..=4 {
+        cycles(&mut s, 2);
+        assert_eq!(read(&mut s, m, COUNT), n - 1);
+        cycles(&mut s, 1);
+        assert_eq!(read(&mut s, m, COUNT), n);
+    }
+
+    // writing the count restarts the prescaler
+    cycles(&mut s, 2);
+    write(&mut s, m, COUNT, 100);
+    cycles(&mut s, 2);
+    assert_eq!(s.component::<Timer>(t).unwrap().count(), 100);
+    cycles(&mut s, 1);
+    assert_eq!(s.component::<Timer>(t).unwrap().count(), 101);
+}
+
+#[test]
+fn compare_match() {
+    let (mut s, t, m) = system();
+    write(&mut s, m, COMPARE, 5);
+    write(&mut s, m, CONTROL, ENABLE);
+
+    cycles(&mut s, 4);
+    assert_eq!(read(&mut s, m, STATUS), 0);
+    cycles(&mut s, 1);
+    assert_eq!(read(&mut s, m, STATUS), 1);
+    // flagged without interrupting until asked to, and counting on
+    assert!(!s.read(t, IRQ));
+    write(&mut s, m, CONTROL, ENABLE | MATCH_INT);
+    assert!(s.read(t, IRQ));
+    cycles(&mut s, 2);
+    assert_eq!(read(&mut s, m, COUNT), 7);
+    assert!(s.read(t, IRQ));
+
+    write(&mut s, m, STATUS, 1);
+    assert!(!s.read(t, IRQ));
+    assert!(!s.component::<Timer>(t).unwrap().is_matched());
+}
+
+#[test]
+fn restart() {
+    let (mut s, t, m) = system();
+    write(&mut s, m, COMPARE, 3);
+    write(&mut s, m, PRESCALER, 1);
+    write(&mut s, m, CONTROL, ENABLE | MATCH_INT | RESTART);
+
+    // every compare * (prescaler + 1) cycles
+    for _ in 0..3 {
+        cycles(&mut s, 5);
+        assert!(!s.read(t, IRQ));
+        cycles(&mut s, 1);
+        assert!(s.read(t, IRQ));
+        assert_eq!(read(&mut s, m, COUNT), 0);
+        write(&mut s, m, STATUS, 1);
+    }
+}
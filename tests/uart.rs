@@ -0,0 +1,222 @@
+// A Uart driven through its registers by an Input standing in for the bus
+// master, its serial lines watched and driven a clock cycle at a time
+
+use sim_rs::{
+    components::{port::Input, uart::Uart},
+    Sim,
+};
+
+const DATA: u32 = 0;
+const STATUS: u32 = 1;
+const CONTROL: u32 = 3;
+
+const RX_READY: u32 = 1;
+const TX_READY: u32 = 1 << 1;
+const TX_IDLE: u32 = 1 << 2;
+const OVERRUN: u32 = 1 << 3;
+
+const RX_INT: u32 = 1;
+const TX_INT: u32 = 1 << 1;
+
+const TX: usize = 67;
+const RX: usize = 68;
+const IRQ: usize = 69;
+
+fn set(s: &mut Sim, c: usize, first: usize, width: usize, value: u32) {
+    for bit in 0..width {
+        s.write(c, first + bit, value >> bit & 1 != 0);
+    }
+}
+
+fn get(s: &Sim, c: usize, first: usize, width: usize) -> u32 {
+    (0..width).fold(0, |acc, bit| acc | u32::from(s.read(c, first + bit)) << bit)
+}
+
+// a register write, taking effect as the strobe falls
+fn write(s: &mut Sim, master: usize, reg: u32, value: u32) {
+    s.write(master, 65, true);
+    set(s, master, 1, 32, reg);
+    set(s, master, 33, 32, value);
+    s.write(master, 65, false);
+}
+
+fn read(s: &mut Sim, master: usize, reg: u32) -> u32 {
+    set(s, master, 1, 32, reg);
+    get(s, master, 33, 32)
+}
+
+fn cycles(s: &mut Sim, n: usize) {
+    for _ in 0..2 * n {
+        s.tick();
+    }
+}
+
+// Uarts of `divisor` cycles per bit with a master each, the rx line of the
+// first driven by an Input and that of every other by the tx line of the one
+// before. Returns the Sim, the Uarts, their masters and the rx Input.
+fn system(uarts: usize, divisor: u32) -> (Sim, Vec<usize>, Vec<usize>, usize) {
+    let mut s = Sim::new();
+    s.begin_build();
+
+    let line = s.add_component(Input::new(1));
+    let mut keys = Vec::new();
+    let mut masters = Vec::new();
+    for _ in 0..uarts {
+        let u = s.add_component(Uart::new(divisor));
+        let m = s.add_component(Input::new(65));
+        for pin in 1..=65 {
+            s.connect(m, pin, u, pin);
+        }
+        s.connect_to_clk(u, 66);
+        match keys.last() {
+            Some(&previous) => s.connect(previous, TX, u, RX),
+            None => s.connect(line, 1, u, RX),
+        }
+        keys.push(u);
+        masters.push(m);
+    }
+
+    s.finish_build();
+    // the rx lines high for a cycle, so the Uarts listen to them
+    s.write(line, 1, true);
+    cycles(&mut s, 1);
+    (s, keys, masters, line)
+}
+
+#[test]
+fn tx_framing() {
+    let (mut s, u, m, _) = system(1, 4);
+    assert_eq!(read(&mut s, m[0], STATUS), TX_READY | TX_IDLE);
+
+    write(&mut s, m[0], DATA, 0x5a);
+    assert_eq!(read(&mut s, m[0], STATUS) & TX_IDLE, 0);
+
+    // a start bit, the data LSB first and a stop bit, a bit every 4 cycles
+    let mut bits = vec![false];
+    bits.extend((0..8).map(|i| 0x5a >> i & 1 != 0));
+    bits.push(true);
+    for (i, &bit) in bits.iter().enumerate() {
+        for cycle in 0..4 {
+            cycles(&mut s, 1);
+            assert_eq!(s.read(u[0], TX), bit, "bit {i}, cycle {cycle}");
+        }
+    }
+
+    cycles(&mut s, 1);
+    assert!(s.read(u[0], TX));
+    assert_eq!(read(&mut s, m[0], STATUS), TX_READY | TX_IDLE);
+    let output = s.component_mut::<Uart>(u[0]).unwrap().take_output();
+    assert_eq!(output, [0x5a]);
+}
+
+#[test]
+fn tx_fifo() {
+    let (mut s, u, m, _) = system(1, 1);
+
+    // 16 bytes fit, the 17th is dropped
+    for byte in 0..17 {
+        write(&mut s, m[0], DATA, byte);
+    }
+    assert_eq!(read(&mut s, m[0], STATUS) & TX_READY, 0);
+
+    cycles(&mut s, 17 * 10);
+    let output = s.component_mut::<Uart>(u[0]).unwrap().take_output();
+    assert_eq!(output, (0..16).collect::<Vec<_>>());
+}
+
+// drives the rx line of the first Uart with a frame, `stop` being the level
+// of its stop bit, `divisor` cycles a bit
+fn send(s: &mut Sim, line: usize, byte: u8, stop: bool, divisor: usize) {
+    let bits = std::iter::once(false)
+        .chain((0..8).map(|i| byte >> i & 1 != 0))
+        .chain(std::iter::once(stop));
+    for bit in bits {
+        s.write(line, 1, bit);
+        cycles(s, divisor);
+    }
+    s.write(line, 1, true);
+    cycles(s, divisor);
+}
+
+#[test]
+fn rx_sampling() {
+    let (mut s, _, m, line) = system(1, 8);
+
+    send(&mut s, line, 0xa5, true, 8);
+    assert_eq!(read(&mut s, m[0], STATUS) & RX_READY, RX_READY);
+    // reads leave the byte there until it is taken
+    assert_eq!(read(&mut s, m[0], DATA), 0xa5);
+    assert_eq!(read(&mut s, m[0], DATA), 0xa5);
+    write(&mut s, m[0], STATUS, RX_READY);
+    assert_eq!(read(&mut s, m[0], STATUS) & RX_READY, 0);
+    assert_eq!(read(&mut s, m[0], DATA), 0);
+
+    // no stop bit
+    send(&mut s, line, 0x3c, false, 8);
+    assert_eq!(read(&mut s, m[0], STATUS) & RX_READY, 0);
+
+    // low for less than half a bit is a glitch rather than a start bit
+    s.write(line, 1, false);
+    cycles(&mut s, 3);
+    s.write(line, 1, true);
+    cycles(&mut s, 20);
+    assert_eq!(read(&mut s, m[0], STATUS) & RX_READY, 0);
+
+    send(&mut s, line, 0x81, true, 8);
+    assert_eq!(read(&mut s, m[0], DATA), 0x81);
+}
+
+#[test]
+fn overrun() {
+    let (mut s, _, m, _) = system(2, 2);
+
+    // the second Uart receives what the first sends, 17 bytes for a 16 byte FIFO
+    for byte in 0..16 {
+        write(&mut s, m[0], DATA, byte);
+    }
+    cycles(&mut s, 30);
+    write(&mut s, m[0], DATA, 16);
+    cycles(&mut s, 17 * 20 + 20);
+
+    assert_eq!(
+        read(&mut s, m[1], STATUS),
+        RX_READY | TX_READY | TX_IDLE | OVERRUN
+    );
+    for byte in 0..16 {
+        assert_eq!(read(&mut s, m[1], DATA), byte);
+        write(&mut s, m[1], STATUS, RX_READY);
+    }
+    assert_eq!(read(&mut s, m[1], STATUS), TX_READY | TX_IDLE | OVERRUN);
+    write(&mut s, m[1], STATUS, OVERRUN);
+    assert_eq!(read(&mut s, m[1], STATUS), TX_READY | TX_IDLE);
+}
+
+#[test]
+fn irq() {
+    let (mut s, u, m, _) = system(2, 2);
+
+    // while there is nothing left to transmit
+    assert!(!s.read(u[0], IRQ));
+    write(&mut s, m[0], CONTROL, TX_INT);
+    assert!(s.read(u[0], IRQ));
+    write(&mut s, m[0], DATA, 0x42);
+    assert!(!s.read(u[0], IRQ));
+    cycles(&mut s, 10 * 2);
+    assert!(!s.read(u[0], IRQ));
+    cycles(&mut s, 1);
+    assert!(s.read(u[0], IRQ));
+
+    // while a byte is received
+    write(&mut s, m[1], CONTROL, RX_INT);
+    assert!(s.read(u[1], IRQ));
+    write(&mut s, m[1], STATUS, RX_READY);
+    assert!(!s.read(u[1], IRQ));
+    // and only once enabled
+    write(&mut s, m[1], CONTROL, 0);
+    write(&mut s, m[0], DATA, 0x43);
+    cycles(&mut s, 10 * 2 + 1);
+    assert!(!s.read(u[1], IRQ));
+    write(&mut s, m[1], CONTROL, RX_INT | TX_INT);
+    assert_eq!(read(&mut s, m[1], CONTROL), RX_INT | TX_INT);
+    assert!(s.read(u[1], IRQ));
+}
@@ -0,0 +1,251 @@
+// Structural Verilog imported and checked against the same logic in Rust,
+// including through an export and import again, and the stubs behavioural
+// components are exported as
+
+use sim_rs::{
+    components::mem::{Register, Trigger},
+    import::{verilog, Ports},
+    Sim,
+};
+
+const EXPRESSIONS: &str = "
+module top (a, b, c, y, z, w);
+  input [3:0] a, b;
+  input c;
+  output [3:0] y;
+  output z;
+  output [7:0] w;
+  assign y = (a & b) | ~(a ^ {4{c}});
+  assign z = a[3] ^ b[0];
+  assign w = {a[1:0], {2{c}}, b};
+endmodule
+";
+
+const ADDER: &str = "
+module half (x, y, s, c);
+  input x, y;
+  output s, c;
+  xor (s, x, y);
+  and (c, x, y);
+endmodule
+
+module full (a, b, cin, sum, cout);
+  input a, b, cin;
+  output sum, cout;
+  wire s1, c1, c2;
+  half h0 (.x(a), .y(b), .s(s1), .c(c1));
+  half h1 (s1, cin, sum, c2);
+  or (cout, c1, c2);
+endmodule
+
+module add4 (a, b, cin, sum, cout);
+  input [3:0] a, b;
+  input cin;
+  output [3:0] sum;
+  output cout;
+  wire [2:0] c;
+  full f0 (a[0], b[0], cin, sum[0], c[0]);
+  full f1 (a[1], b[1], c[0], sum[1], c[1]);
+  full f2 (a[2], b[2], c[1], sum[2], c[2]);
+  full f3 (a[3], b[3], c[2], sum[3], cout);
+endmodule
+";
+
+fn load(source: &str, top: Option<&str>) -> (Sim, Ports) {
+    let mut s = Sim::new();
+    let ports = verilog::load(&mut s, source, top).unwrap();
+    (s, ports)
+}
+
+fn set(s: &mut Sim, ports: &Ports, name: &str, width: usize, value: u32) {
+    for bit in 0..width {
+        s.write(ports[name], bit + 1, value >> bit & 1 != 0);
+    }
+}
+
+fn get(s: &Sim, ports: &Ports, name: &str, width: usize) -> u32 {
+    (0..width).fold(0, |acc, bit| {
+        acc | u32::from(s.read(ports[name], bit + 1)) << bit
+    })
+}
+
+// the line of the error loading `source`
+fn error_line(source: &str, top: Option<&str>) -> usize {
+    verilog::load(&mut Sim::new(), source, top)
+        .unwrap_err()
+        .line()
+}
+
+#[test]
+fn expressions() {
+    let (mut s, ports) = load(EXPRESSIONS, None);
+
+    for a in 0..16 {
+        for b in 0..16 {
+            for c in 0..2 {
+                set(&mut s, &ports, "a", 4, a);
+                set(&mut s, &ports, "b", 4, b);
+                set(&mut s, &ports, "c", 1, c);
+
+                let cs = if c == 1 { 0xf } else { 0 };
+                let inputs = format!("a {a}, b {b}, c {c}");
+                assert_eq!(
+                    get(&s, &ports, "y", 4),
+                    (a & b | !(a ^ cs)) & 0xf,
+                    "{inputs}"
+                );
+                assert_eq!(get(&s, &ports, "z", 1), (a >> 3 ^ b) & 1, "{inputs}");
+                assert_eq!(
+                    get(&s, &ports, "w", 8),
+                    (a & 3) << 6 | c << 5 | c << 4 | b,
+                    "{inputs}"
+                );
+            }
+        }
+    }
+}
+
+fn check_adder(s: &mut Sim, ports: &Ports) {
+    for a in 0..16 {
+        for b in 0..16 {
+            for cin in 0..2 {
+                set(s, ports, "a", 4, a);
+                set(s, ports, "b", 4, b);
+                set(s, ports, "cin", 1, cin);
+
+                let sum = get(s, ports, "sum", 4) | get(s, ports, "cout", 1) << 4;
+                assert_eq!(sum, a + b + cin, "{a} + {b} + {cin}");
+            }
+        }
+    }
+}
+
+#[test]
+fn hierarchy() {
+    let (mut s, ports) = load(ADDER, None);
+    check_adder(&mut s, &ports);
+
+    // a module of the hierarchy can be the top one too
+    let (mut s, ports) = load(ADDER, Some("full"));
+    for i in 0..8 {
+        set(&mut s, &ports, "a", 1, i & 1);
+        set(&mut s, &ports, "b", 1, i >> 1 & 1);
+        set(&mut s, &ports, "cin", 1, i >> 2);
+
+        let sum = get(&s, &ports, "sum", 1) | get(&s, &ports, "cout", 1) << 1;
+        assert_eq!(sum, (i & 1) + (i >> 1 & 1) + (i >> 2), "inputs {i:03b}");
+    }
+}
+
+#[test]
+fn export_round_trip() {
+    let (s, _) = load(ADDER, None);
+    let exported = s.to_verilog("add4");
+
+    let (mut s, ports) = load(&exported, Some("add4"));
+    check_adder(&mut s, &ports);
+}
+
+#[test]
+fn export_stubs() {
+    // alternating widths of the same component
+    let mut s = Sim::new();
+    for &width in &[4, 8, 4, 8, 1] {
+        s.add_component(Register::new(width, Trigger::Rising, 0));
+    }
+    let exported = s.to_verilog("top");
+    assert_eq!(exported, s.to_verilog("top"));
+
+    // a stub for each width, declaring its d bus
+    let stubs = exported
+        .split("endmodule")
+        .filter(|m| m.contains("inout"))
+        .map(|m| {
+            let name = m.split_whitespace().nth(1).unwrap();
+            let d = m.lines().find(|l| l.trim().ends_with(" d;")).unwrap();
+            (name, d.trim())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        stubs,
+        [
+            ("Register", "inout [3:0] d;"),
+            ("Register_1", "inout [7:0] d;"),
+            ("Register_2", "inout [0:0] d;"),
+        ]
+    );
+
+    // and every instance connected as its stub declares
+    let instances = exported
+        .lines()
+        .filter(|l| l.contains(".d("))
+        .map(|l| {
+            let module = l.split_whitespace().next().unwrap();
+            let d = &l[l.find(".d(").unwrap()..];
+            let bits = d[..d.find(')').unwrap()].split(',').count();
+            (module, bits)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        instances,
+        [
+            ("Register", 4),
+            ("Register_1", 8),
+            ("Register", 4),
+            ("Register_1", 8),
+            ("Register_2", 1),
+        ]
+    );
+}
+
+#[test]
+fn error_lines() {
+    // syntax
+    assert_eq!(error_line("module m (a);\n  input a\nendmodule\n", None), 3);
+    // unsupported constructs
+    assert_eq!(
+        error_line("module m (a);\n  input a;\n  reg r;\nendmodule\n", None),
+        3
+    );
+    // unknown instance
+    assert_eq!(
+        error_line("module m (a);\n  input a;\n  n i (a);\nendmodule\n", None),
+        3
+    );
+
+    // an unknown top module points at the end of the source
+    assert_eq!(error_line(ADDER, Some("add8")), ADDER.lines().count());
+    // two candidates for the top module point at the second one
+    assert_eq!(
+        error_line(
+            "module a (x);\n  input x;\nendmodule\nmodule b (x);\n  input x;\nendmodule\n",
+            None
+        ),
+        4
+    );
+    // as do modules instantiating each other at the first
+    assert_eq!(
+        error_line(
+            "\nmodule a (x);\n  input x;\n  b i (x);\nendmodule\nmodule b (x);\n  input x;\n  a i (x);\nendmodule\n",
+            None
+        ),
+        2
+    );
+    assert_eq!(error_line("", None), 1);
+}
+
+#[test]
+fn replication_width() {
+    let wide = |e: &str| {
+        format!("module m (a, y);\n  input a;\n  output y;\n  assign y = {e};\nendmodule\n")
+    };
+
+    // the count alone, while parsing, then the count times the width of what
+    // is replicated
+    for e in &["{70000{a}}", "{300{ {300{a}} }}"] {
+        let error = verilog::load(&mut Sim::new(), &wide(e), None).unwrap_err();
+        assert_eq!(error.line(), 4, "{e}");
+        assert!(error.message().contains("replication"), "{}: {}", e, error);
+    }
+    assert!(verilog::load(&mut Sim::new(), &wide("{3{a}}"), None).is_ok());
+}
@@ -0,0 +1,117 @@
+// Watchpoints on a register rotating a single set bit on every rising edge,
+// reading 1, 2, 4, 8 and 1 again after ticks 0, 1, 3, 5 and 7
+
+use sim_rs::{
+    components::{
+        logic::Not,
+        mem::{Register, Trigger},
+        port::Input,
+    },
+    Sim, StopReason,
+};
+
+const Q: [usize; 4] = [5, 6, 7, 8];
+
+// the Sim and the register
+fn rotator() -> (Sim, usize) {
+    let mut s = Sim::new();
+    s.begin_build();
+
+    let high = s.add_component(Not);
+    let r = s.add_component(Register::new(4, Trigger::Rising, 1));
+    for i in 0..4 {
+        s.connect(r, (i + 1) % 4 + 1, r, Q[i]);
+    }
+    s.connect_to_clk(r, 9);
+    s.connect(r, 10, high, 2);
+
+    s.finish_build();
+    (s, r)
+}
+
+fn q(s: &Sim, r: usize) -> u32 {
+    (0..4).fold(0, |acc, i| acc | u32::from(s.read(r, Q[i])) << i)
+}
+
+#[test]
+fn conditions() {
+    // (fired by, first tick it fires on)
+    let cases: &[(fn(&mut Sim, usize) -> usize, usize)] = &[
+        (|s, r| s.watch_equals(r, &Q, 8), 5),
+        (|s, r| s.watch_change(r, &Q), 1),
+        (|s, r| s.watch_rising(r, &Q[3..]), 5),
+        (|s, r| s.watch_falling(r, &Q[..1]), 1),
+        (|s, r| s.watch_range(r, &Q, 3, 6), 3),
+    ];
+
+    for (i, &(watch, tick)) in cases.iter().enumerate() {
+        let (mut s, r) = rotator();
+        let id = watch(&mut s, r);
+
+        assert_eq!(s.run(100), StopReason::Breakpoint, "case {}", i);
+        let hit = s.last_hit().unwrap();
+        assert_eq!((hit.watchpoint(), hit.tick()), (id, tick), "case {}", i);
+        assert_eq!(s.ticks(), tick, "case {}", i);
+    }
+}
+
+#[test]
+fn fires_when_becoming_true() {
+    let (mut s, r) = rotator();
+    s.watch_equals(r, &Q, 8);
+
+    assert_eq!(s.run(100), StopReason::Breakpoint);
+    assert_eq!(q(&s, r), 8);
+    // still 8 after tick 6, so only once the bit has come round again
+    assert_eq!(s.run(6), StopReason::Done);
+    assert_eq!(s.last_hit(), None);
+    assert_eq!(s.run(100), StopReason::Breakpoint);
+    assert_eq!(s.last_hit().unwrap().tick(), 13);
+}
+
+#[test]
+fn first_watchpoint_wins() {
+    let (mut s, r) = rotator();
+    // both fire on tick 1
+    let change = s.watch_change(r, &Q);
+    let equals = s.watch_equals(r, &Q, 2);
+
+    assert_eq!(s.run(100), StopReason::Breakpoint);
+    assert_eq!(s.last_hit().unwrap().watchpoint(), change);
+
+    // the id is reused, and the other still fires on its own
+    assert!(s.remove_watchpoint(change));
+    let eight = s.watch_equals(r, &Q, 8);
+    assert_eq!(eight, change);
+    assert_eq!(s.run(100), StopReason::Breakpoint);
+    assert_eq!(s.last_hit().unwrap().watchpoint(), eight);
+    assert_eq!(s.run(100), StopReason::Breakpoint);
+    assert_eq!(s.last_hit().unwrap().watchpoint(), equals);
+    assert_eq!(s.ticks(), 9);
+}
+
+#[test]
+fn remove() {
+    let (mut s, r) = rotator();
+    let id = s.watch_change(r, &Q);
+
+    assert!(s.remove_watchpoint(id));
+    assert!(!s.remove_watchpoint(id));
+    assert!(!s.remove_watchpoint(id + 1));
+    assert_eq!(s.run(20), StopReason::Done);
+    assert_eq!(s.last_hit(), None);
+}
+
+#[test]
+fn writes_fire_on_the_next_tick() {
+    let mut s = Sim::new();
+    let input = s.add_component(Input::new(2));
+    let id = s.watch_equals(input, &[1, 2], 3);
+
+    s.write(input, 1, true);
+    s.write(input, 2, true);
+    assert_eq!(s.last_hit(), None);
+    assert_eq!(s.run(5), StopReason::Breakpoint);
+    let hit = s.last_hit().unwrap();
+    assert_eq!((hit.watchpoint(), hit.tick()), (id, 1));
+}